@@ -0,0 +1,2 @@
+-- There can only ever be one system user. This guarantees that concurrent setups can't both succeed.
+CREATE UNIQUE INDEX users_unique_system_role ON users (role) WHERE role = 0;
@@ -0,0 +1,24 @@
+-- Usernames that only differ in case can't be told apart anymore, so they have to be renamed by hand before
+-- this migration can be applied. Fail with a list of them instead of the bare unique violation of the index.
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(usernames, '; ')
+    INTO duplicates
+    FROM (
+        SELECT string_agg(username, ', ' ORDER BY username) AS usernames
+        FROM users
+        GROUP BY lower(username)
+        HAVING count(*) > 1
+    ) AS duplicate_groups;
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION 'Usernames have to be unique regardless of their case, but these usernames only differ in case: %', duplicates
+            USING HINT = 'Rename all but one user of every group, for example with UPDATE users SET username = ''new name'' WHERE username = ''old name'', and start Rasopus again.';
+    END IF;
+END
+$$;
+
+-- Usernames are unique, regardless of their case
+CREATE UNIQUE INDEX users_unique_username ON users (lower(username));
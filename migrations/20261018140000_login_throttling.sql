@@ -0,0 +1,21 @@
+CREATE TABLE login_attempts (
+    uuid UUID PRIMARY KEY,
+    username VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(45),
+    succeeded BOOLEAN NOT NULL,
+    attempted_at BIGINT NOT NULL
+);
+
+CREATE INDEX login_attempts_username_attempted_at ON login_attempts (username, attempted_at);
+CREATE INDEX login_attempts_ip_address_attempted_at ON login_attempts (ip_address, attempted_at);
+
+CREATE TABLE account_lockouts (
+    uuid UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    locked_at BIGINT NOT NULL,
+    locked_until BIGINT NOT NULL,
+    unlocked_at BIGINT,
+    unlocked_by UUID REFERENCES users (uuid) ON DELETE SET NULL
+);
+
+CREATE INDEX account_lockouts_user_uuid_locked_at ON account_lockouts (user_uuid, locked_at);
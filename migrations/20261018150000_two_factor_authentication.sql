@@ -0,0 +1,17 @@
+CREATE TABLE user_totps (
+    user_uuid UUID PRIMARY KEY REFERENCES users (uuid) ON DELETE CASCADE,
+    encrypted_secret BYTEA NOT NULL,
+    confirmed BOOLEAN NOT NULL,
+    last_used_step BIGINT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE recovery_codes (
+    uuid UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    code_hash VARCHAR(255) NOT NULL,
+    used_at BIGINT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX recovery_codes_user_uuid_code_hash ON recovery_codes (user_uuid, code_hash);
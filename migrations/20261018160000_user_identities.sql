@@ -0,0 +1,10 @@
+CREATE TABLE user_identities (
+    uuid UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    issuer VARCHAR(255) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE UNIQUE INDEX user_identities_unique_subject ON user_identities (issuer, subject);
+CREATE INDEX user_identities_user_uuid ON user_identities (user_uuid);
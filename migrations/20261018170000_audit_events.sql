@@ -0,0 +1,17 @@
+-- Audit events outlive the users they refer to, so actor_uuid and target_uuid are no foreign keys
+CREATE TABLE audit_events (
+    uuid UUID PRIMARY KEY,
+    action VARCHAR(64) NOT NULL,
+    actor_uuid UUID,
+    target_type VARCHAR(64),
+    target_uuid UUID,
+    before JSONB,
+    after JSONB,
+    ip_address VARCHAR(45),
+    occurred_at BIGINT NOT NULL
+);
+
+CREATE INDEX audit_events_occurred_at ON audit_events (occurred_at, uuid);
+CREATE INDEX audit_events_action_occurred_at ON audit_events (action, occurred_at);
+CREATE INDEX audit_events_actor_uuid_occurred_at ON audit_events (actor_uuid, occurred_at);
+CREATE INDEX audit_events_target_occurred_at ON audit_events (target_type, target_uuid, occurred_at);
@@ -0,0 +1,3 @@
+-- Users are listed page by page, continuing after the sort value and UUID of the last user of the previous page
+CREATE INDEX users_created_at ON users (created_at, uuid);
+CREATE INDEX users_username ON users (username, uuid);
@@ -0,0 +1,63 @@
+-- Timestamps are stored as TIMESTAMPTZ instead of seconds since the epoch, so they keep sub-second precision
+-- and can be used with Postgres' date functions. Existing timestamps are converted without losing data.
+ALTER TABLE users
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING to_timestamp(created_at);
+
+ALTER TABLE login_attempts
+    ALTER COLUMN attempted_at TYPE TIMESTAMPTZ USING to_timestamp(attempted_at);
+
+ALTER TABLE account_lockouts
+    ALTER COLUMN locked_at TYPE TIMESTAMPTZ USING to_timestamp(locked_at),
+    ALTER COLUMN locked_until TYPE TIMESTAMPTZ USING to_timestamp(locked_until),
+    ALTER COLUMN unlocked_at TYPE TIMESTAMPTZ USING to_timestamp(unlocked_at);
+
+ALTER TABLE user_totps
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING to_timestamp(created_at);
+
+ALTER TABLE recovery_codes
+    ALTER COLUMN used_at TYPE TIMESTAMPTZ USING to_timestamp(used_at),
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING to_timestamp(created_at);
+
+ALTER TABLE user_identities
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING to_timestamp(created_at);
+
+ALTER TABLE audit_events
+    ALTER COLUMN occurred_at TYPE TIMESTAMPTZ USING to_timestamp(occurred_at);
+
+-- Keeps the updated_at column of a row current. Rows that an update leaves unchanged keep their updated_at.
+CREATE FUNCTION set_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW IS DISTINCT FROM OLD THEN
+        NEW.updated_at = now();
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Every table whose rows can change has an updated_at column maintained by set_updated_at.
+-- login_attempts and audit_events are append-only logs, so they have none.
+ALTER TABLE users ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+UPDATE users SET updated_at = created_at;
+CREATE TRIGGER users_set_updated_at BEFORE UPDATE ON users
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+ALTER TABLE account_lockouts ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+UPDATE account_lockouts SET updated_at = COALESCE(unlocked_at, locked_at);
+CREATE TRIGGER account_lockouts_set_updated_at BEFORE UPDATE ON account_lockouts
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+ALTER TABLE user_totps ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+UPDATE user_totps SET updated_at = created_at;
+CREATE TRIGGER user_totps_set_updated_at BEFORE UPDATE ON user_totps
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+ALTER TABLE recovery_codes ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+UPDATE recovery_codes SET updated_at = COALESCE(used_at, created_at);
+CREATE TRIGGER recovery_codes_set_updated_at BEFORE UPDATE ON recovery_codes
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+ALTER TABLE user_identities ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+UPDATE user_identities SET updated_at = created_at;
+CREATE TRIGGER user_identities_set_updated_at BEFORE UPDATE ON user_identities
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
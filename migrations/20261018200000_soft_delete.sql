@@ -0,0 +1,5 @@
+-- Deleted users are moved to the trash by setting deleted_at, and purged once the trash retention is over.
+-- Until then their rows keep their usernames reserved, as users_unique_username covers them as well.
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX users_deleted_at ON users (deleted_at) WHERE deleted_at IS NOT NULL;
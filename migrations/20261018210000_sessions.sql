@@ -0,0 +1,11 @@
+-- The session cookie only holds the UUID of a session, so sessions can expire and be revoked on the server
+CREATE TABLE sessions (
+    uuid UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX sessions_user_uuid ON sessions (user_uuid);
+CREATE INDEX sessions_expires_at ON sessions (expires_at);
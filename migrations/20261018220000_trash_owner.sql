@@ -0,0 +1,5 @@
+-- Who moved a user to the trash, so everyone can list what they deleted themselves.
+-- The reference is deferrable, so restoring a backup can insert users regardless of who deleted whom.
+ALTER TABLE users ADD COLUMN deleted_by UUID REFERENCES users (uuid) ON DELETE SET NULL DEFERRABLE;
+
+CREATE INDEX users_deleted_by ON users (deleted_by) WHERE deleted_by IS NOT NULL;
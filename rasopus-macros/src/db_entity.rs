@@ -0,0 +1,741 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Field, Fields, Ident, LitStr, Type};
+
+struct Column<'a> {
+    ident: &'a Ident,
+    name: String,
+}
+
+/// What the struct is marked with `#[db(...)]`.
+struct Table {
+    name: LitStr,
+    soft_delete: bool,
+}
+
+/// The column soft-deleted entities hold the time of their deletion in.
+///
+/// This is the only definition of it. The rest of the code gets it from `DbEntityTrash::DELETED_AT_COLUMN`.
+const DELETED_AT_COLUMN: &str = "deleted_at";
+
+/// What a field is marked as with `#[db(...)]`.
+#[derive(PartialEq, Eq)]
+enum FieldKind {
+    Column,
+    Id,
+    Generated,
+}
+
+pub fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let table = parse_table(&input)?;
+    let table_name = table.name.value();
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "DbEntity can only be derived for structs",
+        ));
+    };
+
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "DbEntity can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut id: Option<(Column, &Type)> = None;
+    let mut columns = Vec::new();
+    let mut has_deleted_at = false;
+    for field in &fields.named {
+        let column = Column::from(field);
+        // The deletion time is only ever set by delete and restore
+        if table.soft_delete && column.name == DELETED_AT_COLUMN {
+            has_deleted_at = true;
+            continue;
+        }
+
+        match field_kind(field)? {
+            FieldKind::Column => {
+                columns.push(column);
+                continue;
+            }
+            // Generated columns are maintained by the database, so they are loaded but never written
+            FieldKind::Generated => continue,
+            FieldKind::Id => {}
+        }
+
+        if id.is_some() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "Only one field can be marked with #[db(id)]",
+            ));
+        }
+        id = Some((column, &field.ty));
+    }
+
+    let Some((id, id_type)) = id else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "One field has to be marked with #[db(id)]",
+        ));
+    };
+
+    if table.soft_delete && !has_deleted_at {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Soft-deleted entities need a `deleted_at` field",
+        ));
+    }
+
+    // An update would have nothing to set
+    if columns.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "At least one field besides the #[db(id)] field has to be a column",
+        ));
+    }
+
+    // The identifiers are quoted, so names that are keywords in SQL can be used as well
+    let table_name = quote_identifier(&table_name);
+    let id_name = quote_identifier(&id.name);
+    let column_names = columns
+        .iter()
+        .map(|column| quote_identifier(&column.name))
+        .collect::<Vec<_>>();
+    let deleted_at = quote_identifier(DELETED_AT_COLUMN);
+
+    // Soft-deleted entities are left out of everything but the trash operations
+    let not_deleted = if table.soft_delete {
+        format!(" AND {} IS NULL", deleted_at)
+    } else {
+        String::new()
+    };
+
+    let select_query = format!(
+        "SELECT * FROM {} WHERE {} = $1{} LIMIT 1",
+        table_name, id_name, not_deleted
+    );
+    let purge_query = format!("DELETE FROM {} WHERE {} = $1", table_name, id_name);
+    let delete_query = if table.soft_delete {
+        format!(
+            "UPDATE {} SET {} = now() WHERE {} = $1{}",
+            table_name, deleted_at, id_name, not_deleted
+        )
+    } else {
+        purge_query.clone()
+    };
+
+    let insert_columns = std::iter::once(&id_name)
+        .chain(&column_names)
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let insert_placeholders = (1..=insert_columns.len())
+        .map(|index| format!("${}", index))
+        .collect::<Vec<_>>();
+    let insert_query = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table_name,
+        insert_columns.join(", "),
+        insert_placeholders.join(", ")
+    );
+
+    let update_assignments = column_names
+        .iter()
+        .enumerate()
+        .map(|(index, column_name)| format!("{} = ${}", column_name, index + 1))
+        .collect::<Vec<_>>();
+    let update_query = format!(
+        "UPDATE {} SET {} WHERE {} = ${}{}",
+        table_name,
+        update_assignments.join(", "),
+        id_name,
+        columns.len() + 1,
+        not_deleted
+    );
+
+    let upsert_assignments = column_names
+        .iter()
+        .map(|column_name| format!("{} = EXCLUDED.{}", column_name, column_name))
+        .collect::<Vec<_>>();
+    // Entities in the trash are left alone, so the upsert returns no row for them
+    let upsert_condition = if table.soft_delete {
+        format!(" WHERE {}.{} IS NULL", table_name, deleted_at)
+    } else {
+        String::new()
+    };
+    let upsert_query = format!(
+        "{} ON CONFLICT ({}) DO UPDATE SET {}{} RETURNING (xmax = 0) AS inserted",
+        insert_query,
+        id_name,
+        upsert_assignments.join(", "),
+        upsert_condition
+    );
+
+    let name = &input.ident;
+    let id_ident = id.ident;
+    let column_idents = columns
+        .iter()
+        .map(|column| column.ident)
+        .collect::<Vec<_>>();
+
+    let trash_impl = if table.soft_delete {
+        let load_deleted_query = format!(
+            "SELECT * FROM {} WHERE {} = $1 AND {} IS NOT NULL LIMIT 1",
+            table_name, id_name, deleted_at
+        );
+        let restore_query = format!(
+            "UPDATE {} SET {} = NULL WHERE {} = $1 AND {} IS NOT NULL",
+            table_name, deleted_at, id_name, deleted_at
+        );
+        let purge_deleted_before_query = format!(
+            "DELETE FROM {} WHERE {} < $1 RETURNING *",
+            table_name, deleted_at
+        );
+
+        quote! {
+            #[::rocket::async_trait]
+            impl crate::model::trash::DbEntityTrash for #name {
+                const DELETED_AT_COLUMN: &'static str = #DELETED_AT_COLUMN;
+
+                async fn load_deleted<'e, E>(
+                    identifier: &Self::Identifier,
+                    executor: E,
+                ) -> Result<Self, ::sqlx::Error>
+                where
+                    E: ::sqlx::PgExecutor<'e>,
+                {
+                    let entity = ::sqlx::query_as(#load_deleted_query)
+                        .bind(identifier)
+                        .fetch_one(executor)
+                        .await?;
+
+                    Ok(entity)
+                }
+
+                async fn restore<'e, E>(
+                    identifier: &Self::Identifier,
+                    executor: E,
+                ) -> Result<bool, ::sqlx::Error>
+                where
+                    E: ::sqlx::PgExecutor<'e>,
+                {
+                    let result = ::sqlx::query(#restore_query)
+                        .bind(identifier)
+                        .execute(executor)
+                        .await?;
+
+                    Ok(result.rows_affected() > 0)
+                }
+
+                async fn purge<'e, E>(
+                    identifier: &Self::Identifier,
+                    executor: E,
+                ) -> Result<bool, ::sqlx::Error>
+                where
+                    E: ::sqlx::PgExecutor<'e>,
+                {
+                    let result = ::sqlx::query(#purge_query)
+                        .bind(identifier)
+                        .execute(executor)
+                        .await?;
+
+                    Ok(result.rows_affected() > 0)
+                }
+
+                async fn purge_deleted_before<'e, E>(
+                    deleted_before: ::chrono::DateTime<::chrono::Utc>,
+                    executor: E,
+                ) -> Result<Vec<Self>, ::sqlx::Error>
+                where
+                    E: ::sqlx::PgExecutor<'e>,
+                {
+                    let entities = ::sqlx::query_as(#purge_deleted_before_query)
+                        .bind(deleted_before)
+                        .fetch_all(executor)
+                        .await?;
+
+                    Ok(entities)
+                }
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
+
+    let table = table.name;
+    Ok(quote! {
+        #trash_impl
+
+        #[::rocket::async_trait]
+        impl crate::model::DbEntity for #name {
+            type Identifier = #id_type;
+            type ExistsError = ::sqlx::Error;
+            type CreateError = crate::model::PersistError;
+            type LoadError = ::sqlx::Error;
+            type UpdateError = crate::model::PersistError;
+            type DeleteError = ::sqlx::Error;
+            type PersistError = crate::model::PersistError;
+
+            fn main_table_name() -> &'static str {
+                #table
+            }
+
+            fn get_identifier(&self) -> &Self::Identifier {
+                &self.#id_ident
+            }
+
+            async fn exists<'e, E>(
+                identifier: &Self::Identifier,
+                executor: E,
+            ) -> Result<bool, Self::ExistsError>
+            where
+                E: ::sqlx::PgExecutor<'e>,
+            {
+                let result = ::sqlx::query(#select_query)
+                    .bind(identifier)
+                    .fetch_optional(executor)
+                    .await?;
+
+                Ok(result.is_some())
+            }
+
+            async fn create<'e, E>(&self, executor: E) -> Result<(), Self::CreateError>
+            where
+                E: ::sqlx::PgExecutor<'e>,
+            {
+                ::sqlx::query(#insert_query)
+                    .bind(&self.#id_ident)
+                    #(.bind(&self.#column_idents))*
+                    .execute(executor)
+                    .await?;
+
+                Ok(())
+            }
+
+            async fn load<'e, E>(
+                identifier: &Self::Identifier,
+                executor: E,
+            ) -> Result<Self, Self::LoadError>
+            where
+                E: ::sqlx::PgExecutor<'e>,
+            {
+                let entity = ::sqlx::query_as(#select_query)
+                    .bind(identifier)
+                    .fetch_one(executor)
+                    .await?;
+
+                Ok(entity)
+            }
+
+            async fn update<'e, E>(&self, executor: E) -> Result<bool, Self::UpdateError>
+            where
+                E: ::sqlx::PgExecutor<'e>,
+            {
+                let result = ::sqlx::query(#update_query)
+                    #(.bind(&self.#column_idents))*
+                    .bind(&self.#id_ident)
+                    .execute(executor)
+                    .await?;
+
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn delete<'e, E>(
+                identifier: &Self::Identifier,
+                executor: E,
+            ) -> Result<bool, Self::DeleteError>
+            where
+                E: ::sqlx::PgExecutor<'e>,
+            {
+                let result = ::sqlx::query(#delete_query)
+                    .bind(identifier)
+                    .execute(executor)
+                    .await?;
+
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn persist<'e, E>(
+                &self,
+                executor: E,
+            ) -> Result<crate::model::Persisted, Self::PersistError>
+            where
+                E: ::sqlx::PgExecutor<'e>,
+            {
+                // xmax is zero for rows the statement inserted and set for rows it updated
+                let inserted: Option<bool> = ::sqlx::query_scalar(#upsert_query)
+                    .bind(&self.#id_ident)
+                    #(.bind(&self.#column_idents))*
+                    .fetch_optional(executor)
+                    .await?;
+
+                match inserted {
+                    Some(true) => Ok(crate::model::Persisted::Inserted),
+                    Some(false) => Ok(crate::model::Persisted::Updated),
+                    None => Err(crate::model::PersistError::Deleted),
+                }
+            }
+        }
+    })
+}
+
+/// Quotes the given name for SQL. A qualified name like `schema.table` is quoted part by part.
+fn quote_identifier(name: &str) -> String {
+    name.split('.')
+        .map(|part| format!("\"{}\"", part.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn parse_table(input: &DeriveInput) -> syn::Result<Table> {
+    let mut name = None;
+    let mut soft_delete = false;
+    for attribute in input
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("db"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                name = Some(meta.value()?.parse::<LitStr>()?);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("soft_delete") {
+                soft_delete = true;
+                return Ok(());
+            }
+
+            Err(meta.error("Unsupported db attribute, expected `table = \"...\"` or `soft_delete`"))
+        })?;
+    }
+
+    let name = name.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "The table has to be given with #[db(table = \"...\")]",
+        )
+    })?;
+
+    Ok(Table { name, soft_delete })
+}
+
+fn field_kind(field: &Field) -> syn::Result<FieldKind> {
+    let mut kind = FieldKind::Column;
+    for attribute in field
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("db"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if kind != FieldKind::Column {
+                return Err(meta.error("A field can only be marked with one db attribute"));
+            }
+
+            if meta.path.is_ident("id") {
+                kind = FieldKind::Id;
+                return Ok(());
+            }
+
+            if meta.path.is_ident("generated") {
+                kind = FieldKind::Generated;
+                return Ok(());
+            }
+
+            Err(meta.error("Unsupported db attribute, expected `id` or `generated`"))
+        })?;
+    }
+
+    Ok(kind)
+}
+
+impl<'a> From<&'a Field> for Column<'a> {
+    fn from(field: &'a Field) -> Self {
+        let ident = field
+            .ident
+            .as_ref()
+            .expect("named fields always have an identifier");
+
+        Self {
+            ident,
+            name: ident.to_string().trim_start_matches("r#").to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn expand_to_string(input: DeriveInput) -> String {
+        expand(input)
+            .expect("The input should be expanded")
+            .to_string()
+    }
+
+    fn expand_error(input: DeriveInput) -> String {
+        match expand(input) {
+            Ok(_) => panic!("The input should be rejected"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    /// Whether the generated code contains the given SQL as a string literal.
+    fn contains_sql(output: &str, sql: &str) -> bool {
+        output.contains(&proc_macro2::Literal::string(sql).to_string())
+    }
+
+    /// Whether the generated code contains the given code, ignoring whitespace.
+    fn contains_code(output: &str, code: &str) -> bool {
+        let strip = |code: &str| code.split_whitespace().collect::<String>();
+        strip(output).contains(&strip(code))
+    }
+
+    #[test]
+    fn binds_columns_in_declaration_order() {
+        let output = expand_to_string(parse_quote! {
+            #[db(table = "users")]
+            struct DbUser {
+                #[db(id)]
+                uuid: Uuid,
+                username: String,
+                role: i16,
+                #[db(generated)]
+                updated_at: DateTime<Utc>,
+            }
+        });
+
+        assert!(contains_sql(
+            &output,
+            r#"SELECT * FROM "users" WHERE "uuid" = $1 LIMIT 1"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"INSERT INTO "users" ("uuid", "username", "role") VALUES ($1, $2, $3)"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"UPDATE "users" SET "username" = $1, "role" = $2 WHERE "uuid" = $3"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"INSERT INTO "users" ("uuid", "username", "role") VALUES ($1, $2, $3) ON CONFLICT ("uuid") DO UPDATE SET "username" = EXCLUDED."username", "role" = EXCLUDED."role" RETURNING (xmax = 0) AS inserted"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"DELETE FROM "users" WHERE "uuid" = $1"#
+        ));
+        assert!(contains_code(
+            &output,
+            ".bind(&self.uuid).bind(&self.username).bind(&self.role).execute(executor)"
+        ));
+        assert!(!output.contains("updated_at"));
+        assert!(!output.contains("DbEntityTrash"));
+    }
+
+    #[test]
+    fn returns_typed_errors_for_writes() {
+        let output = expand_to_string(parse_quote! {
+            #[db(table = "sessions")]
+            struct DbSession {
+                #[db(id)]
+                uuid: Uuid,
+                user_uuid: Uuid,
+            }
+        });
+
+        assert!(contains_code(&output, "type Identifier = Uuid;"));
+        assert!(contains_code(&output, "type ExistsError = ::sqlx::Error;"));
+        assert!(contains_code(
+            &output,
+            "type CreateError = crate::model::PersistError;"
+        ));
+        assert!(contains_code(&output, "type LoadError = ::sqlx::Error;"));
+        assert!(contains_code(
+            &output,
+            "type UpdateError = crate::model::PersistError;"
+        ));
+        assert!(contains_code(&output, "type DeleteError = ::sqlx::Error;"));
+        assert!(contains_code(
+            &output,
+            "type PersistError = crate::model::PersistError;"
+        ));
+    }
+
+    #[test]
+    fn moves_soft_deleted_entities_to_the_trash() {
+        let output = expand_to_string(parse_quote! {
+            #[db(table = "users", soft_delete)]
+            struct DbUser {
+                #[db(id)]
+                uuid: Uuid,
+                username: String,
+                deleted_at: Option<DateTime<Utc>>,
+            }
+        });
+
+        assert!(contains_sql(
+            &output,
+            r#"SELECT * FROM "users" WHERE "uuid" = $1 AND "deleted_at" IS NULL LIMIT 1"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"UPDATE "users" SET "deleted_at" = now() WHERE "uuid" = $1 AND "deleted_at" IS NULL"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"INSERT INTO "users" ("uuid", "username") VALUES ($1, $2)"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"INSERT INTO "users" ("uuid", "username") VALUES ($1, $2) ON CONFLICT ("uuid") DO UPDATE SET "username" = EXCLUDED."username" WHERE "users"."deleted_at" IS NULL RETURNING (xmax = 0) AS inserted"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"UPDATE "users" SET "deleted_at" = NULL WHERE "uuid" = $1 AND "deleted_at" IS NOT NULL"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"DELETE FROM "users" WHERE "uuid" = $1"#
+        ));
+        assert!(contains_sql(
+            &output,
+            r#"DELETE FROM "users" WHERE "deleted_at" < $1 RETURNING *"#
+        ));
+        assert!(contains_code(
+            &output,
+            "impl crate::model::trash::DbEntityTrash for DbUser"
+        ));
+        assert!(contains_code(
+            &output,
+            r#"const DELETED_AT_COLUMN: &'static str = "deleted_at";"#
+        ));
+    }
+
+    #[test]
+    fn uses_raw_identifiers_as_column_names() {
+        let output = expand_to_string(parse_quote! {
+            #[db(table = "entries")]
+            struct DbEntry {
+                #[db(id)]
+                uuid: Uuid,
+                r#type: String,
+            }
+        });
+
+        assert!(contains_sql(
+            &output,
+            r#"INSERT INTO "entries" ("uuid", "type") VALUES ($1, $2)"#
+        ));
+        assert!(contains_code(&output, ".bind(&self.r#type)"));
+    }
+
+    #[test]
+    fn quotes_qualified_table_names() {
+        let output = expand_to_string(parse_quote! {
+            #[db(table = "auth.users")]
+            struct DbUser {
+                #[db(id)]
+                uuid: Uuid,
+                username: String,
+            }
+        });
+
+        assert!(contains_sql(
+            &output,
+            r#"DELETE FROM "auth"."users" WHERE "uuid" = $1"#
+        ));
+        assert!(contains_code(&output, r#""auth.users""#));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        let cases: [(DeriveInput, &str); 8] = [
+            (
+                parse_quote! {
+                    struct DbUser {
+                        #[db(id)]
+                        uuid: Uuid,
+                    }
+                },
+                "The table has to be given with #[db(table = \"...\")]",
+            ),
+            (
+                parse_quote! {
+                    #[db(table = "users")]
+                    struct DbUser {
+                        uuid: Uuid,
+                    }
+                },
+                "One field has to be marked with #[db(id)]",
+            ),
+            (
+                parse_quote! {
+                    #[db(table = "users")]
+                    struct DbUser {
+                        #[db(id)]
+                        uuid: Uuid,
+                        #[db(id)]
+                        username: String,
+                    }
+                },
+                "Only one field can be marked with #[db(id)]",
+            ),
+            (
+                parse_quote! {
+                    #[db(table = "users")]
+                    struct DbUser {
+                        #[db(id)]
+                        uuid: Uuid,
+                        #[db(generated)]
+                        updated_at: DateTime<Utc>,
+                    }
+                },
+                "At least one field besides the #[db(id)] field has to be a column",
+            ),
+            (
+                parse_quote! {
+                    #[db(table = "users", soft_delete)]
+                    struct DbUser {
+                        #[db(id)]
+                        uuid: Uuid,
+                    }
+                },
+                "Soft-deleted entities need a `deleted_at` field",
+            ),
+            (
+                parse_quote! {
+                    #[db(table = "users")]
+                    enum DbUser {
+                        Uuid(Uuid),
+                    }
+                },
+                "DbEntity can only be derived for structs",
+            ),
+            (
+                parse_quote! {
+                    #[db(table = "users")]
+                    struct DbUser(Uuid);
+                },
+                "DbEntity can only be derived for structs with named fields",
+            ),
+            (
+                parse_quote! {
+                    #[db(table = "users")]
+                    struct DbUser {
+                        #[db(id, generated)]
+                        uuid: Uuid,
+                    }
+                },
+                "A field can only be marked with one db attribute",
+            ),
+        ];
+
+        for (input, message) in cases {
+            assert_eq!(expand_error(input), message);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use proc_macro::TokenStream;
+use syn::{DeriveInput, parse_macro_input};
+
+mod db_entity;
+
+/// Implements `DbEntity` for a struct that represents a row of a database table.
+///
+/// The table is given with `#[db(table = "users")]` on the struct and the identifier with `#[db(id)]` on one of
+/// its fields. Every field is a column of the same name, and at least one has to be written besides the identifier.
+/// The generated statements quote the table and column names, list the columns and bind the fields in the order
+/// they are declared in. Fields marked with `#[db(generated)]` are columns the database maintains itself, like
+/// `updated_at`, which are loaded but never written.
+///
+/// With `#[db(soft_delete)]` on the struct, `delete` moves entities to the trash by setting their `deleted_at` field,
+/// which the struct has to have, and `DbEntityTrash` is implemented as well. Entities in the trash are left out of all
+/// other operations, so `persist` fails with `model::PersistError::Deleted` for them instead of changing them.
+///
+/// The operations that write entities, `create`, `update` and `persist`, return `model::PersistError`, which tells
+/// violated unique constraints apart from other database errors. All other operations return `sqlx::Error`.
+///
+/// ```ignore
+/// #[derive(FromRow, DbEntity)]
+/// #[db(table = "users")]
+/// pub struct DbUser {
+///     #[db(id)]
+///     pub uuid: Uuid,
+///     pub username: String,
+///     #[db(generated)]
+///     pub updated_at: DateTime<Utc>,
+/// }
+/// ```
+#[proc_macro_derive(DbEntity, attributes(db))]
+pub fn derive_db_entity(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    db_entity::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
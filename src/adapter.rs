@@ -2,7 +2,14 @@ use std::fmt::Debug;
 
 use crate::model::DbEntity;
 
+pub mod account_lockout;
+pub mod audit_event;
+pub mod login_attempt;
+pub mod recovery_code;
+pub mod session;
 pub mod user;
+pub mod user_identity;
+pub mod user_totp;
 
 pub trait DbEntityAdapter<T>: Sized
 where
@@ -0,0 +1,44 @@
+use crate::model::entity::account_lockout::{AccountLockout, DbAccountLockout};
+
+use super::{DbEntityAdapter, DbEntityReference};
+
+impl From<AccountLockout> for DbAccountLockout {
+    fn from(account_lockout: AccountLockout) -> Self {
+        Self::from(&account_lockout)
+    }
+}
+
+impl From<&AccountLockout> for DbAccountLockout {
+    fn from(account_lockout: &AccountLockout) -> Self {
+        Self {
+            uuid: account_lockout.uuid,
+            user_uuid: account_lockout.user_uuid,
+            locked_at: account_lockout.locked_at,
+            locked_until: account_lockout.locked_until,
+            unlocked_at: account_lockout.unlocked_at,
+            unlocked_by: account_lockout.unlocked_by,
+        }
+    }
+}
+
+impl From<DbAccountLockout> for AccountLockout {
+    fn from(db_account_lockout: DbAccountLockout) -> Self {
+        Self::from(&db_account_lockout)
+    }
+}
+
+impl From<&DbAccountLockout> for AccountLockout {
+    fn from(db_account_lockout: &DbAccountLockout) -> Self {
+        Self {
+            uuid: db_account_lockout.uuid,
+            user_uuid: db_account_lockout.user_uuid,
+            locked_at: db_account_lockout.locked_at,
+            locked_until: db_account_lockout.locked_until,
+            unlocked_at: db_account_lockout.unlocked_at,
+            unlocked_by: db_account_lockout.unlocked_by,
+        }
+    }
+}
+
+impl DbEntityAdapter<DbAccountLockout> for AccountLockout {}
+impl DbEntityReference<DbAccountLockout> for AccountLockout {}
@@ -0,0 +1,172 @@
+use std::net::{AddrParseError, IpAddr};
+
+use rocket::async_trait;
+use sqlx::{Postgres, QueryBuilder};
+use thiserror::Error;
+
+use crate::model::{
+    entity::audit_event::{
+        AuditEvent, AuditFilter, AuditSortColumn, DbAuditEvent, ParseAuditActionError,
+        ParseAuditTargetTypeError,
+    },
+    list::{CursorValue, CursorValueKind, DbEntityList, ListFilter, SortColumn},
+};
+
+use super::{DbEntityAdapter, DbEntityReference};
+
+#[async_trait]
+impl DbEntityList for DbAuditEvent {
+    type Filter = AuditFilter;
+    type SortColumn = AuditSortColumn;
+
+    fn identifier_column() -> &'static str {
+        "uuid"
+    }
+
+    fn identifier_value_kind() -> CursorValueKind {
+        CursorValueKind::Uuid
+    }
+
+    fn cursor_value(&self, sort: Self::SortColumn) -> CursorValue {
+        match sort {
+            AuditSortColumn::OccurredAt => CursorValue::Timestamp(self.occurred_at),
+        }
+    }
+
+    fn identifier_cursor_value(&self) -> CursorValue {
+        CursorValue::Uuid(self.uuid)
+    }
+}
+
+impl ListFilter for AuditFilter {
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(action) = self.action {
+            query.push(" AND action = ").push_bind(action.as_str());
+        }
+
+        if let Some(actor_uuid) = self.actor_uuid {
+            query.push(" AND actor_uuid = ").push_bind(actor_uuid);
+        }
+
+        if let Some(target_type) = self.target_type {
+            query
+                .push(" AND target_type = ")
+                .push_bind(target_type.as_str());
+        }
+
+        if let Some(target_uuid) = self.target_uuid {
+            query.push(" AND target_uuid = ").push_bind(target_uuid);
+        }
+
+        if let Some(since) = self.since {
+            query.push(" AND occurred_at >= ").push_bind(since);
+        }
+
+        if let Some(until) = self.until {
+            query.push(" AND occurred_at < ").push_bind(until);
+        }
+    }
+}
+
+impl SortColumn for AuditSortColumn {
+    fn column_name(&self) -> &'static str {
+        match self {
+            Self::OccurredAt => "occurred_at",
+        }
+    }
+
+    fn value_kind(&self) -> CursorValueKind {
+        match self {
+            Self::OccurredAt => CursorValueKind::Timestamp,
+        }
+    }
+}
+
+impl From<AuditEvent> for DbAuditEvent {
+    fn from(audit_event: AuditEvent) -> Self {
+        Self {
+            uuid: audit_event.uuid,
+            action: audit_event.action.to_string(),
+            actor_uuid: audit_event.actor_uuid,
+            target_type: audit_event
+                .target_type
+                .map(|target_type| target_type.to_string()),
+            target_uuid: audit_event.target_uuid,
+            before: audit_event.before,
+            after: audit_event.after,
+            ip_address: audit_event
+                .ip_address
+                .map(|ip_address| ip_address.to_string()),
+            occurred_at: audit_event.occurred_at,
+        }
+    }
+}
+
+impl From<&AuditEvent> for DbAuditEvent {
+    fn from(audit_event: &AuditEvent) -> Self {
+        Self {
+            uuid: audit_event.uuid,
+            action: audit_event.action.to_string(),
+            actor_uuid: audit_event.actor_uuid,
+            target_type: audit_event
+                .target_type
+                .map(|target_type| target_type.to_string()),
+            target_uuid: audit_event.target_uuid,
+            before: audit_event.before.clone(),
+            after: audit_event.after.clone(),
+            ip_address: audit_event
+                .ip_address
+                .map(|ip_address| ip_address.to_string()),
+            occurred_at: audit_event.occurred_at,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UnadaptAuditEventError {
+    #[error("Failed to parse action: {0}")]
+    ActionParse(#[from] ParseAuditActionError),
+
+    #[error("Failed to parse target type: {0}")]
+    TargetTypeParse(#[from] ParseAuditTargetTypeError),
+
+    #[error("Failed to parse IP address: {0}")]
+    IpAddressParse(#[from] AddrParseError),
+}
+
+impl TryFrom<DbAuditEvent> for AuditEvent {
+    type Error = UnadaptAuditEventError;
+
+    fn try_from(db_audit_event: DbAuditEvent) -> Result<Self, Self::Error> {
+        Self::try_from(&db_audit_event)
+    }
+}
+
+impl TryFrom<&DbAuditEvent> for AuditEvent {
+    type Error = UnadaptAuditEventError;
+
+    fn try_from(db_audit_event: &DbAuditEvent) -> Result<Self, Self::Error> {
+        Ok(Self {
+            uuid: db_audit_event.uuid,
+            action: db_audit_event.action.parse()?,
+            actor_uuid: db_audit_event.actor_uuid,
+            target_type: db_audit_event
+                .target_type
+                .as_deref()
+                .map(str::parse)
+                .transpose()?,
+            target_uuid: db_audit_event.target_uuid,
+            before: db_audit_event.before.clone(),
+            after: db_audit_event.after.clone(),
+            ip_address: db_audit_event
+                .ip_address
+                .as_deref()
+                .map(str::parse::<IpAddr>)
+                .transpose()?,
+            occurred_at: db_audit_event.occurred_at,
+        })
+    }
+}
+
+impl DbEntityAdapter<DbAuditEvent> for AuditEvent {}
+impl DbEntityReference<DbAuditEvent> for AuditEvent {}
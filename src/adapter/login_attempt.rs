@@ -0,0 +1,70 @@
+use std::net::{AddrParseError, IpAddr};
+
+use thiserror::Error;
+
+use crate::model::entity::login_attempt::{DbLoginAttempt, LoginAttempt};
+
+use super::{DbEntityAdapter, DbEntityReference};
+
+impl From<LoginAttempt> for DbLoginAttempt {
+    fn from(login_attempt: LoginAttempt) -> Self {
+        Self {
+            uuid: login_attempt.uuid,
+            username: login_attempt.username,
+            ip_address: login_attempt
+                .ip_address
+                .map(|ip_address| ip_address.to_string()),
+            succeeded: login_attempt.succeeded,
+            attempted_at: login_attempt.attempted_at,
+        }
+    }
+}
+
+impl From<&LoginAttempt> for DbLoginAttempt {
+    fn from(login_attempt: &LoginAttempt) -> Self {
+        Self {
+            uuid: login_attempt.uuid,
+            username: login_attempt.username.clone(),
+            ip_address: login_attempt
+                .ip_address
+                .map(|ip_address| ip_address.to_string()),
+            succeeded: login_attempt.succeeded,
+            attempted_at: login_attempt.attempted_at,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UnadaptLoginAttemptError {
+    #[error("Failed to parse IP address: {0}")]
+    IpAddressParse(#[from] AddrParseError),
+}
+
+impl TryFrom<DbLoginAttempt> for LoginAttempt {
+    type Error = UnadaptLoginAttemptError;
+
+    fn try_from(db_login_attempt: DbLoginAttempt) -> Result<Self, Self::Error> {
+        Self::try_from(&db_login_attempt)
+    }
+}
+
+impl TryFrom<&DbLoginAttempt> for LoginAttempt {
+    type Error = UnadaptLoginAttemptError;
+
+    fn try_from(db_login_attempt: &DbLoginAttempt) -> Result<Self, Self::Error> {
+        Ok(Self {
+            uuid: db_login_attempt.uuid,
+            username: db_login_attempt.username.clone(),
+            ip_address: db_login_attempt
+                .ip_address
+                .as_deref()
+                .map(str::parse::<IpAddr>)
+                .transpose()?,
+            succeeded: db_login_attempt.succeeded,
+            attempted_at: db_login_attempt.attempted_at,
+        })
+    }
+}
+
+impl DbEntityAdapter<DbLoginAttempt> for LoginAttempt {}
+impl DbEntityReference<DbLoginAttempt> for LoginAttempt {}
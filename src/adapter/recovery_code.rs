@@ -0,0 +1,54 @@
+use crate::model::entity::recovery_code::{DbRecoveryCode, RecoveryCode};
+
+use super::{DbEntityAdapter, DbEntityReference};
+
+impl From<RecoveryCode> for DbRecoveryCode {
+    fn from(recovery_code: RecoveryCode) -> Self {
+        Self {
+            uuid: recovery_code.uuid,
+            user_uuid: recovery_code.user_uuid,
+            code_hash: recovery_code.code_hash,
+            used_at: recovery_code.used_at,
+            created_at: recovery_code.created_at,
+        }
+    }
+}
+
+impl From<&RecoveryCode> for DbRecoveryCode {
+    fn from(recovery_code: &RecoveryCode) -> Self {
+        Self {
+            uuid: recovery_code.uuid,
+            user_uuid: recovery_code.user_uuid,
+            code_hash: recovery_code.code_hash.clone(),
+            used_at: recovery_code.used_at,
+            created_at: recovery_code.created_at,
+        }
+    }
+}
+
+impl From<DbRecoveryCode> for RecoveryCode {
+    fn from(db_recovery_code: DbRecoveryCode) -> Self {
+        Self {
+            uuid: db_recovery_code.uuid,
+            user_uuid: db_recovery_code.user_uuid,
+            code_hash: db_recovery_code.code_hash,
+            used_at: db_recovery_code.used_at,
+            created_at: db_recovery_code.created_at,
+        }
+    }
+}
+
+impl From<&DbRecoveryCode> for RecoveryCode {
+    fn from(db_recovery_code: &DbRecoveryCode) -> Self {
+        Self {
+            uuid: db_recovery_code.uuid,
+            user_uuid: db_recovery_code.user_uuid,
+            code_hash: db_recovery_code.code_hash.clone(),
+            used_at: db_recovery_code.used_at,
+            created_at: db_recovery_code.created_at,
+        }
+    }
+}
+
+impl DbEntityAdapter<DbRecoveryCode> for RecoveryCode {}
+impl DbEntityReference<DbRecoveryCode> for RecoveryCode {}
@@ -0,0 +1,54 @@
+use crate::model::entity::session::{DbSession, Session};
+
+use super::{DbEntityAdapter, DbEntityReference};
+
+impl From<Session> for DbSession {
+    fn from(session: Session) -> Self {
+        Self {
+            uuid: session.uuid,
+            user_uuid: session.user_uuid,
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+            revoked_at: session.revoked_at,
+        }
+    }
+}
+
+impl From<&Session> for DbSession {
+    fn from(session: &Session) -> Self {
+        Self {
+            uuid: session.uuid,
+            user_uuid: session.user_uuid,
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+            revoked_at: session.revoked_at,
+        }
+    }
+}
+
+impl From<DbSession> for Session {
+    fn from(db_session: DbSession) -> Self {
+        Self {
+            uuid: db_session.uuid,
+            user_uuid: db_session.user_uuid,
+            created_at: db_session.created_at,
+            expires_at: db_session.expires_at,
+            revoked_at: db_session.revoked_at,
+        }
+    }
+}
+
+impl From<&DbSession> for Session {
+    fn from(db_session: &DbSession) -> Self {
+        Self {
+            uuid: db_session.uuid,
+            user_uuid: db_session.user_uuid,
+            created_at: db_session.created_at,
+            expires_at: db_session.expires_at,
+            revoked_at: db_session.revoked_at,
+        }
+    }
+}
+
+impl DbEntityAdapter<DbSession> for Session {}
+impl DbEntityReference<DbSession> for Session {}
@@ -1,118 +1,92 @@
-use chrono::DateTime;
 use num_enum::TryFromPrimitiveError;
 use orion::{errors::UnknownCryptoError, pwhash::PasswordHash};
 use rocket::async_trait;
-use sqlx::{Pool, Postgres};
+use sqlx::{Postgres, QueryBuilder};
 use thiserror::Error;
-use uuid::Uuid;
 
 use crate::model::{
-    DbEntity,
-    entity::user::{DbUser, Role, User},
+    entity::user::{DbUser, Role, User, UserFilter, UserSortColumn},
+    list::{CursorValue, CursorValueKind, DbEntityList, ListFilter, SortColumn},
+    trash::DbEntityTrash,
 };
 
 use super::{DbEntityAdapter, DbEntityReference};
 
+/// The primary key of the users table.
+pub const PRIMARY_KEY: &str = "users_pkey";
+
+/// The unique index guaranteeing that there is at most one system user.
+pub const UNIQUE_SYSTEM_ROLE_INDEX: &str = "users_unique_system_role";
+
+/// The unique index guaranteeing that usernames are unique, regardless of their case.
+pub const UNIQUE_USERNAME_INDEX: &str = "users_unique_username";
+
 #[async_trait]
-impl DbEntity for DbUser {
-    type Identifier = Uuid;
-    type ExistsError = sqlx::Error;
-    type CreateError = sqlx::Error;
-    type LoadError = sqlx::Error;
-    type UpdateError = sqlx::Error;
-    type DeleteError = sqlx::Error;
-    type PersistError = sqlx::Error;
-
-    fn main_table_name() -> &'static str {
-        "users"
+impl DbEntityList for DbUser {
+    type Filter = UserFilter;
+    type SortColumn = UserSortColumn;
+
+    fn identifier_column() -> &'static str {
+        "uuid"
     }
 
-    fn get_identifier(&self) -> &Self::Identifier {
-        &self.uuid
+    fn identifier_value_kind() -> CursorValueKind {
+        CursorValueKind::Uuid
     }
 
-    async fn exists(
-        identifier: &Self::Identifier,
-        postgres_pool: &Pool<Postgres>,
-    ) -> Result<bool, Self::ExistsError> {
-        let query = format!(
-            "SELECT * FROM {} WHERE uuid = $1 LIMIT 1",
-            Self::main_table_name()
-        );
-
-        let result = sqlx::query(&query)
-            .bind(identifier)
-            .fetch_optional(postgres_pool)
-            .await?;
-
-        Ok(result.is_some())
+    fn deleted_at_column() -> Option<&'static str> {
+        Some(Self::DELETED_AT_COLUMN)
     }
 
-    async fn create(&self, postgres_pool: &Pool<Postgres>) -> Result<(), Self::CreateError> {
-        let query = format!(
-            "INSERT INTO {} (uuid, username, role, password_hash, created_at) VALUES ($1, $2, $3, $4, $5)",
-            Self::main_table_name()
-        );
-
-        sqlx::query(&query)
-            .bind(self.uuid)
-            .bind(&self.username)
-            .bind(self.role)
-            .bind(&self.password_hash)
-            .bind(self.created_at)
-            .execute(postgres_pool)
-            .await?;
-
-        Ok(())
+    fn cursor_value(&self, sort: Self::SortColumn) -> CursorValue {
+        match sort {
+            UserSortColumn::CreatedAt => CursorValue::Timestamp(self.created_at),
+            UserSortColumn::Username => CursorValue::Text(self.username.clone()),
+        }
     }
 
-    async fn load(
-        identifier: &Self::Identifier,
-        postgres_pool: &Pool<Postgres>,
-    ) -> Result<Self, Self::LoadError> {
-        let query = format!(
-            "SELECT * FROM {} WHERE uuid = $1 LIMIT 1",
-            Self::main_table_name()
-        );
-
-        let db_user = sqlx::query_as(&query)
-            .bind(identifier)
-            .fetch_one(postgres_pool)
-            .await?;
-
-        Ok(db_user)
+    fn identifier_cursor_value(&self) -> CursorValue {
+        CursorValue::Uuid(self.uuid)
     }
+}
+
+impl ListFilter for UserFilter {
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(deleted_by) = self.deleted_by {
+            query.push(" AND deleted_by = ").push_bind(deleted_by);
+        }
+
+        if let Some(role) = self.role {
+            query.push(" AND role = ").push_bind::<i16>(role.into());
+        }
 
-    async fn update(&self, postgres_pool: &Pool<Postgres>) -> Result<(), Self::UpdateError> {
-        let query = format!(
-            "UPDATE {} SET username = $1, role = $2, password_hash = $3, created_at = $4 WHERE uuid = $5",
-            Self::main_table_name()
-        );
-
-        sqlx::query(&query)
-            .bind(&self.username)
-            .bind(self.role)
-            .bind(&self.password_hash)
-            .bind(self.created_at)
-            .bind(self.uuid)
-            .execute(postgres_pool)
-            .await?;
-
-        Ok(())
+        if let Some(created_since) = self.created_since {
+            query.push(" AND created_at >= ").push_bind(created_since);
+        }
+
+        if let Some(created_until) = self.created_until {
+            query.push(" AND created_at < ").push_bind(created_until);
+        }
     }
 
-    async fn delete(
-        identifier: &Self::Identifier,
-        postgres_pool: &Pool<Postgres>,
-    ) -> Result<(), Self::DeleteError> {
-        let query = format!("DELETE FROM {} WHERE uuid = $1", Self::main_table_name());
+    fn lists_trash(&self) -> bool {
+        self.deleted
+    }
+}
 
-        sqlx::query(&query)
-            .bind(identifier)
-            .execute(postgres_pool)
-            .await?;
+impl SortColumn for UserSortColumn {
+    fn column_name(&self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::Username => "username",
+        }
+    }
 
-        Ok(())
+    fn value_kind(&self) -> CursorValueKind {
+        match self {
+            Self::CreatedAt => CursorValueKind::Timestamp,
+            Self::Username => CursorValueKind::Text,
+        }
     }
 }
 
@@ -123,7 +97,10 @@ impl From<User> for DbUser {
             username: user.username,
             role: user.role.into(),
             password_hash: user.password_hash.unprotected_as_encoded().to_string(),
-            created_at: user.created_at.timestamp(),
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            deleted_at: user.deleted_at,
+            deleted_by: user.deleted_by,
         }
     }
 }
@@ -135,7 +112,10 @@ impl From<&User> for DbUser {
             username: user.username.clone(),
             role: user.role.into(),
             password_hash: user.password_hash.unprotected_as_encoded().to_string(),
-            created_at: user.created_at.timestamp(),
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            deleted_at: user.deleted_at,
+            deleted_by: user.deleted_by,
         }
     }
 }
@@ -150,9 +130,6 @@ pub enum UnadaptUserError {
 
     #[error("Failed to parse password hash: {0}")]
     PasswordHashParse(#[from] UnknownCryptoError),
-
-    #[error("Failed to parse created_at timestamp: The timestamp was out of range: {0}")]
-    CreatedAtParse(i64),
 }
 
 impl TryFrom<DbUser> for User {
@@ -164,8 +141,10 @@ impl TryFrom<DbUser> for User {
             username: db_user.username,
             role: Role::try_from(db_user.role)?,
             password_hash: PasswordHash::from_encoded(&db_user.password_hash)?,
-            created_at: DateTime::from_timestamp(db_user.created_at, 0)
-                .ok_or(UnadaptUserError::CreatedAtParse(db_user.created_at))?,
+            created_at: db_user.created_at,
+            updated_at: db_user.updated_at,
+            deleted_at: db_user.deleted_at,
+            deleted_by: db_user.deleted_by,
         })
     }
 }
@@ -179,8 +158,10 @@ impl TryFrom<&DbUser> for User {
             username: db_user.username.clone(),
             role: Role::try_from(db_user.role)?,
             password_hash: PasswordHash::from_encoded(&db_user.password_hash)?,
-            created_at: DateTime::from_timestamp(db_user.created_at, 0)
-                .ok_or(UnadaptUserError::CreatedAtParse(db_user.created_at))?,
+            created_at: db_user.created_at,
+            updated_at: db_user.updated_at,
+            deleted_at: db_user.deleted_at,
+            deleted_by: db_user.deleted_by,
         })
     }
 }
@@ -0,0 +1,57 @@
+use crate::model::entity::user_identity::{DbUserIdentity, UserIdentity};
+
+use super::{DbEntityAdapter, DbEntityReference};
+
+/// The unique index guaranteeing that an identity is linked to at most one user.
+pub const UNIQUE_SUBJECT_INDEX: &str = "user_identities_unique_subject";
+
+impl From<UserIdentity> for DbUserIdentity {
+    fn from(user_identity: UserIdentity) -> Self {
+        Self {
+            uuid: user_identity.uuid,
+            user_uuid: user_identity.user_uuid,
+            issuer: user_identity.issuer,
+            subject: user_identity.subject,
+            created_at: user_identity.created_at,
+        }
+    }
+}
+
+impl From<&UserIdentity> for DbUserIdentity {
+    fn from(user_identity: &UserIdentity) -> Self {
+        Self {
+            uuid: user_identity.uuid,
+            user_uuid: user_identity.user_uuid,
+            issuer: user_identity.issuer.clone(),
+            subject: user_identity.subject.clone(),
+            created_at: user_identity.created_at,
+        }
+    }
+}
+
+impl From<DbUserIdentity> for UserIdentity {
+    fn from(db_user_identity: DbUserIdentity) -> Self {
+        Self {
+            uuid: db_user_identity.uuid,
+            user_uuid: db_user_identity.user_uuid,
+            issuer: db_user_identity.issuer,
+            subject: db_user_identity.subject,
+            created_at: db_user_identity.created_at,
+        }
+    }
+}
+
+impl From<&DbUserIdentity> for UserIdentity {
+    fn from(db_user_identity: &DbUserIdentity) -> Self {
+        Self {
+            uuid: db_user_identity.uuid,
+            user_uuid: db_user_identity.user_uuid,
+            issuer: db_user_identity.issuer.clone(),
+            subject: db_user_identity.subject.clone(),
+            created_at: db_user_identity.created_at,
+        }
+    }
+}
+
+impl DbEntityAdapter<DbUserIdentity> for UserIdentity {}
+impl DbEntityReference<DbUserIdentity> for UserIdentity {}
@@ -0,0 +1,54 @@
+use crate::model::entity::user_totp::{DbUserTotp, UserTotp};
+
+use super::{DbEntityAdapter, DbEntityReference};
+
+impl From<UserTotp> for DbUserTotp {
+    fn from(user_totp: UserTotp) -> Self {
+        Self {
+            user_uuid: user_totp.user_uuid,
+            encrypted_secret: user_totp.encrypted_secret,
+            confirmed: user_totp.confirmed,
+            last_used_step: user_totp.last_used_step.map(|step| step as i64),
+            created_at: user_totp.created_at,
+        }
+    }
+}
+
+impl From<&UserTotp> for DbUserTotp {
+    fn from(user_totp: &UserTotp) -> Self {
+        Self {
+            user_uuid: user_totp.user_uuid,
+            encrypted_secret: user_totp.encrypted_secret.clone(),
+            confirmed: user_totp.confirmed,
+            last_used_step: user_totp.last_used_step.map(|step| step as i64),
+            created_at: user_totp.created_at,
+        }
+    }
+}
+
+impl From<DbUserTotp> for UserTotp {
+    fn from(db_user_totp: DbUserTotp) -> Self {
+        Self {
+            user_uuid: db_user_totp.user_uuid,
+            encrypted_secret: db_user_totp.encrypted_secret,
+            confirmed: db_user_totp.confirmed,
+            last_used_step: db_user_totp.last_used_step.map(|step| step as u64),
+            created_at: db_user_totp.created_at,
+        }
+    }
+}
+
+impl From<&DbUserTotp> for UserTotp {
+    fn from(db_user_totp: &DbUserTotp) -> Self {
+        Self {
+            user_uuid: db_user_totp.user_uuid,
+            encrypted_secret: db_user_totp.encrypted_secret.clone(),
+            confirmed: db_user_totp.confirmed,
+            last_used_step: db_user_totp.last_used_step.map(|step| step as u64),
+            created_at: db_user_totp.created_at,
+        }
+    }
+}
+
+impl DbEntityAdapter<DbUserTotp> for UserTotp {}
+impl DbEntityReference<DbUserTotp> for UserTotp {}
@@ -0,0 +1,684 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rocket::{
+    futures::TryStreamExt,
+    serde::{
+        Deserialize, Serialize,
+        json::{Value, serde_json},
+    },
+};
+use sha2::{Digest, Sha256};
+use sqlx::{
+    PgConnection, Pool, Postgres,
+    migrate::{MigrateError, Migrator},
+};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use thiserror::Error;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, Lines},
+};
+
+use uuid::Uuid;
+
+use crate::database::{self, CheckMigrationError};
+
+/// Identifies Rasopus backup archives.
+const FORMAT: &str = "rasopus-backup";
+
+/// The version of the archive format. It changes whenever archives of the previous version can't be restored anymore.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The tables Rasopus owns, in an order in which inserting them satisfies their foreign keys.
+/// Tables that are missing here make backups fail, so no backup silently leaves data out.
+const TABLES: [&str; 7] = [
+    "users",
+    "user_identities",
+    "user_totps",
+    "recovery_codes",
+    "account_lockouts",
+    "login_attempts",
+    "audit_events",
+];
+
+/// The tables Rasopus owns that are deliberately left out of backups. Sessions are short-lived, and restoring them
+/// would log users back in whose sessions were ended after the backup was taken.
+const SKIPPED_TABLES: [&str; 1] = ["sessions"];
+
+/// The table sqlx keeps track of the applied migrations in. It is not backed up, as restoring runs the migrations.
+const MIGRATIONS_TABLE: &str = "_sqlx_migrations";
+
+/// How many rows are inserted with one statement while restoring.
+const RESTORE_BATCH_SIZE: usize = 500;
+
+/// The first line of an archive.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Header {
+    format: String,
+    format_version: u32,
+    rasopus_version: String,
+    migration_version: i64,
+    created_at: DateTime<Utc>,
+}
+
+/// A line of an archive holding a row of a table.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Row {
+    table: String,
+    row: Value,
+}
+
+/// The last line of an archive, holding the number of rows and the SHA-256 checksum of all lines before it.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Trailer {
+    rows: u64,
+    sha256: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde", untagged)]
+enum Entry {
+    Row(Row),
+    Trailer(Trailer),
+}
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Failed to check database migrations: {0}")]
+    CheckMigration(#[from] CheckMigrationError),
+
+    #[error(
+        "The database is not migrated to the schema of this Rasopus version, start Rasopus once to migrate it"
+    )]
+    NotMigrated,
+
+    #[error("The table {0} is not known to the backup, refusing to create an incomplete backup")]
+    UnknownTable(String),
+
+    #[error("The archive path {0} doesn't name a file")]
+    NotAFile(PathBuf),
+
+    #[error("Failed to serialize a row: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("Failed to write the archive: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum RestoreError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Failed to run database migrations: {0}")]
+    Migrate(#[from] MigrateError),
+
+    #[error("Failed to read the archive: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("The file is not a Rasopus backup archive")]
+    NotAnArchive,
+
+    #[error(
+        "The archive has format version {0}, but this Rasopus version restores format version {FORMAT_VERSION}"
+    )]
+    UnsupportedFormatVersion(u32),
+
+    #[error(
+        "The archive was created at migration version {archive}, but this Rasopus version is at migration version {current}"
+    )]
+    IncompatibleMigrationVersion { archive: i64, current: i64 },
+
+    #[error("Line {0} of the archive is malformed: {1}")]
+    Malformed(u64, serde_json::Error),
+
+    #[error("The archive contains rows of the unknown table {0}")]
+    UnknownTable(String),
+
+    #[error("The archive ends without its checksum, it is probably truncated")]
+    Truncated,
+
+    #[error("The archive contains data after its checksum")]
+    TrailingData,
+
+    #[error("The checksum of the archive does not match its contents")]
+    ChecksumMismatch,
+
+    #[error("The table {0} is not empty, a backup can only be restored into an empty database")]
+    NotEmpty(&'static str),
+}
+
+/// Returns the migration version of the schema the given migrator migrates to.
+pub fn migration_version(migrator: &Migrator) -> i64 {
+    migrator
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Writes all rows of the Rasopus tables to an archive at the given path. Returns how many rows were written.
+///
+/// The archive consists of a header line, a line per row and a trailer line with the checksum. The rows are read in
+/// one repeatable read transaction, so the archive is a consistent snapshot even while Rasopus is running.
+///
+/// The archive is written to a temporary file next to the given path and only moved there once it is complete and
+/// synced to disk, so a failed or interrupted backup never leaves a partial archive behind.
+pub async fn backup(
+    postgres_pool: &Pool<Postgres>,
+    migrator: &Migrator,
+    path: &Path,
+) -> Result<u64, BackupError> {
+    if database::needs_migration(postgres_pool, migrator).await? {
+        return Err(BackupError::NotMigrated);
+    }
+
+    let mut transaction = postgres_pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *transaction)
+        .await?;
+
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT tablename::TEXT FROM pg_tables WHERE schemaname = current_schema()",
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    if let Some(table) = tables.into_iter().find(|table| {
+        table != MIGRATIONS_TABLE
+            && !TABLES.contains(&table.as_str())
+            && !SKIPPED_TABLES.contains(&table.as_str())
+    }) {
+        return Err(BackupError::UnknownTable(table));
+    }
+
+    let (Some(directory), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(BackupError::NotAFile(path.to_path_buf()));
+    };
+    // A bare file name has an empty parent, which stands for the working directory
+    let directory = if directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        directory
+    };
+    let temporary_path = directory.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        Uuid::new_v4()
+    ));
+
+    let result: Result<u64, BackupError> = async {
+        let mut writer = ArchiveWriter::create(&temporary_path).await?;
+        writer
+            .write_line(&Header {
+                format: FORMAT.to_string(),
+                format_version: FORMAT_VERSION,
+                rasopus_version: env!("CARGO_PKG_VERSION").to_string(),
+                migration_version: migration_version(migrator),
+                created_at: Utc::now(),
+            })
+            .await?;
+
+        for table in TABLES {
+            let query = format!("SELECT to_jsonb({table}) FROM {table}");
+            let mut rows = sqlx::query_scalar::<_, Value>(&query).fetch(&mut *transaction);
+            while let Some(row) = rows.try_next().await? {
+                writer
+                    .write_line(&Row {
+                        table: table.to_string(),
+                        row,
+                    })
+                    .await?;
+                writer.rows += 1;
+            }
+        }
+
+        transaction.commit().await?;
+
+        let (rows, file) = writer.finish().await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&temporary_path, path).await?;
+
+        Ok(rows)
+    }
+    .await;
+
+    let rows = match result {
+        Ok(rows) => rows,
+        Err(error) => {
+            tokio::fs::remove_file(&temporary_path).await.ok();
+            return Err(error);
+        }
+    };
+
+    // The rename only survives a crash once the directory holding the archive is synced as well
+    #[cfg(unix)]
+    File::open(directory).await?.sync_all().await?;
+
+    Ok(rows)
+}
+
+/// Restores an archive into an empty database. Returns how many rows were restored.
+///
+/// The database is migrated first, so it has the schema the archive was created with. The migrations and the rows
+/// are applied in one transaction, which is only committed once the checksum of the whole archive has been verified.
+/// A database that turns out not to be empty is left as it was.
+pub async fn restore(
+    postgres_pool: &Pool<Postgres>,
+    migrator: &Migrator,
+    path: &Path,
+) -> Result<u64, RestoreError> {
+    let file = File::open(path).await?;
+    let (mut reader, header) = ArchiveReader::open(BufReader::new(file)).await?;
+
+    let current_migration_version = migration_version(migrator);
+    if header.migration_version != current_migration_version {
+        return Err(RestoreError::IncompatibleMigrationVersion {
+            archive: header.migration_version,
+            current: current_migration_version,
+        });
+    }
+
+    println!(
+        "Restoring backup of Rasopus v{} created at {}",
+        header.rasopus_version, header.created_at
+    );
+
+    let mut transaction = postgres_pool.begin().await?;
+    // `run` doesn't accept a transaction in a future that has to be `Send`, `run_direct` is sqlx's way around that
+    migrator.run_direct(&mut *transaction).await?;
+
+    for table in TABLES {
+        let query = format!("SELECT EXISTS (SELECT 1 FROM {})", table);
+        let has_rows: bool = sqlx::query_scalar(&query)
+            .fetch_one(&mut *transaction)
+            .await?;
+        if has_rows {
+            return Err(RestoreError::NotEmpty(table));
+        }
+    }
+
+    // Rows referencing rows of the same table may come before them
+    sqlx::query("SET CONSTRAINTS ALL DEFERRED")
+        .execute(&mut *transaction)
+        .await?;
+
+    let mut batch: Option<(&'static str, Vec<Value>)> = None;
+    while let Some(row) = reader.next_row().await? {
+        let Some(table) = TABLES.into_iter().find(|table| *table == row.table) else {
+            return Err(RestoreError::UnknownTable(row.table));
+        };
+
+        match &mut batch {
+            Some((batch_table, rows))
+                if *batch_table == table && rows.len() < RESTORE_BATCH_SIZE =>
+            {
+                rows.push(row.row);
+            }
+            _ => {
+                if let Some((batch_table, rows)) = batch.replace((table, vec![row.row])) {
+                    insert_rows(batch_table, rows, &mut transaction).await?;
+                }
+            }
+        }
+    }
+
+    if let Some((table, rows)) = batch {
+        insert_rows(table, rows, &mut transaction).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(reader.rows)
+}
+
+/// Inserts rows as they were serialized by `to_jsonb`, converting their values back to the types of the columns.
+async fn insert_rows(
+    table: &str,
+    rows: Vec<Value>,
+    connection: &mut PgConnection,
+) -> Result<(), sqlx::Error> {
+    let query =
+        format!("INSERT INTO {table} SELECT * FROM jsonb_populate_recordset(NULL::{table}, $1)");
+
+    sqlx::query(&query)
+        .bind(Value::Array(rows))
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
+
+fn hash_line(hasher: &mut Sha256, line: &str) {
+    hasher.update(line.as_bytes());
+    hasher.update(b"\n");
+}
+
+/// Writes the lines of an archive and hashes them for its checksum.
+struct ArchiveWriter<W> {
+    writer: BufWriter<W>,
+    hasher: Sha256,
+    rows: u64,
+}
+
+impl ArchiveWriter<File> {
+    /// Creates a new archive file, failing if the path is taken. Only its owner can read it, as it contains password
+    /// hashes and TOTP secrets.
+    async fn create(path: &Path) -> Result<Self, BackupError> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let file = options.open(path).await?;
+
+        // The umask may have taken away permissions from the mode, set them exactly
+        #[cfg(unix)]
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .await?;
+
+        Ok(Self::new(file))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> ArchiveWriter<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer: BufWriter::new(writer),
+            hasher: Sha256::new(),
+            rows: 0,
+        }
+    }
+
+    async fn write_line<T: Serialize>(&mut self, value: &T) -> Result<(), BackupError> {
+        let line = serde_json::to_string(value)?;
+        hash_line(&mut self.hasher, &line);
+
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.write_all(b"\n").await?;
+
+        Ok(())
+    }
+
+    /// Writes the trailer and returns how many rows were written, along with the underlying writer.
+    async fn finish(self) -> Result<(u64, W), BackupError> {
+        let Self {
+            mut writer,
+            hasher,
+            rows,
+        } = self;
+
+        let trailer = Trailer {
+            rows,
+            sha256: format!("{:x}", hasher.finalize()),
+        };
+        let line = serde_json::to_string(&trailer)?;
+        writer.write_all(line.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+        writer.flush().await?;
+
+        Ok((rows, writer.into_inner()))
+    }
+}
+
+/// Reads the lines of an archive and verifies them against its checksum.
+struct ArchiveReader<R> {
+    lines: Lines<R>,
+    hasher: Sha256,
+    line_number: u64,
+    rows: u64,
+}
+
+impl<R: AsyncBufRead + Unpin> ArchiveReader<R> {
+    /// Reads the header of the archive and checks that this Rasopus version can read the rest of it.
+    async fn open(reader: R) -> Result<(Self, Header), RestoreError> {
+        let mut lines = reader.lines();
+
+        let Some(line) = lines.next_line().await? else {
+            return Err(RestoreError::NotAnArchive);
+        };
+        let header: Header = serde_json::from_str(&line).map_err(|_| RestoreError::NotAnArchive)?;
+        if header.format != FORMAT {
+            return Err(RestoreError::NotAnArchive);
+        }
+        if header.format_version != FORMAT_VERSION {
+            return Err(RestoreError::UnsupportedFormatVersion(
+                header.format_version,
+            ));
+        }
+
+        let mut hasher = Sha256::new();
+        hash_line(&mut hasher, &line);
+
+        let reader = Self {
+            lines,
+            hasher,
+            line_number: 1,
+            rows: 0,
+        };
+
+        Ok((reader, header))
+    }
+
+    /// Reads the next row. Returns `None` once the trailer was reached and the archive matched its checksum.
+    ///
+    /// Rows are returned before the checksum is verified, so they must not be used before this returned `None`.
+    async fn next_row(&mut self) -> Result<Option<Row>, RestoreError> {
+        let Some(line) = self.lines.next_line().await? else {
+            return Err(RestoreError::Truncated);
+        };
+        self.line_number += 1;
+
+        let entry = serde_json::from_str(&line)
+            .map_err(|error| RestoreError::Malformed(self.line_number, error))?;
+        let trailer = match entry {
+            Entry::Row(row) => {
+                hash_line(&mut self.hasher, &line);
+                self.rows += 1;
+                return Ok(Some(row));
+            }
+            Entry::Trailer(trailer) => trailer,
+        };
+
+        if self.lines.next_line().await?.is_some() {
+            return Err(RestoreError::TrailingData);
+        }
+
+        let checksum = format!("{:x}", self.hasher.clone().finalize());
+        if checksum != trailer.sha256 || self.rows != trailer.rows {
+            return Err(RestoreError::ChecksumMismatch);
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> Header {
+        Header {
+            format: FORMAT.to_string(),
+            format_version: FORMAT_VERSION,
+            rasopus_version: "0.0.1".to_string(),
+            migration_version: 20261018220000,
+            created_at: Utc::now(),
+        }
+    }
+
+    async fn archive(rows: &[Row]) -> String {
+        let mut writer = ArchiveWriter::new(Vec::new());
+        writer.write_line(&header()).await.unwrap();
+        for row in rows {
+            writer.write_line(row).await.unwrap();
+            writer.rows += 1;
+        }
+        let (_, archive) = writer.finish().await.unwrap();
+
+        String::from_utf8(archive).unwrap()
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row {
+                table: "users".to_string(),
+                row: serde_json::json!({ "username": "alice" }),
+            },
+            Row {
+                table: "audit_events".to_string(),
+                row: serde_json::json!({ "action": "user.created" }),
+            },
+        ]
+    }
+
+    async fn read(archive: &str) -> Result<Vec<Row>, RestoreError> {
+        let (mut reader, _) = ArchiveReader::open(archive.as_bytes()).await?;
+
+        let mut rows = Vec::new();
+        while let Some(row) = reader.next_row().await? {
+            rows.push(row);
+        }
+
+        Ok(rows)
+    }
+
+    fn replace_line(archive: &str, index: usize, line: &str) -> String {
+        let mut lines: Vec<&str> = archive.lines().collect();
+        lines[index] = line;
+        lines.join("\n") + "\n"
+    }
+
+    #[tokio::test]
+    async fn reads_written_archive() {
+        let archive = archive(&rows()).await;
+        let (mut reader, header) = ArchiveReader::open(archive.as_bytes()).await.unwrap();
+
+        assert_eq!(header.migration_version, 20261018220000);
+        for expected in rows() {
+            let row = reader.next_row().await.unwrap().unwrap();
+            assert_eq!(row.table, expected.table);
+            assert_eq!(row.row, expected.row);
+        }
+        assert!(reader.next_row().await.unwrap().is_none());
+        assert_eq!(reader.rows, 2);
+    }
+
+    #[tokio::test]
+    async fn reads_empty_archive() {
+        let archive = archive(&[]).await;
+
+        assert!(read(&archive).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_other_files() {
+        for file in ["", "not json\n", "{\"format\":\"pg_dump\"}\n"] {
+            assert!(matches!(read(file).await, Err(RestoreError::NotAnArchive)));
+        }
+
+        let mut header = header();
+        header.format = "another-backup".to_string();
+        let file = serde_json::to_string(&header).unwrap();
+        assert!(matches!(read(&file).await, Err(RestoreError::NotAnArchive)));
+    }
+
+    #[tokio::test]
+    async fn rejects_other_format_versions() {
+        let mut header = header();
+        header.format_version = FORMAT_VERSION + 1;
+        let archive = replace_line(
+            &archive(&rows()).await,
+            0,
+            &serde_json::to_string(&header).unwrap(),
+        );
+
+        assert!(matches!(
+            read(&archive).await,
+            Err(RestoreError::UnsupportedFormatVersion(version)) if version == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_truncated_archive() {
+        let archive = archive(&rows()).await;
+        let truncated: String = archive
+            .lines()
+            .take(2)
+            .map(|line| line.to_string() + "\n")
+            .collect();
+
+        assert!(matches!(
+            read(&truncated).await,
+            Err(RestoreError::Truncated)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_lines() {
+        let archive = replace_line(&archive(&rows()).await, 2, "{\"table\":");
+
+        assert!(matches!(
+            read(&archive).await,
+            Err(RestoreError::Malformed(3, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_data_after_trailer() {
+        let archive = archive(&rows()).await + "{\"table\":\"users\",\"row\":{}}\n";
+
+        assert!(matches!(
+            read(&archive).await,
+            Err(RestoreError::TrailingData)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_changed_archive() {
+        let archive = archive(&rows()).await;
+
+        let tampered = replace_line(
+            &archive,
+            1,
+            &serde_json::to_string(&Row {
+                table: "users".to_string(),
+                row: serde_json::json!({ "username": "mallory" }),
+            })
+            .unwrap(),
+        );
+        assert!(matches!(
+            read(&tampered).await,
+            Err(RestoreError::ChecksumMismatch)
+        ));
+
+        let mut lines: Vec<&str> = archive.lines().collect();
+        lines.remove(2);
+        let missing_row = lines.join("\n") + "\n";
+        assert!(matches!(
+            read(&missing_row).await,
+            Err(RestoreError::ChecksumMismatch)
+        ));
+
+        let trailer: Trailer = serde_json::from_str(archive.lines().last().unwrap()).unwrap();
+        let wrong_count = replace_line(
+            &archive,
+            3,
+            &serde_json::to_string(&Trailer {
+                rows: 3,
+                sha256: trailer.sha256,
+            })
+            .unwrap(),
+        );
+        assert!(matches!(
+            read(&wrong_count).await,
+            Err(RestoreError::ChecksumMismatch)
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+pub const USAGE: &str = "Usage: rasopus [backup <file> | restore <file>]
+
+Commands:
+  (none)          Start the Rasopus server
+  backup <file>   Write all data of Rasopus to an archive
+  restore <file>  Restore an archive into an empty database";
+
+/// What Rasopus was started to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    Backup(PathBuf),
+    Restore(PathBuf),
+}
+
+#[derive(Debug, Error)]
+pub enum ParseCommandError {
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+
+    #[error("The {0} command expects exactly one file")]
+    FileExpected(String),
+}
+
+impl Command {
+    /// Parses the command line arguments, without the name of the executable.
+    pub fn parse(args: &[String]) -> Result<Self, ParseCommandError> {
+        match args {
+            [] => Ok(Self::Serve),
+            [command, file] if command == "backup" => Ok(Self::Backup(file.into())),
+            [command, file] if command == "restore" => Ok(Self::Restore(file.into())),
+            [command, ..] if command == "backup" || command == "restore" => {
+                Err(ParseCommandError::FileExpected(command.clone()))
+            }
+            [command, ..] => Err(ParseCommandError::UnknownCommand(command.clone())),
+        }
+    }
+}
@@ -1,4 +1,11 @@
+pub mod ldap_auth_backend;
+pub mod login_throttle_service;
+pub mod oidc_service;
 pub mod postgres;
 pub mod rasopus;
 pub mod rocket;
+pub mod session_service;
+pub mod setup_service;
+pub mod trash_service;
+pub mod two_factor_service;
 pub mod user_service;
@@ -0,0 +1,59 @@
+use rocket::serde::{Deserialize, Serialize};
+
+use super::rasopus::RasopusConfig;
+
+const DEFAULT_USERNAME_ATTRIBUTE: &str = "uid";
+const DEFAULT_GROUP_FILTER: &str = "(|(member={dn})(uniqueMember={dn}))";
+const DEFAULT_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LdapAuthBackendConfig {
+    pub url: Option<String>,
+    pub starttls: bool,
+    pub user_dn_template: Option<String>,
+    pub username_attribute: String,
+    pub group_base_dn: Option<String>,
+    pub group_filter: String,
+    pub admin_groups: Vec<String>,
+    pub user_groups: Vec<String>,
+    pub timeout_secs: u64,
+}
+
+impl From<RasopusConfig> for LdapAuthBackendConfig {
+    fn from(value: RasopusConfig) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl From<&RasopusConfig> for LdapAuthBackendConfig {
+    fn from(value: &RasopusConfig) -> Self {
+        Self {
+            url: value.ldap_url.clone(),
+            starttls: value.ldap_starttls.unwrap_or(false),
+            user_dn_template: value.ldap_user_dn_template.clone(),
+            username_attribute: value
+                .ldap_username_attribute
+                .clone()
+                .unwrap_or(DEFAULT_USERNAME_ATTRIBUTE.to_string()),
+            group_base_dn: value.ldap_group_base_dn.clone(),
+            group_filter: value
+                .ldap_group_filter
+                .clone()
+                .unwrap_or(DEFAULT_GROUP_FILTER.to_string()),
+            admin_groups: split_list(value.ldap_admin_groups.as_deref()),
+            user_groups: split_list(value.ldap_user_groups.as_deref()),
+            timeout_secs: value.ldap_timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS),
+        }
+    }
+}
+
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,56 @@
+use rocket::serde::{Deserialize, Serialize};
+
+use super::rasopus::RasopusConfig;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LoginThrottleServiceConfig {
+    pub window_secs: u64,
+    pub delay_base_ms: u64,
+    pub delay_max_secs: u64,
+    pub lockout_threshold: u32,
+    pub lockout_secs: u64,
+    pub ip_threshold: u32,
+    pub max_concurrent_verifications: usize,
+}
+
+// With the default Argon2 settings, 4 concurrent verifications need at most 280 MiB of memory.
+impl Default for LoginThrottleServiceConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 900,
+            delay_base_ms: 1000,
+            delay_max_secs: 60,
+            lockout_threshold: 10,
+            lockout_secs: 900,
+            ip_threshold: 50,
+            max_concurrent_verifications: 4,
+        }
+    }
+}
+
+impl From<RasopusConfig> for LoginThrottleServiceConfig {
+    fn from(value: RasopusConfig) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl From<&RasopusConfig> for LoginThrottleServiceConfig {
+    fn from(value: &RasopusConfig) -> Self {
+        let default = Self::default();
+
+        Self {
+            window_secs: value.login_window_secs.unwrap_or(default.window_secs),
+            delay_base_ms: value.login_delay_base_ms.unwrap_or(default.delay_base_ms),
+            delay_max_secs: value.login_delay_max_secs.unwrap_or(default.delay_max_secs),
+            lockout_threshold: value
+                .login_lockout_threshold
+                .unwrap_or(default.lockout_threshold),
+            lockout_secs: value.login_lockout_secs.unwrap_or(default.lockout_secs),
+            ip_threshold: value.login_ip_threshold.unwrap_or(default.ip_threshold),
+            max_concurrent_verifications: value
+                .login_max_concurrent_verifications
+                .unwrap_or(default.max_concurrent_verifications),
+        }
+    }
+}
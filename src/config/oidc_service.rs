@@ -0,0 +1,57 @@
+use rocket::serde::{Deserialize, Serialize};
+
+use super::rasopus::RasopusConfig;
+
+const DEFAULT_SCOPES: &str = "openid profile email";
+const DEFAULT_USERNAME_CLAIM: &str = "preferred_username";
+const DEFAULT_FLOW_TIMEOUT_SECS: u64 = 600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct OidcServiceConfig {
+    pub issuer_url: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub redirect_url: Option<String>,
+    pub scopes: Vec<String>,
+    pub username_claim: String,
+    pub default_role: Option<String>,
+    pub role_claim: Option<String>,
+    pub role_mapping: Option<String>,
+    pub flow_timeout_secs: u64,
+}
+
+impl From<RasopusConfig> for OidcServiceConfig {
+    fn from(value: RasopusConfig) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl From<&RasopusConfig> for OidcServiceConfig {
+    fn from(value: &RasopusConfig) -> Self {
+        Self {
+            issuer_url: value.oidc_issuer_url.clone(),
+            client_id: value.oidc_client_id.clone(),
+            client_secret: value.oidc_client_secret.clone(),
+            redirect_url: value.oidc_redirect_url.clone(),
+            scopes: value
+                .oidc_scopes
+                .as_deref()
+                .unwrap_or(DEFAULT_SCOPES)
+                .split([' ', ','])
+                .filter(|scope| !scope.is_empty())
+                .map(str::to_string)
+                .collect(),
+            username_claim: value
+                .oidc_username_claim
+                .clone()
+                .unwrap_or(DEFAULT_USERNAME_CLAIM.to_string()),
+            default_role: value.oidc_default_role.clone(),
+            role_claim: value.oidc_role_claim.clone(),
+            role_mapping: value.oidc_role_mapping.clone(),
+            flow_timeout_secs: value
+                .oidc_flow_timeout_secs
+                .unwrap_or(DEFAULT_FLOW_TIMEOUT_SECS),
+        }
+    }
+}
@@ -1,8 +1,17 @@
+use std::time::Duration;
+
 use rocket::serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use super::rasopus::RasopusConfig;
 
 const DEFAULT_POSTGRES_POOL_SIZE: u32 = 10;
+const DEFAULT_POSTGRES_MIN_CONNECTIONS: u32 = 0;
+const DEFAULT_POSTGRES_ACQUIRE_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_POSTGRES_IDLE_TIMEOUT_SECS: u64 = 600;
+const DEFAULT_POSTGRES_MAX_LIFETIME_SECS: u64 = 1800;
+const DEFAULT_POSTGRES_CONNECT_BACKOFF_MS: u64 = 500;
+const DEFAULT_POSTGRES_CONNECT_MAX_WAIT_SECS: u64 = 60;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
@@ -13,6 +22,13 @@ pub struct PostgresConfig {
     pub port: u16,
     pub database: String,
     pub pool_size: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+    pub max_lifetime_secs: u64,
+    pub statement_timeout_ms: Option<u64>,
+    pub connect_backoff_ms: u64,
+    pub connect_max_wait_secs: u64,
 }
 
 impl PostgresConfig {
@@ -22,26 +38,60 @@ impl PostgresConfig {
             self.user, self.password, self.host, self.port, self.database
         )
     }
-}
 
-impl From<RasopusConfig> for PostgresConfig {
-    fn from(environment_config: RasopusConfig) -> Self {
-        Self {
-            user: environment_config.postgres_user,
-            password: environment_config.postgres_password,
-            host: environment_config.postgres_host,
-            port: environment_config.postgres_port,
-            database: environment_config.postgres_database,
-            pool_size: environment_config
-                .postgres_pool_size
-                .unwrap_or(DEFAULT_POSTGRES_POOL_SIZE),
+    pub fn acquire_timeout(&self) -> Duration {
+        Duration::from_secs(self.acquire_timeout_secs)
+    }
+
+    // A value of 0 disables the timeout
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        match self.idle_timeout_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    // A value of 0 disables the timeout
+    pub fn max_lifetime(&self) -> Option<Duration> {
+        match self.max_lifetime_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
         }
     }
+
+    pub fn connect_backoff(&self) -> Duration {
+        Duration::from_millis(self.connect_backoff_ms)
+    }
+
+    pub fn connect_max_wait(&self) -> Duration {
+        Duration::from_secs(self.connect_max_wait_secs)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PostgresConfigError {
+    #[error(
+        "RASOPUS_POSTGRES_MIN_CONNECTIONS ({min_connections}) must not be greater than RASOPUS_POSTGRES_POOL_SIZE ({pool_size})"
+    )]
+    MinConnectionsExceedPoolSize {
+        min_connections: u32,
+        pool_size: u32,
+    },
+}
+
+impl TryFrom<RasopusConfig> for PostgresConfig {
+    type Error = PostgresConfigError;
+
+    fn try_from(environment_config: RasopusConfig) -> Result<Self, Self::Error> {
+        Self::try_from(&environment_config)
+    }
 }
 
-impl From<&RasopusConfig> for PostgresConfig {
-    fn from(environment_config: &RasopusConfig) -> Self {
-        Self {
+impl TryFrom<&RasopusConfig> for PostgresConfig {
+    type Error = PostgresConfigError;
+
+    fn try_from(environment_config: &RasopusConfig) -> Result<Self, Self::Error> {
+        let postgres_config = Self {
             user: environment_config.postgres_user.clone(),
             password: environment_config.postgres_password.clone(),
             host: environment_config.postgres_host.clone(),
@@ -50,6 +100,34 @@ impl From<&RasopusConfig> for PostgresConfig {
             pool_size: environment_config
                 .postgres_pool_size
                 .unwrap_or(DEFAULT_POSTGRES_POOL_SIZE),
+            min_connections: environment_config
+                .postgres_min_connections
+                .unwrap_or(DEFAULT_POSTGRES_MIN_CONNECTIONS),
+            acquire_timeout_secs: environment_config
+                .postgres_acquire_timeout_secs
+                .unwrap_or(DEFAULT_POSTGRES_ACQUIRE_TIMEOUT_SECS),
+            idle_timeout_secs: environment_config
+                .postgres_idle_timeout_secs
+                .unwrap_or(DEFAULT_POSTGRES_IDLE_TIMEOUT_SECS),
+            max_lifetime_secs: environment_config
+                .postgres_max_lifetime_secs
+                .unwrap_or(DEFAULT_POSTGRES_MAX_LIFETIME_SECS),
+            statement_timeout_ms: environment_config.postgres_statement_timeout_ms,
+            connect_backoff_ms: environment_config
+                .postgres_connect_backoff_ms
+                .unwrap_or(DEFAULT_POSTGRES_CONNECT_BACKOFF_MS),
+            connect_max_wait_secs: environment_config
+                .postgres_connect_max_wait_secs
+                .unwrap_or(DEFAULT_POSTGRES_CONNECT_MAX_WAIT_SECS),
+        };
+
+        if postgres_config.min_connections > postgres_config.pool_size {
+            return Err(PostgresConfigError::MinConnectionsExceedPoolSize {
+                min_connections: postgres_config.min_connections,
+                pool_size: postgres_config.pool_size,
+            });
         }
+
+        Ok(postgres_config)
     }
 }
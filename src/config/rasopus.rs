@@ -12,6 +12,7 @@ pub struct RasopusConfig {
     pub address: Option<String>,
     pub port: Option<u16>,
     pub secret_key: String,
+    pub ip_header: Option<String>,
 
     //Postgres
     pub postgres_user: String,
@@ -20,10 +21,71 @@ pub struct RasopusConfig {
     pub postgres_port: u16,
     pub postgres_database: String,
     pub postgres_pool_size: Option<u32>,
+    pub postgres_min_connections: Option<u32>,
+    pub postgres_acquire_timeout_secs: Option<u64>,
+    pub postgres_idle_timeout_secs: Option<u64>,
+    pub postgres_max_lifetime_secs: Option<u64>,
+    pub postgres_statement_timeout_ms: Option<u64>,
+    pub postgres_connect_backoff_ms: Option<u64>,
+    pub postgres_connect_max_wait_secs: Option<u64>,
+
+    //LoginThrottleService
+    pub login_window_secs: Option<u64>,
+    pub login_delay_base_ms: Option<u64>,
+    pub login_delay_max_secs: Option<u64>,
+    pub login_lockout_threshold: Option<u32>,
+    pub login_lockout_secs: Option<u64>,
+    pub login_ip_threshold: Option<u32>,
+    pub login_max_concurrent_verifications: Option<usize>,
+
+    //OidcService
+    pub oidc_issuer_url: Option<String>,
+    pub oidc_client_id: Option<String>,
+    pub oidc_client_secret: Option<String>,
+    pub oidc_redirect_url: Option<String>,
+    pub oidc_scopes: Option<String>,
+    pub oidc_username_claim: Option<String>,
+    pub oidc_default_role: Option<String>,
+    pub oidc_role_claim: Option<String>,
+    pub oidc_role_mapping: Option<String>,
+    pub oidc_flow_timeout_secs: Option<u64>,
+
+    //SessionService
+    pub session_lifetime_secs: Option<u64>,
+
+    //SetupService
+    pub setup_token: Option<String>,
+
+    //TrashService
+    pub trash_retention_secs: Option<u64>,
+    pub trash_purge_interval_secs: Option<u64>,
+
+    //TwoFactorService
+    pub totp_issuer: Option<String>,
+    pub totp_recovery_codes: Option<usize>,
+    pub totp_pending_login_secs: Option<u64>,
 
     //UserService
+    pub auth_backends: Option<String>,
     pub argon2_iterations: Option<u32>,
     pub argon2_memory_mib: Option<u32>,
+    pub password_min_length: Option<usize>,
+    pub password_max_length: Option<usize>,
+    pub password_require_lowercase: Option<bool>,
+    pub password_require_uppercase: Option<bool>,
+    pub password_require_digit: Option<bool>,
+    pub password_require_symbol: Option<bool>,
+    pub password_min_strength: Option<u8>,
+    pub password_breached_list: Option<String>,
+    pub ldap_url: Option<String>,
+    pub ldap_starttls: Option<bool>,
+    pub ldap_user_dn_template: Option<String>,
+    pub ldap_username_attribute: Option<String>,
+    pub ldap_group_base_dn: Option<String>,
+    pub ldap_group_filter: Option<String>,
+    pub ldap_admin_groups: Option<String>,
+    pub ldap_user_groups: Option<String>,
+    pub ldap_timeout_secs: Option<u64>,
 }
 
 impl RasopusConfig {
@@ -11,6 +11,10 @@ pub struct RocketConfig {
     pub address: Option<String>,
     pub port: Option<u16>,
     pub secret_key: String,
+
+    /// The header a trusted reverse proxy puts the IP address of the client in. Clients can set any header, so it
+    /// is ignored unless it is configured.
+    pub ip_header: Option<String>,
 }
 
 impl RocketConfig {
@@ -25,6 +29,12 @@ impl RocketConfig {
 
         rocket_figment = rocket_figment.merge(("secret_key", self.secret_key));
 
+        // Rocket trusts X-Real-IP by default, which would let clients choose the IP address they are throttled by
+        rocket_figment = match &self.ip_header {
+            Some(ip_header) => rocket_figment.merge(("ip_header", ip_header)),
+            None => rocket_figment.merge(("ip_header", false)),
+        };
+
         rocket_figment
     }
 }
@@ -35,6 +45,7 @@ impl From<RasopusConfig> for RocketConfig {
             address: environment_config.address,
             port: environment_config.port,
             secret_key: environment_config.secret_key,
+            ip_header: environment_config.ip_header,
         }
     }
 }
@@ -45,6 +56,7 @@ impl From<&RasopusConfig> for RocketConfig {
             address: environment_config.address.clone(),
             port: environment_config.port,
             secret_key: environment_config.secret_key.clone(),
+            ip_header: environment_config.ip_header.clone(),
         }
     }
 }
@@ -0,0 +1,27 @@
+use rocket::serde::{Deserialize, Serialize};
+
+use super::rasopus::RasopusConfig;
+
+const DEFAULT_SESSION_LIFETIME_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SessionServiceConfig {
+    pub lifetime_secs: u64,
+}
+
+impl From<RasopusConfig> for SessionServiceConfig {
+    fn from(value: RasopusConfig) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl From<&RasopusConfig> for SessionServiceConfig {
+    fn from(value: &RasopusConfig) -> Self {
+        Self {
+            lifetime_secs: value
+                .session_lifetime_secs
+                .unwrap_or(DEFAULT_SESSION_LIFETIME_SECS),
+        }
+    }
+}
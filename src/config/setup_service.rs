@@ -0,0 +1,25 @@
+use rocket::serde::{Deserialize, Serialize};
+
+use super::rasopus::RasopusConfig;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SetupServiceConfig {
+    pub setup_token: Option<String>,
+}
+
+impl From<RasopusConfig> for SetupServiceConfig {
+    fn from(value: RasopusConfig) -> Self {
+        Self {
+            setup_token: value.setup_token,
+        }
+    }
+}
+
+impl From<&RasopusConfig> for SetupServiceConfig {
+    fn from(value: &RasopusConfig) -> Self {
+        Self {
+            setup_token: value.setup_token.clone(),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use rocket::serde::{Deserialize, Serialize};
+
+use super::rasopus::RasopusConfig;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TrashServiceConfig {
+    pub retention_secs: u64,
+    pub purge_interval_secs: u64,
+}
+
+// Deleted entities can be restored for 30 days
+impl Default for TrashServiceConfig {
+    fn default() -> Self {
+        Self {
+            retention_secs: 30 * 24 * 60 * 60,
+            purge_interval_secs: 60 * 60,
+        }
+    }
+}
+
+impl From<RasopusConfig> for TrashServiceConfig {
+    fn from(value: RasopusConfig) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl From<&RasopusConfig> for TrashServiceConfig {
+    fn from(value: &RasopusConfig) -> Self {
+        let default = Self::default();
+
+        Self {
+            retention_secs: value.trash_retention_secs.unwrap_or(default.retention_secs),
+            purge_interval_secs: value
+                .trash_purge_interval_secs
+                .unwrap_or(default.purge_interval_secs),
+        }
+    }
+}
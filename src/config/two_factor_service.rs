@@ -0,0 +1,40 @@
+use rocket::serde::{Deserialize, Serialize};
+
+use super::rasopus::RasopusConfig;
+
+const DEFAULT_TOTP_ISSUER: &str = "Rasopus";
+const DEFAULT_RECOVERY_CODE_COUNT: usize = 10;
+const DEFAULT_PENDING_LOGIN_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TwoFactorServiceConfig {
+    pub secret_key: String,
+    pub issuer: String,
+    pub recovery_code_count: usize,
+    pub pending_login_secs: u64,
+}
+
+impl From<RasopusConfig> for TwoFactorServiceConfig {
+    fn from(value: RasopusConfig) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl From<&RasopusConfig> for TwoFactorServiceConfig {
+    fn from(value: &RasopusConfig) -> Self {
+        Self {
+            secret_key: value.secret_key.clone(),
+            issuer: value
+                .totp_issuer
+                .clone()
+                .unwrap_or(DEFAULT_TOTP_ISSUER.to_string()),
+            recovery_code_count: value
+                .totp_recovery_codes
+                .unwrap_or(DEFAULT_RECOVERY_CODE_COUNT),
+            pending_login_secs: value
+                .totp_pending_login_secs
+                .unwrap_or(DEFAULT_PENDING_LOGIN_SECS),
+        }
+    }
+}
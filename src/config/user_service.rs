@@ -1,12 +1,17 @@
 use rocket::serde::{Deserialize, Serialize};
 
-use super::rasopus::RasopusConfig;
+use crate::validation::password::PasswordPolicy;
+
+use super::{ldap_auth_backend::LdapAuthBackendConfig, rasopus::RasopusConfig};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct UserServiceConfig {
     pub argon2_iterations: u32,
     pub argon2_memory_mib: u32,
+    pub password_policy: PasswordPolicy,
+    pub auth_backends: Vec<String>,
+    pub ldap: Option<LdapAuthBackendConfig>,
 }
 
 // This is way above OWASP's 2025 recommendations, so should be fine :)
@@ -16,28 +21,72 @@ impl Default for UserServiceConfig {
         Self {
             argon2_iterations: 3,
             argon2_memory_mib: 70,
+            password_policy: PasswordPolicy::default(),
+            auth_backends: vec!["password".to_string()],
+            ldap: None,
         }
     }
 }
 
 impl From<RasopusConfig> for UserServiceConfig {
     fn from(value: RasopusConfig) -> Self {
-        let default = Self::default();
-
-        Self {
-            argon2_iterations: value.argon2_iterations.unwrap_or(default.argon2_iterations),
-            argon2_memory_mib: value.argon2_memory_mib.unwrap_or(default.argon2_memory_mib),
-        }
+        Self::from(&value)
     }
 }
 
 impl From<&RasopusConfig> for UserServiceConfig {
     fn from(value: &RasopusConfig) -> Self {
         let default = Self::default();
+        let default_policy = default.password_policy;
+
+        // When LDAP is configured, it is consulted before the local passwords by default
+        let ldap = value
+            .ldap_url
+            .is_some()
+            .then(|| LdapAuthBackendConfig::from(value));
+        let auth_backends = match (&value.auth_backends, &ldap) {
+            (Some(auth_backends), _) => auth_backends
+                .split(',')
+                .map(str::trim)
+                .filter(|auth_backend| !auth_backend.is_empty())
+                .map(str::to_string)
+                .collect(),
+            (None, Some(_)) => vec!["ldap".to_string(), "password".to_string()],
+            (None, None) => default.auth_backends,
+        };
 
         Self {
             argon2_iterations: value.argon2_iterations.unwrap_or(default.argon2_iterations),
             argon2_memory_mib: value.argon2_memory_mib.unwrap_or(default.argon2_memory_mib),
+            password_policy: PasswordPolicy {
+                min_length: value
+                    .password_min_length
+                    .unwrap_or(default_policy.min_length),
+                max_length: value
+                    .password_max_length
+                    .unwrap_or(default_policy.max_length),
+                require_lowercase: value
+                    .password_require_lowercase
+                    .unwrap_or(default_policy.require_lowercase),
+                require_uppercase: value
+                    .password_require_uppercase
+                    .unwrap_or(default_policy.require_uppercase),
+                require_digit: value
+                    .password_require_digit
+                    .unwrap_or(default_policy.require_digit),
+                require_symbol: value
+                    .password_require_symbol
+                    .unwrap_or(default_policy.require_symbol),
+                min_strength: value
+                    .password_min_strength
+                    .unwrap_or(default_policy.min_strength),
+                breached_passwords_file: value
+                    .password_breached_list
+                    .clone()
+                    .or(default_policy.breached_passwords_file),
+            },
+            auth_backends,
+            ldap,
         }
     }
 }
@@ -1,8 +1,49 @@
-use rocket::Route;
+use rocket::{
+    Request, Route,
+    response::Responder,
+    serde::{Serialize, json::Json},
+};
 use rocket_okapi::openapi_get_routes;
 
+use crate::model::payload::page::Page;
+
+pub mod audit;
+pub mod guard;
+pub mod login;
+pub mod oidc;
 pub mod setup;
+pub mod two_factor;
+pub mod user;
 
 pub fn openapi_get_routes() -> Vec<Route> {
-    openapi_get_routes![setup::setup_get, setup::setup_post]
+    openapi_get_routes![
+        setup::setup_get,
+        setup::setup_post,
+        login::login_post,
+        login::login_two_factor_post,
+        login::logout_post,
+        oidc::oidc_get,
+        oidc::oidc_callback_get,
+        oidc::user_oidc_get,
+        two_factor::totp_post,
+        two_factor::totp_confirm_post,
+        two_factor::totp_disable_post,
+        user::users_get,
+        user::users_trash_get,
+        user::users_me_trash_get,
+        user::user_post,
+        user::user_password_put,
+        user::user_role_put,
+        user::user_delete,
+        user::user_restore_post,
+        user::user_lockout_delete,
+        user::user_totp_delete,
+        audit::audit_get,
+    ]
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for Page<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
 }
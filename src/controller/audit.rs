@@ -0,0 +1,121 @@
+use rocket::{
+    Request, State, get,
+    http::Status,
+    response::{Responder, status},
+    serde::json::{Json, serde_json},
+};
+use rocket_okapi::openapi;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    impl_okapi_json_responder,
+    model::{
+        entity::audit_event::{AuditFilter, AuditSortColumn},
+        list::{DEFAULT_PAGE_SIZE, ListQuery, SortDirection, parse_timestamp},
+        payload::audit::{
+            AuditEventResponse, AuditGetErrorResponse, AuditGetQuery, AuditGetResponse,
+        },
+    },
+    service::AuditService,
+};
+
+use super::guard::AdminUser;
+
+/// List the audit log of administrative and security relevant actions, newest first.
+///
+/// For example, `?target_type=user&target_uuid=<uuid>&action=user.role_changed` answers who changed the role of a user.
+#[openapi]
+#[get("/admin/audit?<query..>")]
+pub async fn audit_get(
+    query: AuditGetQuery,
+    _admin: AdminUser,
+    audit_service: &State<AuditService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<AuditGetResponse, AuditGetErrorResponse> {
+    let action = query
+        .action
+        .as_deref()
+        .map(|action| {
+            action
+                .parse()
+                .map_err(|_| AuditGetErrorResponse::InvalidAction(action.to_string()))
+        })
+        .transpose()?;
+
+    let target_type = query
+        .target_type
+        .as_deref()
+        .map(|target_type| {
+            target_type
+                .parse()
+                .map_err(|_| AuditGetErrorResponse::InvalidTargetType(target_type.to_string()))
+        })
+        .transpose()?;
+
+    let filter = AuditFilter {
+        action,
+        actor_uuid: query.actor_uuid,
+        target_type,
+        target_uuid: query.target_uuid,
+        since: parse_timestamp(query.since.as_deref())?,
+        until: parse_timestamp(query.until.as_deref())?,
+    };
+
+    let list_query = ListQuery {
+        filter,
+        sort: AuditSortColumn::OccurredAt,
+        direction: SortDirection::Descending,
+        cursor: query.cursor.as_deref().map(str::parse).transpose()?,
+        limit: query.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+    };
+
+    let (audit_events, total) = audit_service.list(&list_query, postgres_pool).await?;
+
+    Ok(AuditGetResponse::new(audit_events, total))
+}
+
+impl_okapi_json_responder!(AuditGetResponse, {
+    "200" => {
+        description: "The page of the audit log",
+        example: serde_json::json!(AuditGetResponse {
+            items: vec![AuditEventResponse {
+                uuid: uuid::Uuid::nil(),
+                action: "user.role_changed".to_string(),
+                actor_uuid: Some(uuid::Uuid::nil()),
+                target_type: Some("user".to_string()),
+                target_uuid: Some(uuid::Uuid::nil()),
+                before: Some(serde_json::json!({ "uuid": uuid::Uuid::nil(), "username": "alice", "role": "User", "created_at": "2025-01-01T00:00:00+00:00" })),
+                after: Some(serde_json::json!({ "uuid": uuid::Uuid::nil(), "username": "alice", "role": "Admin", "created_at": "2025-01-01T00:00:00+00:00" })),
+                ip_address: Some("127.0.0.1".to_string()),
+                occurred_at: "2025-01-01T00:15:00+00:00".to_string(),
+            }],
+            next_cursor: Some("eyJzb3J0Ijoib2NjdXJyZWRfYXQiLCJ2YWx1ZSI6eyJUaW1lc3RhbXAiOiIyMDI1LTAxLTAxVDAwOjE1OjAwWiJ9LCJpZGVudGlmaWVyIjp7IlV1aWQiOiIwMDAwMDAwMC0wMDAwLTAwMDAtMDAwMC0wMDAwMDAwMDAwMDAifX0".to_string()),
+            total: 120,
+        }),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for AuditGetErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::InvalidAction(_) => Status::UnprocessableEntity.code,
+            Self::InvalidTargetType(_) => Status::UnprocessableEntity.code,
+            Self::InvalidTimestamp(_) => Status::UnprocessableEntity.code,
+            Self::InvalidCursor(_) => Status::UnprocessableEntity.code,
+            Self::AuditServiceList(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(AuditGetErrorResponse, {
+    "422" => {
+        description: "A filter or the cursor is invalid, for example an unknown action.",
+        example: serde_json::json!(AuditGetErrorResponse::InvalidAction("user.renamed".to_string())),
+    },
+    "500" => {
+        description: "The audit log could not be listed.",
+        example: serde_json::json!(AuditGetErrorResponse::AuditServiceList("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
@@ -0,0 +1,421 @@
+use std::net::IpAddr;
+
+use chrono::Utc;
+use okapi::openapi3::{Object, SecurityRequirement, SecurityScheme, SecuritySchemeData};
+use rocket::{
+    Request,
+    http::{Cookie, CookieJar, SameSite, Status},
+    outcome::try_outcome,
+    request::{FromRequest, Outcome},
+};
+use rocket_okapi::{
+    r#gen::OpenApiGenerator,
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+use sqlx::{PgExecutor, Pool, Postgres};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    model::entity::{
+        audit_event::{AuditAction, AuditEvent},
+        session::Session,
+        user::{Role, User},
+    },
+    service::{
+        AuditService, SessionService, TwoFactorService, UserService,
+        audit::{AuditRecord, RecordError},
+        session,
+        user::LoadError,
+    },
+};
+
+/// The name of the private cookie holding the UUID of the session of the logged in user.
+pub const SESSION_COOKIE_NAME: &str = "rasopus_session";
+
+/// The name of the private cookie holding the UUID of a user who entered their password, but not their second factor
+/// yet, and when they entered their password.
+pub const PENDING_TWO_FACTOR_COOKIE_NAME: &str = "rasopus_pending_two_factor";
+
+const SESSION_SECURITY_SCHEME_NAME: &str = "session";
+const PENDING_TWO_FACTOR_SECURITY_SCHEME_NAME: &str = "pending_two_factor";
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("Not logged in")]
+    NotLoggedIn,
+
+    #[error("The session is invalid")]
+    InvalidSession,
+
+    #[error("The session expired or was revoked")]
+    SessionEnded,
+
+    #[error("The user of the session does not exist anymore")]
+    UserNotFound,
+
+    #[error("The session service returned an error while loading the session: {0}")]
+    SessionServiceLoad(#[from] session::LoadError),
+
+    #[error("The user service returned an error while loading the user of the session: {0}")]
+    UserServiceLoad(#[from] LoadError),
+
+    #[error("The {0} is not managed by Rocket")]
+    MissingState(&'static str),
+}
+
+#[derive(Debug, Error)]
+pub enum PendingTwoFactorError {
+    #[error("There is no pending login")]
+    NoPendingLogin,
+
+    #[error("The pending login is invalid")]
+    InvalidPendingLogin,
+
+    #[error("The pending login has expired")]
+    Expired,
+
+    #[error("The user of the pending login does not exist anymore")]
+    UserNotFound,
+
+    #[error("The user service returned an error while loading the user of the pending login: {0}")]
+    UserServiceLoad(#[from] LoadError),
+
+    #[error("The {0} is not managed by Rocket")]
+    MissingState(&'static str),
+}
+
+#[derive(Debug, Error)]
+pub enum AdminError {
+    #[error("{0}")]
+    Session(#[from] SessionError),
+
+    #[error("Only admins can do this")]
+    InsufficientRole,
+}
+
+#[derive(Debug, Error)]
+pub enum AuditorError {
+    #[error("The {0} is not managed by Rocket")]
+    MissingState(&'static str),
+}
+
+/// The user who is logged in through the session cookie, and their session.
+#[derive(Debug)]
+pub struct SessionUser(pub User, pub Session);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SessionUser {
+    type Error = SessionError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(cookie) = request.cookies().get_private(SESSION_COOKIE_NAME) else {
+            return Outcome::Error((Status::Unauthorized, SessionError::NotLoggedIn));
+        };
+
+        let Ok(session_uuid) = Uuid::parse_str(cookie.value()) else {
+            return Outcome::Error((Status::Unauthorized, SessionError::InvalidSession));
+        };
+
+        let Some(session_service) = request.rocket().state::<SessionService>() else {
+            return Outcome::Error((
+                Status::InternalServerError,
+                SessionError::MissingState("session service"),
+            ));
+        };
+
+        let Some(user_service) = request.rocket().state::<UserService>() else {
+            return Outcome::Error((
+                Status::InternalServerError,
+                SessionError::MissingState("user service"),
+            ));
+        };
+
+        let Some(postgres_pool) = request.rocket().state::<Pool<Postgres>>() else {
+            return Outcome::Error((
+                Status::InternalServerError,
+                SessionError::MissingState("postgres pool"),
+            ));
+        };
+
+        let session = match session_service
+            .load_active(&session_uuid, postgres_pool)
+            .await
+        {
+            Ok(Some(session)) => session,
+            Ok(None) => {
+                request.cookies().remove_private(SESSION_COOKIE_NAME);
+                return Outcome::Error((Status::Unauthorized, SessionError::SessionEnded));
+            }
+            Err(error) => return Outcome::Error((Status::InternalServerError, error.into())),
+        };
+
+        // Deleted users can't be loaded, so their sessions end with them
+        match user_service.load(&session.user_uuid, postgres_pool).await {
+            Ok(Some(user)) => Outcome::Success(SessionUser(user, session)),
+            Ok(None) => Outcome::Error((Status::Unauthorized, SessionError::UserNotFound)),
+            Err(error) => Outcome::Error((Status::InternalServerError, error.into())),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for SessionUser {
+    fn from_request_input(
+        _generator: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(session_security_input())
+    }
+}
+
+/// The user who is logged in through the session cookie, if they are an admin or the system user.
+#[derive(Debug)]
+pub struct AdminUser(pub User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminUser {
+    type Error = AdminError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let SessionUser(user, _) = try_outcome!(
+            request
+                .guard::<SessionUser>()
+                .await
+                .map_error(|(status, error)| (status, AdminError::Session(error)))
+        );
+
+        if user.role > Role::Admin {
+            return Outcome::Error((Status::Forbidden, AdminError::InsufficientRole));
+        }
+
+        Outcome::Success(AdminUser(user))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for AdminUser {
+    fn from_request_input(
+        _generator: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(session_security_input())
+    }
+}
+
+/// The user who entered their password through `POST /login`, but still has to enter their second factor.
+#[derive(Debug)]
+pub struct PendingTwoFactorUser(pub User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for PendingTwoFactorUser {
+    type Error = PendingTwoFactorError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(cookie) = request
+            .cookies()
+            .get_private(PENDING_TWO_FACTOR_COOKIE_NAME)
+        else {
+            return Outcome::Error((Status::Unauthorized, PendingTwoFactorError::NoPendingLogin));
+        };
+
+        let Some((user_uuid, started_at)) = cookie.value().split_once(':') else {
+            return Outcome::Error((
+                Status::Unauthorized,
+                PendingTwoFactorError::InvalidPendingLogin,
+            ));
+        };
+
+        let (Ok(user_uuid), Ok(started_at)) =
+            (Uuid::parse_str(user_uuid), started_at.parse::<i64>())
+        else {
+            return Outcome::Error((
+                Status::Unauthorized,
+                PendingTwoFactorError::InvalidPendingLogin,
+            ));
+        };
+
+        let Some(two_factor_service) = request.rocket().state::<TwoFactorService>() else {
+            return Outcome::Error((
+                Status::InternalServerError,
+                PendingTwoFactorError::MissingState("two-factor service"),
+            ));
+        };
+
+        // The cookie's max age can't be trusted, as the client decides when to drop it
+        let expires_at = started_at.saturating_add(two_factor_service.pending_login_secs() as i64);
+        if Utc::now().timestamp() >= expires_at {
+            return Outcome::Error((Status::Unauthorized, PendingTwoFactorError::Expired));
+        }
+
+        let Some(user_service) = request.rocket().state::<UserService>() else {
+            return Outcome::Error((
+                Status::InternalServerError,
+                PendingTwoFactorError::MissingState("user service"),
+            ));
+        };
+
+        let Some(postgres_pool) = request.rocket().state::<Pool<Postgres>>() else {
+            return Outcome::Error((
+                Status::InternalServerError,
+                PendingTwoFactorError::MissingState("postgres pool"),
+            ));
+        };
+
+        match user_service.load(&user_uuid, postgres_pool).await {
+            Ok(Some(user)) => Outcome::Success(PendingTwoFactorUser(user)),
+            Ok(None) => Outcome::Error((Status::Unauthorized, PendingTwoFactorError::UserNotFound)),
+            Err(error) => Outcome::Error((Status::InternalServerError, error.into())),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for PendingTwoFactorUser {
+    fn from_request_input(
+        _generator: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        let security_scheme = SecurityScheme {
+            description: Some(
+                "The private cookie that is set by `POST /login` when a second factor is required."
+                    .to_string(),
+            ),
+            data: SecuritySchemeData::ApiKey {
+                name: PENDING_TWO_FACTOR_COOKIE_NAME.to_string(),
+                location: "cookie".to_string(),
+            },
+            extensions: Object::default(),
+        };
+
+        let mut security_requirement = SecurityRequirement::new();
+        security_requirement.insert(
+            PENDING_TWO_FACTOR_SECURITY_SCHEME_NAME.to_string(),
+            Vec::new(),
+        );
+
+        Ok(RequestHeaderInput::Security(
+            PENDING_TWO_FACTOR_SECURITY_SCHEME_NAME.to_string(),
+            security_scheme,
+            security_requirement,
+        ))
+    }
+}
+
+/// Records audit events for the request, filling in the IP address the request came from.
+#[derive(Debug)]
+pub struct Auditor<'r> {
+    audit_service: &'r AuditService,
+
+    /// The IP address the request came from, if known.
+    pub ip_address: Option<IpAddr>,
+}
+
+impl Auditor<'_> {
+    pub fn audit_service(&self) -> &AuditService {
+        self.audit_service
+    }
+
+    pub async fn record<'e, E>(
+        &self,
+        action: AuditAction,
+        record: AuditRecord,
+        executor: E,
+    ) -> Result<AuditEvent, RecordError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let record = AuditRecord {
+            ip_address: self.ip_address,
+            ..record
+        };
+
+        self.audit_service.record(action, record, executor).await
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Auditor<'r> {
+    type Error = AuditorError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(audit_service) = request.rocket().state::<AuditService>() else {
+            return Outcome::Error((
+                Status::InternalServerError,
+                AuditorError::MissingState("audit service"),
+            ));
+        };
+
+        Outcome::Success(Auditor {
+            audit_service,
+            ip_address: client_ip_address(request),
+        })
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for Auditor<'r> {
+    fn from_request_input(
+        _generator: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// Returns the IP address of the client.
+///
+/// This is the address of the connection, unless a header set by a trusted reverse proxy is configured with
+/// `RASOPUS_IP_HEADER`. Any client can send headers, so they must not be believed otherwise.
+fn client_ip_address(request: &Request<'_>) -> Option<IpAddr> {
+    request
+        .real_ip()
+        .or_else(|| request.remote().map(|remote| remote.ip()))
+}
+
+/// Logs the user of the given session in by setting the session cookie.
+pub fn add_session_cookie(cookies: &CookieJar<'_>, session: &Session) {
+    let max_age = (session.expires_at - Utc::now()).num_seconds().max(0);
+    let cookie = Cookie::build((SESSION_COOKIE_NAME, session.uuid.to_string()))
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .max_age(rocket::time::Duration::seconds(max_age));
+    cookies.add_private(cookie);
+}
+
+/// Remembers that the given user entered their password and still has to enter their second factor.
+pub fn add_pending_two_factor_cookie(
+    cookies: &CookieJar<'_>,
+    user_uuid: &Uuid,
+    pending_login_secs: u64,
+) {
+    let value = format!("{}:{}", user_uuid, Utc::now().timestamp());
+    let cookie = Cookie::build((PENDING_TWO_FACTOR_COOKIE_NAME, value))
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .max_age(rocket::time::Duration::seconds(pending_login_secs as i64));
+    cookies.add_private(cookie);
+}
+
+fn session_security_input() -> RequestHeaderInput {
+    let security_scheme = SecurityScheme {
+        description: Some(
+            "The private session cookie that is set when logging in through `POST /login`."
+                .to_string(),
+        ),
+        data: SecuritySchemeData::ApiKey {
+            name: SESSION_COOKIE_NAME.to_string(),
+            location: "cookie".to_string(),
+        },
+        extensions: Object::default(),
+    };
+
+    let mut security_requirement = SecurityRequirement::new();
+    security_requirement.insert(SESSION_SECURITY_SCHEME_NAME.to_string(), Vec::new());
+
+    RequestHeaderInput::Security(
+        SESSION_SECURITY_SCHEME_NAME.to_string(),
+        security_scheme,
+        security_requirement,
+    )
+}
@@ -0,0 +1,440 @@
+use rocket::{
+    Request, State,
+    http::{CookieJar, Header, Status},
+    post,
+    response::{Responder, status},
+    serde::json::{Json, serde_json},
+};
+use rocket_okapi::openapi;
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{
+    impl_okapi_json_responder,
+    model::{
+        entity::audit_event::{AuditAction, AuditTargetType},
+        payload::login::{
+            LoginPostErrorResponse, LoginPostPayload, LoginPostResponse,
+            LoginTwoFactorPostErrorResponse, LoginTwoFactorPostPayload, LoginTwoFactorPostResponse,
+            LogoutPostErrorResponse, LogoutPostResponse,
+        },
+    },
+    service::{
+        LoginThrottleService, SessionService, TwoFactorService, UnitOfWork, UserService,
+        audit::AuditRecord, two_factor::VerifyError, user::Authenticated,
+    },
+    validation::username,
+};
+
+use super::guard::{
+    Auditor, PENDING_TWO_FACTOR_COOKIE_NAME, PendingTwoFactorUser, SESSION_COOKIE_NAME,
+    add_pending_two_factor_cookie, add_session_cookie,
+};
+
+/// Log in with a username and password.
+///
+/// If the user has two-factor authentication enabled, the login has to be completed through `POST /login/two-factor`.
+#[openapi]
+#[post("/login", data = "<payload>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn login_post(
+    payload: Json<LoginPostPayload>,
+    auditor: Auditor<'_>,
+    cookies: &CookieJar<'_>,
+    user_service: &State<UserService>,
+    login_throttle_service: &State<LoginThrottleService>,
+    two_factor_service: &State<TwoFactorService>,
+    session_service: &State<SessionService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<LoginPostResponse, LoginPostErrorResponse> {
+    let payload = payload.into_inner();
+    let canonical_username = username::canonicalize(&payload.username);
+    let ip_address = auditor.ip_address;
+
+    let user = user_service
+        .load_by_username(&payload.username, postgres_pool.inner())
+        .await?;
+
+    let attempt = login_throttle_service
+        .check(
+            &canonical_username,
+            user.as_ref(),
+            ip_address,
+            postgres_pool,
+        )
+        .await?;
+
+    let authenticated = {
+        let _permit = login_throttle_service.acquire_verification_permit().await?;
+        user_service
+            .authenticate(
+                &payload.username,
+                &payload.password,
+                user.as_ref(),
+                auditor.audit_service(),
+                postgres_pool,
+            )
+            .await
+    };
+
+    let user = match (authenticated, user) {
+        (Ok(Some(Authenticated::Local)), Some(user)) => user,
+        (Ok(Some(Authenticated::External(user))), _) => user,
+        (authenticated, user) => {
+            // Errors count as failed attempts too, so an unreachable backend can't be used to guess without limits
+            let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+            login_throttle_service
+                .fail(attempt, unit_of_work.connection())
+                .await?;
+
+            auditor
+                .record(
+                    AuditAction::LoginFailed,
+                    AuditRecord {
+                        target: user
+                            .as_ref()
+                            .map(|user| (AuditTargetType::User, user.uuid)),
+                        after: Some(
+                            serde_json::json!({ "username": canonical_username, "method": "password" }),
+                        ),
+                        ..Default::default()
+                    },
+                    unit_of_work.connection(),
+                )
+                .await?;
+
+            unit_of_work.commit().await?;
+
+            authenticated?;
+            return Err(LoginPostErrorResponse::InvalidCredentials);
+        }
+    };
+
+    // The attempt is only recorded as successful once the second factor was entered too, as a successful attempt
+    // resets the failed attempts
+    if two_factor_service.is_enabled(&user, postgres_pool).await? {
+        login_throttle_service
+            .discard(attempt, postgres_pool.inner())
+            .await?;
+        add_pending_two_factor_cookie(cookies, &user.uuid, two_factor_service.pending_login_secs());
+
+        return Ok(LoginPostResponse {
+            uuid: user.uuid,
+            two_factor_required: true,
+        });
+    }
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    login_throttle_service
+        .succeed(attempt, unit_of_work.connection())
+        .await?;
+
+    let session = session_service
+        .issue(&user, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::LoginSucceeded,
+            AuditRecord {
+                actor_uuid: Some(user.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                after: Some(
+                    serde_json::json!({ "method": "password", "session_uuid": session.uuid }),
+                ),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    add_session_cookie(cookies, &session);
+
+    Ok(LoginPostResponse {
+        uuid: user.uuid,
+        two_factor_required: false,
+    })
+}
+
+impl<'r> Responder<'r, 'static> for LoginPostResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(LoginPostResponse, {
+    "200" => {
+        description: "The username and password are correct. Unless a second factor is required, the session cookie was set",
+        example: serde_json::json!(LoginPostResponse { uuid: uuid::Uuid::nil(), two_factor_required: false }),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for LoginPostErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::InvalidCredentials => Status::Unauthorized.code,
+            Self::Locked(_) => Status::Locked.code,
+            Self::TooManyAttempts(_) => Status::TooManyRequests.code,
+            Self::VerificationUnavailable => Status::ServiceUnavailable.code,
+            Self::LoginThrottleCheck(_) => Status::InternalServerError.code,
+            Self::LoginThrottleRecord(_) => Status::InternalServerError.code,
+            Self::UserServiceLoad(_) => Status::InternalServerError.code,
+            Self::UserServiceAuthenticate(_) => Status::InternalServerError.code,
+            Self::TwoFactorServiceEnabled(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::SessionServiceIssue(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        let retry_after = match self {
+            Self::TooManyAttempts(retry_after) => Some(retry_after),
+            _ => None,
+        };
+
+        let mut response =
+            status::Custom(Status::new(status_code), Json(self)).respond_to(request)?;
+        if let Some(retry_after) = retry_after {
+            response.set_header(Header::new("Retry-After", retry_after.to_string()));
+        }
+
+        Ok(response)
+    }
+}
+
+impl_okapi_json_responder!(LoginPostErrorResponse, {
+    "401" => {
+        description: "The username or password is wrong.",
+        example: serde_json::json!(LoginPostErrorResponse::InvalidCredentials),
+    },
+    "423" => {
+        description: "The user is locked after too many failed login attempts.",
+        example: serde_json::json!(LoginPostErrorResponse::Locked("2025-01-01T00:15:00+00:00".to_string())),
+    },
+    "429" => {
+        description: "There were too many failed login attempts. The Retry-After header contains the number of seconds to wait.",
+        example: serde_json::json!(LoginPostErrorResponse::TooManyAttempts(8)),
+    },
+    "500" => {
+        description: "The login could not be processed.",
+        example: serde_json::json!(LoginPostErrorResponse::UserServiceLoad("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+    "503" => {
+        description: "The password can't be verified right now.",
+        example: serde_json::json!(LoginPostErrorResponse::VerificationUnavailable),
+    },
+});
+
+/// Complete a login with a code from the authenticator app or a recovery code.
+#[openapi]
+#[post("/login/two-factor", data = "<payload>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn login_two_factor_post(
+    payload: Json<LoginTwoFactorPostPayload>,
+    pending_user: PendingTwoFactorUser,
+    auditor: Auditor<'_>,
+    cookies: &CookieJar<'_>,
+    login_throttle_service: &State<LoginThrottleService>,
+    two_factor_service: &State<TwoFactorService>,
+    session_service: &State<SessionService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<LoginTwoFactorPostResponse, LoginTwoFactorPostErrorResponse> {
+    let payload = payload.into_inner();
+    let PendingTwoFactorUser(user) = pending_user;
+    let canonical_username = username::canonicalize(&user.username);
+    let ip_address = auditor.ip_address;
+
+    let attempt = login_throttle_service
+        .check(&canonical_username, Some(&user), ip_address, postgres_pool)
+        .await?;
+
+    // Retrying can't help when the secret can't be decrypted, so it doesn't count as a failed attempt
+    let verified = match two_factor_service
+        .verify(&user, &payload.code, postgres_pool)
+        .await
+    {
+        Err(error @ VerifyError::SecretUnreadable) => {
+            login_throttle_service
+                .discard(attempt, postgres_pool.inner())
+                .await?;
+            return Err(error.into());
+        }
+        verified => verified?,
+    };
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    if !verified {
+        login_throttle_service
+            .fail(attempt, unit_of_work.connection())
+            .await?;
+
+        auditor
+            .record(
+                AuditAction::LoginFailed,
+                AuditRecord {
+                    target: Some((AuditTargetType::User, user.uuid)),
+                    after: Some(
+                        serde_json::json!({ "username": canonical_username, "method": "two_factor" }),
+                    ),
+                    ..Default::default()
+                },
+                unit_of_work.connection(),
+            )
+            .await?;
+
+        unit_of_work.commit().await?;
+
+        return Err(LoginTwoFactorPostErrorResponse::InvalidCode);
+    }
+
+    login_throttle_service
+        .succeed(attempt, unit_of_work.connection())
+        .await?;
+
+    let session = session_service
+        .issue(&user, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::LoginSucceeded,
+            AuditRecord {
+                actor_uuid: Some(user.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                after: Some(
+                    serde_json::json!({ "method": "two_factor", "session_uuid": session.uuid }),
+                ),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    cookies.remove_private(PENDING_TWO_FACTOR_COOKIE_NAME);
+    add_session_cookie(cookies, &session);
+
+    Ok(LoginTwoFactorPostResponse { uuid: user.uuid })
+}
+
+impl<'r> Responder<'r, 'static> for LoginTwoFactorPostResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(LoginTwoFactorPostResponse, {
+    "200" => {
+        description: "The login was completed and the session cookie was set",
+        example: serde_json::json!(LoginTwoFactorPostResponse { uuid: uuid::Uuid::nil() }),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for LoginTwoFactorPostErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::InvalidCode => Status::Unauthorized.code,
+            Self::TotpSecretUnreadable => Status::Conflict.code,
+            Self::Locked(_) => Status::Locked.code,
+            Self::TooManyAttempts(_) => Status::TooManyRequests.code,
+            Self::LoginThrottleCheck(_) => Status::InternalServerError.code,
+            Self::LoginThrottleRecord(_) => Status::InternalServerError.code,
+            Self::TwoFactorServiceVerify(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::SessionServiceIssue(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        let retry_after = match self {
+            Self::TooManyAttempts(retry_after) => Some(retry_after),
+            _ => None,
+        };
+
+        let mut response =
+            status::Custom(Status::new(status_code), Json(self)).respond_to(request)?;
+        if let Some(retry_after) = retry_after {
+            response.set_header(Header::new("Retry-After", retry_after.to_string()));
+        }
+
+        Ok(response)
+    }
+}
+
+impl_okapi_json_responder!(LoginTwoFactorPostErrorResponse, {
+    "401" => {
+        description: "The code is wrong or was already used.",
+        example: serde_json::json!(LoginTwoFactorPostErrorResponse::InvalidCode),
+    },
+    "409" => {
+        description: "The TOTP secret can't be decrypted, as the secret key was changed. A recovery code still works, otherwise an admin has to reset two-factor authentication.",
+        example: serde_json::json!(LoginTwoFactorPostErrorResponse::TotpSecretUnreadable),
+    },
+    "423" => {
+        description: "The user is locked after too many failed login attempts.",
+        example: serde_json::json!(LoginTwoFactorPostErrorResponse::Locked("2025-01-01T00:15:00+00:00".to_string())),
+    },
+    "429" => {
+        description: "There were too many failed login attempts. The Retry-After header contains the number of seconds to wait.",
+        example: serde_json::json!(LoginTwoFactorPostErrorResponse::TooManyAttempts(8)),
+    },
+    "500" => {
+        description: "The login could not be processed.",
+        example: serde_json::json!(LoginTwoFactorPostErrorResponse::TwoFactorServiceVerify("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
+
+/// Log out by ending the session and removing its cookie.
+#[openapi]
+#[post("/logout")]
+pub async fn logout_post(
+    cookies: &CookieJar<'_>,
+    session_service: &State<SessionService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<LogoutPostResponse, LogoutPostErrorResponse> {
+    // Logging out works without a valid session, so a cookie that can't be parsed is just removed
+    let session_uuid = cookies
+        .get_private(SESSION_COOKIE_NAME)
+        .and_then(|cookie| Uuid::parse_str(cookie.value()).ok());
+    if let Some(session_uuid) = session_uuid {
+        session_service
+            .revoke(&session_uuid, postgres_pool.inner())
+            .await?;
+    }
+
+    cookies.remove_private(SESSION_COOKIE_NAME);
+    cookies.remove_private(PENDING_TWO_FACTOR_COOKIE_NAME);
+
+    Ok(LogoutPostResponse {})
+}
+
+impl<'r> Responder<'r, 'static> for LogoutPostResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(LogoutPostResponse, {
+    "200" => {
+        description: "The session was ended and its cookie was removed",
+        example: serde_json::json!(LogoutPostResponse {}),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for LogoutPostErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::SessionServiceRevoke(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(LogoutPostErrorResponse, {
+    "500" => {
+        description: "The session could not be ended.",
+        example: serde_json::json!(LogoutPostErrorResponse::SessionServiceRevoke("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
@@ -0,0 +1,350 @@
+use rocket::{
+    Request, State, get,
+    http::{Cookie, CookieJar, Header, SameSite, Status},
+    response::{Redirect, Responder, status},
+    serde::json::{Json, serde_json},
+};
+use rocket_okapi::openapi;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    impl_okapi_json_responder,
+    model::{
+        entity::audit_event::{AuditAction, AuditTargetType},
+        payload::oidc::{
+            OidcCallbackGetErrorResponse, OidcCallbackGetResponse, OidcCallbackQuery,
+            OidcGetErrorResponse,
+        },
+    },
+    service::{
+        LoginThrottleService, OidcService, SessionService, TwoFactorService, UnitOfWork,
+        UserService, audit::AuditRecord, oidc::OidcFlow,
+    },
+    validation::username,
+};
+
+use super::guard::{Auditor, SessionUser, add_pending_two_factor_cookie, add_session_cookie};
+
+/// The name of the private cookie holding the state of a login through the identity provider until it redirects back.
+const OIDC_FLOW_COOKIE_NAME: &str = "rasopus_oidc_flow";
+
+/// Log in through the identity provider.
+///
+/// This redirects to the identity provider, which redirects back to `GET /login/oidc/callback`.
+#[openapi]
+#[get("/login/oidc")]
+pub async fn oidc_get(
+    cookies: &CookieJar<'_>,
+    oidc_service: &State<OidcService>,
+) -> Result<Redirect, OidcGetErrorResponse> {
+    let (authorize_url, flow) = oidc_service.begin(None).await?;
+    add_oidc_flow_cookie(cookies, &flow, oidc_service.flow_timeout_secs());
+
+    Ok(Redirect::to(authorize_url))
+}
+
+/// Link an identity of the identity provider to the logged in user, so they can log in through the identity provider.
+///
+/// This redirects to the identity provider, which redirects back to `GET /login/oidc/callback`.
+#[openapi]
+#[get("/users/me/oidc")]
+pub async fn user_oidc_get(
+    session_user: SessionUser,
+    cookies: &CookieJar<'_>,
+    oidc_service: &State<OidcService>,
+) -> Result<Redirect, OidcGetErrorResponse> {
+    let SessionUser(user, _) = session_user;
+
+    let (authorize_url, flow) = oidc_service.begin(Some(user.uuid)).await?;
+    add_oidc_flow_cookie(cookies, &flow, oidc_service.flow_timeout_secs());
+
+    Ok(Redirect::to(authorize_url))
+}
+
+impl<'r> Responder<'r, 'static> for OidcGetErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::NotConfigured => Status::NotFound.code,
+            Self::OidcServiceBegin(_) => Status::BadGateway.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(OidcGetErrorResponse, {
+    "404" => {
+        description: "OpenID Connect is not configured.",
+        example: serde_json::json!(OidcGetErrorResponse::NotConfigured),
+    },
+    "502" => {
+        description: "The identity provider could not be reached.",
+        example: serde_json::json!(OidcGetErrorResponse::OidcServiceBegin("Failed to discover the identity provider: Request failed".to_string())),
+    },
+});
+
+/// Complete a login through the identity provider. The identity provider redirects here.
+///
+/// Locked users can't log in this way either. If the user has two-factor authentication enabled, the login has to be
+/// completed through `POST /login/two-factor`, just like a login with a password.
+#[openapi]
+#[get("/login/oidc/callback?<query..>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn oidc_callback_get(
+    query: OidcCallbackQuery,
+    auditor: Auditor<'_>,
+    cookies: &CookieJar<'_>,
+    oidc_service: &State<OidcService>,
+    user_service: &State<UserService>,
+    login_throttle_service: &State<LoginThrottleService>,
+    two_factor_service: &State<TwoFactorService>,
+    session_service: &State<SessionService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<OidcCallbackGetResponse, OidcCallbackGetErrorResponse> {
+    let flow = cookies
+        .get_private(OIDC_FLOW_COOKIE_NAME)
+        .and_then(|cookie| serde_json::from_str::<OidcFlow>(cookie.value()).ok());
+    cookies.remove_private(OIDC_FLOW_COOKIE_NAME);
+
+    let Some(flow) = flow else {
+        return Err(OidcCallbackGetErrorResponse::NoPendingLogin);
+    };
+
+    if let Some(error) = query.error {
+        let reason = match query.error_description {
+            Some(error_description) => format!("{}: {}", error, error_description),
+            None => error,
+        };
+        record_failed_login(&auditor, &reason, postgres_pool).await?;
+        return Err(OidcCallbackGetErrorResponse::Denied(reason));
+    }
+
+    let (Some(code), Some(state)) = (query.code, query.state) else {
+        return Err(OidcCallbackGetErrorResponse::MissingCode);
+    };
+
+    let identity = match oidc_service.authenticate(flow, &code, &state).await {
+        Ok(identity) => identity,
+        Err(error) => {
+            record_failed_login(&auditor, &error.to_string(), postgres_pool).await?;
+            return Err(error.into());
+        }
+    };
+
+    // The unit of work only starts once the identity provider was asked, so no transaction waits for it
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    let login = match oidc_service
+        .complete(
+            identity,
+            user_service,
+            auditor.audit_service(),
+            unit_of_work.connection(),
+        )
+        .await
+    {
+        Ok(login) => login,
+        Err(error) => {
+            drop(unit_of_work);
+            record_failed_login(&auditor, &error.to_string(), postgres_pool).await?;
+            return Err(error.into());
+        }
+    };
+
+    // The identity provider verified the identity, but a lockout still applies to every way of logging in. The user
+    // is only created, linked or updated once the login is allowed, otherwise the unit of work is rolled back.
+    let canonical_username = username::canonicalize(&login.user.username);
+    let attempt = login_throttle_service
+        .check(
+            &canonical_username,
+            Some(&login.user),
+            auditor.ip_address,
+            postgres_pool,
+        )
+        .await?;
+    unit_of_work.commit().await?;
+
+    if two_factor_service
+        .is_enabled(&login.user, postgres_pool)
+        .await?
+    {
+        login_throttle_service
+            .discard(attempt, postgres_pool.inner())
+            .await?;
+        add_pending_two_factor_cookie(
+            cookies,
+            &login.user.uuid,
+            two_factor_service.pending_login_secs(),
+        );
+
+        return Ok(OidcCallbackGetResponse {
+            uuid: login.user.uuid,
+            created: login.created,
+            linked: login.linked,
+            two_factor_required: true,
+        });
+    }
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    login_throttle_service
+        .succeed(attempt, unit_of_work.connection())
+        .await?;
+
+    let session = session_service
+        .issue(&login.user, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::LoginSucceeded,
+            AuditRecord {
+                actor_uuid: Some(login.user.uuid),
+                target: Some((AuditTargetType::User, login.user.uuid)),
+                after: Some(serde_json::json!({ "method": "oidc", "session_uuid": session.uuid })),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    add_session_cookie(cookies, &session);
+
+    Ok(OidcCallbackGetResponse {
+        uuid: login.user.uuid,
+        created: login.created,
+        linked: login.linked,
+        two_factor_required: false,
+    })
+}
+
+impl<'r> Responder<'r, 'static> for OidcCallbackGetResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(OidcCallbackGetResponse, {
+    "200" => {
+        description: "The identity was verified. Unless a second factor is required, the session cookie was set",
+        example: serde_json::json!(OidcCallbackGetResponse { uuid: uuid::Uuid::nil(), created: false, linked: false, two_factor_required: false }),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for OidcCallbackGetErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::NotConfigured => Status::NotFound.code,
+            Self::NoPendingLogin => Status::BadRequest.code,
+            Self::MissingCode => Status::BadRequest.code,
+            Self::Denied(_) => Status::Unauthorized.code,
+            Self::Expired => Status::BadRequest.code,
+            Self::InvalidState => Status::BadRequest.code,
+            Self::InvalidIdentity(_) => Status::Unauthorized.code,
+            Self::InvalidUsername(_) => Status::UnprocessableEntity.code,
+            Self::UsernameTaken => Status::Conflict.code,
+            Self::NotLinked => Status::Forbidden.code,
+            Self::NoMatchingRole => Status::Forbidden.code,
+            Self::IdentityLinkedToOtherUser => Status::Conflict.code,
+            Self::Locked(_) => Status::Locked.code,
+            Self::TooManyAttempts(_) => Status::TooManyRequests.code,
+            Self::IdentityProvider(_) => Status::BadGateway.code,
+            Self::LoginThrottleCheck(_) => Status::InternalServerError.code,
+            Self::LoginThrottleRecord(_) => Status::InternalServerError.code,
+            Self::TwoFactorServiceEnabled(_) => Status::InternalServerError.code,
+            Self::OidcServiceComplete(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::SessionServiceIssue(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        let retry_after = match self {
+            Self::TooManyAttempts(retry_after) => Some(retry_after),
+            _ => None,
+        };
+
+        let mut response =
+            status::Custom(Status::new(status_code), Json(self)).respond_to(request)?;
+        if let Some(retry_after) = retry_after {
+            response.set_header(Header::new("Retry-After", retry_after.to_string()));
+        }
+
+        Ok(response)
+    }
+}
+
+impl_okapi_json_responder!(OidcCallbackGetErrorResponse, {
+    "400" => {
+        description: "There is no login in progress, it took too long, or the identity provider returned an invalid response.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::InvalidState),
+    },
+    "401" => {
+        description: "The identity provider denied the login or the identity could not be verified.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::Denied("access_denied".to_string())),
+    },
+    "403" => {
+        description: "The identity is not linked to any user or doesn't map to any role.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::NotLinked),
+    },
+    "404" => {
+        description: "OpenID Connect is not configured.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::NotConfigured),
+    },
+    "409" => {
+        description: "The username is taken by another user or the identity is linked to another user.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::UsernameTaken),
+    },
+    "422" => {
+        description: "The identity provider did not return a valid username.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::InvalidUsername("The identity provider did not return the preferred_username claim".to_string())),
+    },
+    "423" => {
+        description: "The user is locked after too many failed login attempts.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::Locked("2025-01-01T00:15:00+00:00".to_string())),
+    },
+    "429" => {
+        description: "There were too many failed login attempts. The Retry-After header contains the number of seconds to wait.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::TooManyAttempts(8)),
+    },
+    "500" => {
+        description: "The login could not be processed.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::OidcServiceComplete("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+    "502" => {
+        description: "The identity provider could not be reached.",
+        example: serde_json::json!(OidcCallbackGetErrorResponse::IdentityProvider("Failed to exchange the authorization code: Request failed".to_string())),
+    },
+});
+
+async fn record_failed_login(
+    auditor: &Auditor<'_>,
+    reason: &str,
+    postgres_pool: &Pool<Postgres>,
+) -> Result<(), OidcCallbackGetErrorResponse> {
+    auditor
+        .record(
+            AuditAction::LoginFailed,
+            AuditRecord {
+                after: Some(serde_json::json!({ "method": "oidc", "reason": reason })),
+                ..Default::default()
+            },
+            postgres_pool,
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn add_oidc_flow_cookie(cookies: &CookieJar<'_>, flow: &OidcFlow, flow_timeout_secs: u64) {
+    // Serializing can't fail, as the flow only contains strings, numbers and UUIDs
+    let Ok(value) = serde_json::to_string(flow) else {
+        return;
+    };
+
+    // The identity provider redirects back with a cross-site navigation, which strict cookies are not sent with
+    let cookie = Cookie::build((OIDC_FLOW_COOKIE_NAME, value))
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(rocket::time::Duration::seconds(flow_timeout_secs as i64));
+    cookies.add_private(cookie);
+}
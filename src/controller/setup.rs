@@ -11,15 +11,20 @@ use sqlx::{Pool, Postgres};
 use crate::{
     impl_okapi_json_responder,
     model::{
-        entity::user::Role,
+        entity::{
+            audit_event::{AuditAction, AuditTargetType},
+            user::Role,
+        },
         payload::setup::{
             SetupGetErrorResponse, SetupGetResponse, SetupPostErrorResponse, SetupPostPayload,
             SetupPostResponse,
         },
     },
-    service::{SetupService, UserService},
+    service::{AuditService, SetupService, UnitOfWork, UserService, audit::AuditRecord},
 };
 
+use super::guard::Auditor;
+
 /// Check whether the backend needs to be set up.
 #[openapi]
 #[get("/setup")]
@@ -70,10 +75,14 @@ impl_okapi_json_responder!(SetupGetErrorResponse, {
 #[post("/setup", data = "<payload>")]
 pub async fn setup_post(
     payload: Json<SetupPostPayload>,
+    auditor: Auditor<'_>,
     setup_service: &State<SetupService>,
     user_service: &State<UserService>,
     postgres_pool: &State<Pool<Postgres>>,
 ) -> Result<SetupPostResponse, SetupPostErrorResponse> {
+    let payload = payload.into_inner();
+    setup_service.verify_setup_token(&payload.setup_token)?;
+
     let needs_setup = setup_service
         .needs_setup(user_service, postgres_pool)
         .await?;
@@ -82,13 +91,31 @@ pub async fn setup_post(
         return Err(SetupPostErrorResponse::AlreadySetup);
     }
 
-    let payload = payload.into_inner();
     let (username, password) = (payload.username, payload.password);
     let user = user_service
         .generate(username, &password, Role::System)
         .await?;
 
-    user_service.create(&user, postgres_pool).await?;
+    // The system user must not exist without the record of the setup
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    user_service
+        .create(&user, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::SetupCompleted,
+            AuditRecord {
+                actor_uuid: Some(user.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                after: Some(AuditService::user_snapshot(&user)),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
 
     Ok(SetupPostResponse {})
 }
@@ -110,9 +137,15 @@ impl<'r> Responder<'r, 'static> for SetupPostErrorResponse {
     fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
         let status_code = match self {
             Self::SetupServiceCheck(_) => Status::InternalServerError.code,
+            Self::InvalidSetupToken => Status::Forbidden.code,
             Self::AlreadySetup => Status::Conflict.code,
+            Self::InvalidUsername(_) => Status::UnprocessableEntity.code,
+            Self::InvalidPassword(_) => Status::UnprocessableEntity.code,
+            Self::UsernameTaken => Status::Conflict.code,
             Self::UserServiceGenerate(_) => Status::InternalServerError.code,
             Self::UserServiceCreate(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
         };
 
         status::Custom(Status::new(status_code), Json(self)).respond_to(request)
@@ -130,19 +163,17 @@ impl_okapi_json_responder!(SetupPostErrorResponse, {
             }
           }),
     },
+    "403" => {
+        description: "The given setup token is invalid.",
+        example: serde_json::json!(SetupPostErrorResponse::InvalidSetupToken),
+    },
     "409" => {
-        description: "The backend is already set up.",
+        description: "The backend is already set up, or the username is already taken.",
         example: serde_json::json!(SetupPostErrorResponse::AlreadySetup),
     },
     "422" => {
-        description: "The request contained invalid data.",
-        example: serde_json::json!({
-            "error": {
-              "code": 422,
-              "reason": "Unprocessable Entity",
-              "description": "The request was well-formed but was unable to be followed due to semantic errors."
-            }
-          }),
+        description: "The request contained invalid data, for example an invalid username or a password that violates the password policy.",
+        example: serde_json::json!(SetupPostErrorResponse::InvalidUsername("The username must be at least 3 characters long".to_string())),
     },
     "500" => {
         description: "The backend could not be set up.",
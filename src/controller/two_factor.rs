@@ -0,0 +1,315 @@
+use rocket::{
+    Request, State,
+    http::{Header, Status},
+    post,
+    response::{Responder, status},
+    serde::json::{Json, serde_json},
+};
+use rocket_okapi::openapi;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    impl_okapi_json_responder,
+    model::{
+        entity::audit_event::{AuditAction, AuditTargetType},
+        payload::two_factor::{
+            TotpConfirmPostErrorResponse, TotpConfirmPostPayload, TotpConfirmPostResponse,
+            TotpDisablePostErrorResponse, TotpDisablePostPayload, TotpDisablePostResponse,
+            TotpPostErrorResponse, TotpPostResponse,
+        },
+    },
+    service::{
+        LoginThrottleService, TwoFactorService, UnitOfWork, audit::AuditRecord,
+        two_factor::VerifyError,
+    },
+    validation::username,
+};
+
+use super::guard::{Auditor, SessionUser};
+
+/// Start enabling two-factor authentication for the logged in user.
+///
+/// Two-factor authentication is only enabled after confirming it through `POST /users/me/totp/confirm`.
+#[openapi]
+#[post("/users/me/totp")]
+pub async fn totp_post(
+    session_user: SessionUser,
+    two_factor_service: &State<TwoFactorService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<TotpPostResponse, TotpPostErrorResponse> {
+    let SessionUser(user, _) = session_user;
+
+    let enrollment = two_factor_service
+        .begin_enrollment(&user, postgres_pool)
+        .await?;
+
+    Ok(TotpPostResponse {
+        secret: enrollment.secret,
+        provisioning_uri: enrollment.provisioning_uri,
+        qr_code: format!("data:image/png;base64,{}", enrollment.qr_code_png_base64),
+    })
+}
+
+impl<'r> Responder<'r, 'static> for TotpPostResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(TotpPostResponse, {
+    "200" => {
+        description: "The TOTP was created and has to be confirmed",
+        example: serde_json::json!(TotpPostResponse {
+            secret: "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP".to_string(),
+            provisioning_uri: "otpauth://totp/Rasopus:admin?secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP&issuer=Rasopus".to_string(),
+            qr_code: "data:image/png;base64,iVBORw0KGgo...".to_string(),
+        }),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for TotpPostErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::AlreadyEnrolled => Status::Conflict.code,
+            Self::TwoFactorServiceEnroll(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(TotpPostErrorResponse, {
+    "409" => {
+        description: "Two-factor authentication is already enabled.",
+        example: serde_json::json!(TotpPostErrorResponse::AlreadyEnrolled),
+    },
+    "500" => {
+        description: "The TOTP could not be created.",
+        example: serde_json::json!(TotpPostErrorResponse::TwoFactorServiceEnroll("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
+
+/// Enable two-factor authentication for the logged in user with a code from the authenticator app.
+#[openapi]
+#[post("/users/me/totp/confirm", data = "<payload>")]
+pub async fn totp_confirm_post(
+    payload: Json<TotpConfirmPostPayload>,
+    session_user: SessionUser,
+    auditor: Auditor<'_>,
+    two_factor_service: &State<TwoFactorService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<TotpConfirmPostResponse, TotpConfirmPostErrorResponse> {
+    let payload = payload.into_inner();
+    let SessionUser(user, _) = session_user;
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    let recovery_codes = two_factor_service
+        .confirm_enrollment(&user, &payload.code, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::TwoFactorEnabled,
+            AuditRecord {
+                actor_uuid: Some(user.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                after: Some(serde_json::json!({ "recovery_codes": recovery_codes.len() })),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    println!("User {} enabled two-factor authentication", user.uuid);
+
+    Ok(TotpConfirmPostResponse { recovery_codes })
+}
+
+impl<'r> Responder<'r, 'static> for TotpConfirmPostResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(TotpConfirmPostResponse, {
+    "200" => {
+        description: "Two-factor authentication was enabled",
+        example: serde_json::json!(TotpConfirmPostResponse {
+            recovery_codes: vec!["ABCDE-FGHJK".to_string(), "LMNPQ-RSTUV".to_string()],
+        }),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for TotpConfirmPostErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::NotEnrolling => Status::Conflict.code,
+            Self::InvalidCode => Status::UnprocessableEntity.code,
+            Self::TotpSecretUnreadable => Status::Conflict.code,
+            Self::TwoFactorServiceConfirm(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(TotpConfirmPostErrorResponse, {
+    "409" => {
+        description: "There is no TOTP to confirm, or its secret can't be decrypted anymore, as the secret key was changed.",
+        example: serde_json::json!(TotpConfirmPostErrorResponse::NotEnrolling),
+    },
+    "422" => {
+        description: "The code is wrong.",
+        example: serde_json::json!(TotpConfirmPostErrorResponse::InvalidCode),
+    },
+    "500" => {
+        description: "The TOTP could not be confirmed.",
+        example: serde_json::json!(TotpConfirmPostErrorResponse::TwoFactorServiceConfirm("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
+
+/// Disable two-factor authentication for the logged in user.
+///
+/// This requires a code from the authenticator app or a recovery code, so a stolen session is not enough.
+#[openapi]
+#[post("/users/me/totp/disable", data = "<payload>")]
+pub async fn totp_disable_post(
+    payload: Json<TotpDisablePostPayload>,
+    session_user: SessionUser,
+    auditor: Auditor<'_>,
+    login_throttle_service: &State<LoginThrottleService>,
+    two_factor_service: &State<TwoFactorService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<TotpDisablePostResponse, TotpDisablePostErrorResponse> {
+    let payload = payload.into_inner();
+    let SessionUser(user, _) = session_user;
+    let canonical_username = username::canonicalize(&user.username);
+    let ip_address = auditor.ip_address;
+
+    let attempt = login_throttle_service
+        .check(&canonical_username, Some(&user), ip_address, postgres_pool)
+        .await?;
+
+    // Retrying can't help when the secret can't be decrypted, so it doesn't count as a failed attempt
+    let verified = match two_factor_service
+        .verify(&user, &payload.code, postgres_pool)
+        .await
+    {
+        Err(error @ VerifyError::SecretUnreadable) => {
+            login_throttle_service
+                .discard(attempt, postgres_pool.inner())
+                .await?;
+            return Err(error.into());
+        }
+        verified => verified?,
+    };
+
+    // Only failures are kept, as a success would reset the failed login attempts
+    if !verified {
+        let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+        login_throttle_service
+            .fail(attempt, unit_of_work.connection())
+            .await?;
+        unit_of_work.commit().await?;
+
+        return Err(TotpDisablePostErrorResponse::InvalidCode);
+    }
+
+    login_throttle_service
+        .discard(attempt, postgres_pool.inner())
+        .await?;
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    two_factor_service
+        .disable(&user.uuid, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::TwoFactorDisabled,
+            AuditRecord {
+                actor_uuid: Some(user.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    println!("User {} disabled two-factor authentication", user.uuid);
+
+    Ok(TotpDisablePostResponse {})
+}
+
+impl<'r> Responder<'r, 'static> for TotpDisablePostResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(TotpDisablePostResponse, {
+    "200" => {
+        description: "Two-factor authentication was disabled",
+        example: serde_json::json!(TotpDisablePostResponse {}),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for TotpDisablePostErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::NotEnrolled => Status::Conflict.code,
+            Self::InvalidCode => Status::UnprocessableEntity.code,
+            Self::TotpSecretUnreadable => Status::Conflict.code,
+            Self::Locked(_) => Status::Locked.code,
+            Self::TooManyAttempts(_) => Status::TooManyRequests.code,
+            Self::LoginThrottleCheck(_) => Status::InternalServerError.code,
+            Self::LoginThrottleRecord(_) => Status::InternalServerError.code,
+            Self::TwoFactorServiceVerify(_) => Status::InternalServerError.code,
+            Self::TwoFactorServiceDisable(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        let retry_after = match self {
+            Self::TooManyAttempts(retry_after) => Some(retry_after),
+            _ => None,
+        };
+
+        let mut response =
+            status::Custom(Status::new(status_code), Json(self)).respond_to(request)?;
+        if let Some(retry_after) = retry_after {
+            response.set_header(Header::new("Retry-After", retry_after.to_string()));
+        }
+
+        Ok(response)
+    }
+}
+
+impl_okapi_json_responder!(TotpDisablePostErrorResponse, {
+    "409" => {
+        description: "Two-factor authentication is not enabled, or the TOTP secret can't be decrypted anymore, as the secret key was changed. A recovery code still works.",
+        example: serde_json::json!(TotpDisablePostErrorResponse::NotEnrolled),
+    },
+    "422" => {
+        description: "The code is wrong or was already used.",
+        example: serde_json::json!(TotpDisablePostErrorResponse::InvalidCode),
+    },
+    "423" => {
+        description: "The user is locked after too many failed attempts.",
+        example: serde_json::json!(TotpDisablePostErrorResponse::Locked("2025-01-01T00:15:00+00:00".to_string())),
+    },
+    "429" => {
+        description: "There were too many failed attempts. The Retry-After header contains the number of seconds to wait.",
+        example: serde_json::json!(TotpDisablePostErrorResponse::TooManyAttempts(8)),
+    },
+    "500" => {
+        description: "Two-factor authentication could not be disabled.",
+        example: serde_json::json!(TotpDisablePostErrorResponse::TwoFactorServiceDisable("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
@@ -0,0 +1,958 @@
+use rocket::{
+    Request, State, delete, get,
+    http::{Header, Status},
+    post, put,
+    response::{Responder, status},
+    serde::json::{Json, serde_json},
+};
+use rocket_okapi::openapi;
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{
+    impl_okapi_json_responder,
+    model::{
+        entity::{
+            audit_event::{AuditAction, AuditTargetType},
+            user::{Role, UserFilter, UserSortColumn},
+        },
+        list::{DEFAULT_PAGE_SIZE, ListQuery, parse_timestamp},
+        payload::{
+            password::PasswordViolationResponse,
+            user::{
+                UserDeleteErrorResponse, UserDeleteResponse, UserLockoutDeleteErrorResponse,
+                UserLockoutDeleteResponse, UserPasswordPutErrorResponse, UserPasswordPutPayload,
+                UserPasswordPutResponse, UserPostErrorResponse, UserPostPayload, UserPostResponse,
+                UserResponse, UserRestorePostErrorResponse, UserRestorePostResponse,
+                UserRolePutErrorResponse, UserRolePutPayload, UserRolePutResponse,
+                UserTotpDeleteErrorResponse, UserTotpDeleteResponse, UsersGetErrorResponse,
+                UsersGetQuery, UsersGetResponse,
+            },
+        },
+    },
+    service::{
+        AuditService, LoginThrottleService, SessionService, TwoFactorService, UnitOfWork,
+        UserService, audit::AuditRecord,
+    },
+    validation::username,
+};
+
+use super::guard::{AdminUser, Auditor, SessionUser};
+
+/// List the users, oldest first unless sorted otherwise.
+///
+/// For example, `?role=admin&sort=username` lists all admins by their username.
+#[openapi]
+#[get("/admin/users?<query..>")]
+pub async fn users_get(
+    query: UsersGetQuery,
+    _admin: AdminUser,
+    user_service: &State<UserService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UsersGetResponse, UsersGetErrorResponse> {
+    let list_query = parse_list_query(query, false, None)?;
+    let (users, total) = user_service.list(&list_query, postgres_pool).await?;
+
+    Ok(UsersGetResponse::new(users, total))
+}
+
+/// List the users in the trash, who can be restored until the trash is purged.
+///
+/// Takes the same query parameters as listing the active users.
+#[openapi]
+#[get("/admin/users/trash?<query..>")]
+pub async fn users_trash_get(
+    query: UsersGetQuery,
+    _admin: AdminUser,
+    user_service: &State<UserService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UsersGetResponse, UsersGetErrorResponse> {
+    let list_query = parse_list_query(query, true, None)?;
+    let (users, total) = user_service.list(&list_query, postgres_pool).await?;
+
+    Ok(UsersGetResponse::new(users, total))
+}
+
+/// List the users in the trash that the requesting user moved there themselves.
+///
+/// Takes the same query parameters as listing the active users.
+#[openapi]
+#[get("/users/me/trash?<query..>")]
+pub async fn users_me_trash_get(
+    query: UsersGetQuery,
+    session_user: SessionUser,
+    user_service: &State<UserService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UsersGetResponse, UsersGetErrorResponse> {
+    let SessionUser(user, _) = session_user;
+
+    let list_query = parse_list_query(query, true, Some(user.uuid))?;
+    let (users, total) = user_service.list(&list_query, postgres_pool).await?;
+
+    Ok(UsersGetResponse::new(users, total))
+}
+
+fn parse_list_query(
+    query: UsersGetQuery,
+    deleted: bool,
+    deleted_by: Option<Uuid>,
+) -> Result<ListQuery<UserFilter, UserSortColumn>, UsersGetErrorResponse> {
+    let role = query
+        .role
+        .as_deref()
+        .map(|role| {
+            role.parse::<Role>()
+                .map_err(|_| UsersGetErrorResponse::InvalidRole(role.to_string()))
+        })
+        .transpose()?;
+
+    let sort = query
+        .sort
+        .as_deref()
+        .map(|sort| {
+            sort.parse()
+                .map_err(|_| UsersGetErrorResponse::InvalidSort(sort.to_string()))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let direction = query
+        .direction
+        .as_deref()
+        .map(|direction| {
+            direction
+                .parse()
+                .map_err(|_| UsersGetErrorResponse::InvalidDirection(direction.to_string()))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let list_query = ListQuery {
+        filter: UserFilter {
+            role,
+            created_since: parse_timestamp(query.created_since.as_deref())?,
+            created_until: parse_timestamp(query.created_until.as_deref())?,
+            deleted,
+            deleted_by,
+        },
+        sort,
+        direction,
+        cursor: query.cursor.as_deref().map(str::parse).transpose()?,
+        limit: query.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+    };
+
+    Ok(list_query)
+}
+
+impl_okapi_json_responder!(UsersGetResponse, {
+    "200" => {
+        description: "The page of the users",
+        example: serde_json::json!(UsersGetResponse {
+            items: vec![UserResponse {
+                uuid: uuid::Uuid::nil(),
+                username: "alice".to_string(),
+                role: Role::Admin,
+                created_at: "2025-01-01T00:00:00+00:00".to_string(),
+                updated_at: "2025-01-01T00:00:00+00:00".to_string(),
+                deleted_at: None,
+                deleted_by: None,
+            }],
+            next_cursor: Some("eyJzb3J0IjoiY3JlYXRlZF9hdCIsInZhbHVlIjp7IlRpbWVzdGFtcCI6IjIwMjUtMDEtMDFUMDA6MDA6MDBaIn0sImlkZW50aWZpZXIiOnsiVXVpZCI6IjAwMDAwMDAwLTAwMDAtMDAwMC0wMDAwLTAwMDAwMDAwMDAwMCJ9fQ".to_string()),
+            total: 75,
+        }),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for UsersGetErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::InvalidRole(_) => Status::UnprocessableEntity.code,
+            Self::InvalidTimestamp(_) => Status::UnprocessableEntity.code,
+            Self::InvalidSort(_) => Status::UnprocessableEntity.code,
+            Self::InvalidDirection(_) => Status::UnprocessableEntity.code,
+            Self::InvalidCursor(_) => Status::UnprocessableEntity.code,
+            Self::UserServiceList(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UsersGetErrorResponse, {
+    "422" => {
+        description: "A filter, the sorting or the cursor is invalid, for example an unknown role.",
+        example: serde_json::json!(UsersGetErrorResponse::InvalidRole("moderator".to_string())),
+    },
+    "500" => {
+        description: "The users could not be listed.",
+        example: serde_json::json!(UsersGetErrorResponse::UserServiceList("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
+
+/// Create a user with a password.
+///
+/// Admins can create admins and users. The password has to follow the password policy.
+#[openapi]
+#[post("/users", data = "<payload>")]
+pub async fn user_post(
+    payload: Json<UserPostPayload>,
+    admin: AdminUser,
+    auditor: Auditor<'_>,
+    user_service: &State<UserService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UserPostResponse, UserPostErrorResponse> {
+    let payload = payload.into_inner();
+    let AdminUser(admin) = admin;
+
+    if payload.role == Role::System {
+        return Err(UserPostErrorResponse::SystemRole);
+    }
+
+    if payload.role < admin.role {
+        return Err(UserPostErrorResponse::InsufficientRole);
+    }
+
+    let user = user_service
+        .generate(payload.username, &payload.password, payload.role)
+        .await?;
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    user_service
+        .create(&user, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::UserCreated,
+            AuditRecord {
+                actor_uuid: Some(admin.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                after: Some(AuditService::user_snapshot(&user)),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    println!("User {} was created by user {}", user.uuid, admin.uuid);
+
+    Ok(UserPostResponse::from(user))
+}
+
+impl<'r> Responder<'r, 'static> for UserResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserPostResponse, {
+    "200" => {
+        description: "The user was created",
+        example: serde_json::json!(UserPostResponse {
+            uuid: uuid::Uuid::nil(),
+            username: "alice".to_string(),
+            role: Role::User,
+            created_at: "2025-01-01T00:00:00+00:00".to_string(),
+            updated_at: "2025-01-01T00:00:00+00:00".to_string(),
+            deleted_at: None,
+            deleted_by: None,
+        }),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for UserPostErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::InsufficientRole => Status::Forbidden.code,
+            Self::SystemRole => Status::UnprocessableEntity.code,
+            Self::InvalidUsername(_) => Status::UnprocessableEntity.code,
+            Self::UsernameTaken => Status::Conflict.code,
+            Self::InvalidPassword(_) => Status::UnprocessableEntity.code,
+            Self::UserServiceGenerate(_) => Status::InternalServerError.code,
+            Self::UserServiceCreate(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserPostErrorResponse, {
+    "403" => {
+        description: "The role is higher than the role of the requesting user.",
+        example: serde_json::json!(UserPostErrorResponse::InsufficientRole),
+    },
+    "409" => {
+        description: "The username is already taken.",
+        example: serde_json::json!(UserPostErrorResponse::UsernameTaken),
+    },
+    "422" => {
+        description: "The request contained invalid data, for example an invalid username or a password that violates the password policy.",
+        example: serde_json::json!(UserPostErrorResponse::InvalidPassword(vec![PasswordViolationResponse::TooShort { min_length: 10 }])),
+    },
+    "500" => {
+        description: "The user could not be created.",
+        example: serde_json::json!(UserPostErrorResponse::UserServiceCreate("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
+
+/// Change the password of the logged in user.
+///
+/// The current password has to be given as well. Wrong current passwords count as failed login attempts.
+#[openapi]
+#[put("/users/me/password", data = "<payload>")]
+pub async fn user_password_put(
+    payload: Json<UserPasswordPutPayload>,
+    session_user: SessionUser,
+    auditor: Auditor<'_>,
+    user_service: &State<UserService>,
+    login_throttle_service: &State<LoginThrottleService>,
+    session_service: &State<SessionService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UserPasswordPutResponse, UserPasswordPutErrorResponse> {
+    let payload = payload.into_inner();
+    let SessionUser(mut user, session) = session_user;
+    let canonical_username = username::canonicalize(&user.username);
+    let ip_address = auditor.ip_address;
+
+    let attempt = login_throttle_service
+        .check(&canonical_username, Some(&user), ip_address, postgres_pool)
+        .await?;
+
+    let verified = {
+        let _permit = login_throttle_service.acquire_verification_permit().await?;
+        user_service
+            .verify_password(&user, &payload.current_password)
+            .await?
+    };
+
+    // Only failures are kept, as a success would reset the failed login attempts
+    if !verified {
+        let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+        login_throttle_service
+            .fail(attempt, unit_of_work.connection())
+            .await?;
+        unit_of_work.commit().await?;
+
+        return Err(UserPasswordPutErrorResponse::InvalidCurrentPassword);
+    }
+
+    login_throttle_service
+        .discard(attempt, postgres_pool.inner())
+        .await?;
+
+    user_service
+        .set_password(&mut user, &payload.new_password)
+        .await?;
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    user_service
+        .update(&user, unit_of_work.connection())
+        .await?;
+
+    // Whoever knew the old password is logged out everywhere, except for the session that changed it
+    session_service
+        .revoke_all(&user.uuid, Some(&session.uuid), unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::UserPasswordChanged,
+            AuditRecord {
+                actor_uuid: Some(user.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    println!("User {} changed their password", user.uuid);
+
+    Ok(UserPasswordPutResponse {})
+}
+
+impl<'r> Responder<'r, 'static> for UserPasswordPutResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserPasswordPutResponse, {
+    "200" => {
+        description: "The password was changed",
+        example: serde_json::json!(UserPasswordPutResponse {}),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for UserPasswordPutErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::InvalidCurrentPassword => Status::UnprocessableEntity.code,
+            Self::InvalidPassword(_) => Status::UnprocessableEntity.code,
+            Self::Locked(_) => Status::Locked.code,
+            Self::TooManyAttempts(_) => Status::TooManyRequests.code,
+            Self::VerificationUnavailable => Status::ServiceUnavailable.code,
+            Self::LoginThrottleCheck(_) => Status::InternalServerError.code,
+            Self::LoginThrottleRecord(_) => Status::InternalServerError.code,
+            Self::UserServiceVerify(_) => Status::InternalServerError.code,
+            Self::UserServiceSetPassword(_) => Status::InternalServerError.code,
+            Self::UserServiceUpdate(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+            Self::SessionServiceRevoke(_) => Status::InternalServerError.code,
+        };
+
+        let retry_after = match self {
+            Self::TooManyAttempts(retry_after) => Some(retry_after),
+            _ => None,
+        };
+
+        let mut response =
+            status::Custom(Status::new(status_code), Json(self)).respond_to(request)?;
+        if let Some(retry_after) = retry_after {
+            response.set_header(Header::new("Retry-After", retry_after.to_string()));
+        }
+
+        Ok(response)
+    }
+}
+
+impl_okapi_json_responder!(UserPasswordPutErrorResponse, {
+    "422" => {
+        description: "The current password is wrong, or the new password violates the password policy.",
+        example: serde_json::json!(UserPasswordPutErrorResponse::InvalidCurrentPassword),
+    },
+    "423" => {
+        description: "The user is locked after too many failed login attempts.",
+        example: serde_json::json!(UserPasswordPutErrorResponse::Locked("2025-01-01T00:15:00+00:00".to_string())),
+    },
+    "429" => {
+        description: "There were too many failed login attempts. The Retry-After header contains the number of seconds to wait.",
+        example: serde_json::json!(UserPasswordPutErrorResponse::TooManyAttempts(8)),
+    },
+    "500" => {
+        description: "The password could not be changed.",
+        example: serde_json::json!(UserPasswordPutErrorResponse::UserServiceUpdate("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+    "503" => {
+        description: "The current password can't be verified right now.",
+        example: serde_json::json!(UserPasswordPutErrorResponse::VerificationUnavailable),
+    },
+});
+
+/// Change the role of a user.
+///
+/// If roles are mapped from the claims of an identity provider, the role of a user with a linked identity is set from
+/// their claims again on their next login through it.
+#[openapi]
+#[put("/users/<uuid>/role", data = "<payload>")]
+pub async fn user_role_put(
+    uuid: Uuid,
+    payload: Json<UserRolePutPayload>,
+    admin: AdminUser,
+    auditor: Auditor<'_>,
+    user_service: &State<UserService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UserRolePutResponse, UserRolePutErrorResponse> {
+    let payload = payload.into_inner();
+    let AdminUser(admin) = admin;
+
+    if payload.role == Role::System {
+        return Err(UserRolePutErrorResponse::SystemRole);
+    }
+
+    let Some(mut user) = user_service.load(&uuid, postgres_pool.inner()).await? else {
+        return Err(UserRolePutErrorResponse::UserNotFound);
+    };
+
+    if user.role < admin.role || payload.role < admin.role {
+        return Err(UserRolePutErrorResponse::InsufficientRole);
+    }
+
+    // Otherwise, the last admin could demote themselves and nobody could manage the users anymore
+    if user.uuid == admin.uuid {
+        return Err(UserRolePutErrorResponse::CannotChangeOwnRole);
+    }
+
+    if user.role == payload.role {
+        return Ok(UserRolePutResponse {});
+    }
+
+    let before = AuditService::user_snapshot(&user);
+    user.role = payload.role;
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    user_service
+        .update(&user, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::UserRoleChanged,
+            AuditRecord {
+                actor_uuid: Some(admin.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                before: Some(before),
+                after: Some(AuditService::user_snapshot(&user)),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    println!(
+        "The role of user {} was changed to {:?} by user {}",
+        user.uuid, user.role, admin.uuid
+    );
+
+    Ok(UserRolePutResponse {})
+}
+
+impl<'r> Responder<'r, 'static> for UserRolePutResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserRolePutResponse, {
+    "200" => {
+        description: "The role was changed",
+        example: serde_json::json!(UserRolePutResponse {}),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for UserRolePutErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::UserNotFound => Status::NotFound.code,
+            Self::InsufficientRole => Status::Forbidden.code,
+            Self::SystemRole => Status::UnprocessableEntity.code,
+            Self::CannotChangeOwnRole => Status::Conflict.code,
+            Self::UserServiceLoad(_) => Status::InternalServerError.code,
+            Self::UserServiceUpdate(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserRolePutErrorResponse, {
+    "403" => {
+        description: "The user or the new role is higher than the role of the requesting user.",
+        example: serde_json::json!(UserRolePutErrorResponse::InsufficientRole),
+    },
+    "404" => {
+        description: "The user does not exist.",
+        example: serde_json::json!(UserRolePutErrorResponse::UserNotFound),
+    },
+    "409" => {
+        description: "The requesting user tried to change their own role.",
+        example: serde_json::json!(UserRolePutErrorResponse::CannotChangeOwnRole),
+    },
+    "422" => {
+        description: "The new role is the system role.",
+        example: serde_json::json!(UserRolePutErrorResponse::SystemRole),
+    },
+    "500" => {
+        description: "The role could not be changed.",
+        example: serde_json::json!(UserRolePutErrorResponse::UserServiceUpdate("Failed to persist user: Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
+
+/// Move a user to the trash.
+///
+/// The user can't log in anymore and their sessions end. Their username stays reserved until the trash is purged,
+/// so they can be restored in the meantime.
+#[openapi]
+#[delete("/users/<uuid>")]
+pub async fn user_delete(
+    uuid: Uuid,
+    admin: AdminUser,
+    auditor: Auditor<'_>,
+    user_service: &State<UserService>,
+    session_service: &State<SessionService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UserDeleteResponse, UserDeleteErrorResponse> {
+    let AdminUser(admin) = admin;
+
+    let Some(user) = user_service.load(&uuid, postgres_pool.inner()).await? else {
+        return Err(UserDeleteErrorResponse::UserNotFound);
+    };
+
+    if user.role < admin.role {
+        return Err(UserDeleteErrorResponse::InsufficientRole);
+    }
+
+    if user.uuid == admin.uuid {
+        return Err(UserDeleteErrorResponse::CannotDeleteSelf);
+    }
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    user_service
+        .delete(&user, &admin.uuid, unit_of_work.connection())
+        .await?;
+
+    // The sessions must not come back to life if the user is restored
+    session_service
+        .revoke_all(&user.uuid, None, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::UserDeleted,
+            AuditRecord {
+                actor_uuid: Some(admin.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                before: Some(AuditService::user_snapshot(&user)),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    println!("User {} was deleted by user {}", user.uuid, admin.uuid);
+
+    Ok(UserDeleteResponse {})
+}
+
+impl<'r> Responder<'r, 'static> for UserDeleteResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserDeleteResponse, {
+    "200" => {
+        description: "The user was moved to the trash",
+        example: serde_json::json!(UserDeleteResponse {}),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for UserDeleteErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::UserNotFound => Status::NotFound.code,
+            Self::InsufficientRole => Status::Forbidden.code,
+            Self::CannotDeleteSelf => Status::Conflict.code,
+            Self::UserServiceLoad(_) => Status::InternalServerError.code,
+            Self::UserServiceDelete(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::SessionServiceRevoke(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserDeleteErrorResponse, {
+    "403" => {
+        description: "The user has a higher role than the requesting user.",
+        example: serde_json::json!(UserDeleteErrorResponse::InsufficientRole),
+    },
+    "404" => {
+        description: "The user does not exist.",
+        example: serde_json::json!(UserDeleteErrorResponse::UserNotFound),
+    },
+    "409" => {
+        description: "The requesting user tried to delete themselves.",
+        example: serde_json::json!(UserDeleteErrorResponse::CannotDeleteSelf),
+    },
+    "500" => {
+        description: "The user could not be deleted.",
+        example: serde_json::json!(UserDeleteErrorResponse::UserServiceDelete("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
+
+/// Restore a user from the trash, so they can log in again.
+#[openapi]
+#[post("/users/<uuid>/restore")]
+pub async fn user_restore_post(
+    uuid: Uuid,
+    admin: AdminUser,
+    auditor: Auditor<'_>,
+    user_service: &State<UserService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UserRestorePostResponse, UserRestorePostErrorResponse> {
+    let AdminUser(admin) = admin;
+
+    let Some(user) = user_service
+        .load_deleted(&uuid, postgres_pool.inner())
+        .await?
+    else {
+        return Err(UserRestorePostErrorResponse::UserNotFound);
+    };
+
+    if user.role < admin.role {
+        return Err(UserRestorePostErrorResponse::InsufficientRole);
+    }
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    user_service
+        .restore(&user, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::UserRestored,
+            AuditRecord {
+                actor_uuid: Some(admin.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                after: Some(AuditService::user_snapshot(&user)),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    println!("User {} was restored by user {}", user.uuid, admin.uuid);
+
+    Ok(UserRestorePostResponse {})
+}
+
+impl<'r> Responder<'r, 'static> for UserRestorePostResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserRestorePostResponse, {
+    "200" => {
+        description: "The user was restored from the trash",
+        example: serde_json::json!(UserRestorePostResponse {}),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for UserRestorePostErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::UserNotFound => Status::NotFound.code,
+            Self::InsufficientRole => Status::Forbidden.code,
+            Self::UserServiceLoad(_) => Status::InternalServerError.code,
+            Self::UserServiceRestore(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserRestorePostErrorResponse, {
+    "403" => {
+        description: "The user has a higher role than the requesting user.",
+        example: serde_json::json!(UserRestorePostErrorResponse::InsufficientRole),
+    },
+    "404" => {
+        description: "The user is not in the trash.",
+        example: serde_json::json!(UserRestorePostErrorResponse::UserNotFound),
+    },
+    "500" => {
+        description: "The user could not be restored.",
+        example: serde_json::json!(UserRestorePostErrorResponse::UserServiceRestore("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
+
+/// Unlock a user who was locked after too many failed login attempts.
+#[openapi]
+#[delete("/users/<uuid>/lockout")]
+pub async fn user_lockout_delete(
+    uuid: Uuid,
+    admin: AdminUser,
+    auditor: Auditor<'_>,
+    user_service: &State<UserService>,
+    login_throttle_service: &State<LoginThrottleService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UserLockoutDeleteResponse, UserLockoutDeleteErrorResponse> {
+    let AdminUser(admin) = admin;
+
+    let Some(user) = user_service.load(&uuid, postgres_pool.inner()).await? else {
+        return Err(UserLockoutDeleteErrorResponse::UserNotFound);
+    };
+
+    if user.role < admin.role {
+        return Err(UserLockoutDeleteErrorResponse::InsufficientRole);
+    }
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    let lockout = login_throttle_service
+        .unlock(&user.uuid, &admin.uuid, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::UserUnlocked,
+            AuditRecord {
+                actor_uuid: Some(admin.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                before: Some(
+                    serde_json::json!({ "locked_until": lockout.locked_until.to_rfc3339() }),
+                ),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    Ok(UserLockoutDeleteResponse {})
+}
+
+impl<'r> Responder<'r, 'static> for UserLockoutDeleteResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserLockoutDeleteResponse, {
+    "200" => {
+        description: "The user was unlocked",
+        example: serde_json::json!(UserLockoutDeleteResponse {}),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for UserLockoutDeleteErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::UserNotFound => Status::NotFound.code,
+            Self::InsufficientRole => Status::Forbidden.code,
+            Self::NotLocked => Status::Conflict.code,
+            Self::UserServiceLoad(_) => Status::InternalServerError.code,
+            Self::LoginThrottleUnlock(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserLockoutDeleteErrorResponse, {
+    "403" => {
+        description: "The user has a higher role than the requesting user.",
+        example: serde_json::json!(UserLockoutDeleteErrorResponse::InsufficientRole),
+    },
+    "404" => {
+        description: "The user does not exist.",
+        example: serde_json::json!(UserLockoutDeleteErrorResponse::UserNotFound),
+    },
+    "409" => {
+        description: "The user is not locked.",
+        example: serde_json::json!(UserLockoutDeleteErrorResponse::NotLocked),
+    },
+    "500" => {
+        description: "The user could not be unlocked.",
+        example: serde_json::json!(UserLockoutDeleteErrorResponse::LoginThrottleUnlock("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
+
+/// Reset the two-factor authentication of a user who lost access to their authenticator app and recovery codes.
+#[openapi]
+#[delete("/users/<uuid>/totp")]
+pub async fn user_totp_delete(
+    uuid: Uuid,
+    admin: AdminUser,
+    auditor: Auditor<'_>,
+    user_service: &State<UserService>,
+    two_factor_service: &State<TwoFactorService>,
+    postgres_pool: &State<Pool<Postgres>>,
+) -> Result<UserTotpDeleteResponse, UserTotpDeleteErrorResponse> {
+    let AdminUser(admin) = admin;
+
+    let Some(user) = user_service.load(&uuid, postgres_pool.inner()).await? else {
+        return Err(UserTotpDeleteErrorResponse::UserNotFound);
+    };
+
+    if user.role < admin.role {
+        return Err(UserTotpDeleteErrorResponse::InsufficientRole);
+    }
+
+    let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+    two_factor_service
+        .disable(&user.uuid, unit_of_work.connection())
+        .await?;
+
+    auditor
+        .record(
+            AuditAction::TwoFactorReset,
+            AuditRecord {
+                actor_uuid: Some(admin.uuid),
+                target: Some((AuditTargetType::User, user.uuid)),
+                ..Default::default()
+            },
+            unit_of_work.connection(),
+        )
+        .await?;
+
+    unit_of_work.commit().await?;
+
+    println!(
+        "Two-factor authentication of user {} was reset by user {}",
+        user.uuid, admin.uuid
+    );
+
+    Ok(UserTotpDeleteResponse {})
+}
+
+impl<'r> Responder<'r, 'static> for UserTotpDeleteResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        Json(self).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserTotpDeleteResponse, {
+    "200" => {
+        description: "The two-factor authentication of the user was reset",
+        example: serde_json::json!(UserTotpDeleteResponse {}),
+    }
+});
+
+impl<'r> Responder<'r, 'static> for UserTotpDeleteErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let status_code = match self {
+            Self::UserNotFound => Status::NotFound.code,
+            Self::InsufficientRole => Status::Forbidden.code,
+            Self::NotEnrolled => Status::Conflict.code,
+            Self::UserServiceLoad(_) => Status::InternalServerError.code,
+            Self::TwoFactorServiceDisable(_) => Status::InternalServerError.code,
+            Self::AuditServiceRecord(_) => Status::InternalServerError.code,
+            Self::UnitOfWork(_) => Status::InternalServerError.code,
+        };
+
+        status::Custom(Status::new(status_code), Json(self)).respond_to(request)
+    }
+}
+
+impl_okapi_json_responder!(UserTotpDeleteErrorResponse, {
+    "403" => {
+        description: "The user has a higher role than the requesting user.",
+        example: serde_json::json!(UserTotpDeleteErrorResponse::InsufficientRole),
+    },
+    "404" => {
+        description: "The user does not exist.",
+        example: serde_json::json!(UserTotpDeleteErrorResponse::UserNotFound),
+    },
+    "409" => {
+        description: "The user has no two-factor authentication.",
+        example: serde_json::json!(UserTotpDeleteErrorResponse::NotEnrolled),
+    },
+    "500" => {
+        description: "The two-factor authentication could not be reset.",
+        example: serde_json::json!(UserTotpDeleteErrorResponse::TwoFactorServiceDisable("Database error: pool timed out while waiting for an open connection".to_string())),
+    },
+});
@@ -1,10 +1,111 @@
+use std::time::{Duration, Instant};
+
 use sqlx::{
     Error as SqlxError, Pool, Postgres,
     error::DatabaseError as SqlxDatabaseError,
     migrate::{Migrate, MigrateError as SqlxMigrateError, Migrator},
+    postgres::{PgConnectOptions, PgPoolOptions},
 };
 use thiserror::Error;
 
+use crate::config::postgres::PostgresConfig;
+
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+// See: https://www.postgresql.org/docs/current/errcodes-appendix.html
+const UNIQUE_VIOLATION_CODE: &str = "23505";
+
+/// The migrations embedded into this Rasopus version.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Returns the name of the violated unique constraint, if the given error is a unique violation.
+pub fn violated_unique_constraint(error: &SqlxError) -> Option<&str> {
+    let SqlxError::Database(database_error) = error else {
+        return None;
+    };
+
+    if database_error.code().as_deref() != Some(UNIQUE_VIOLATION_CODE) {
+        return None;
+    }
+
+    database_error.constraint()
+}
+
+pub fn pool_options(postgres_config: &PostgresConfig) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(postgres_config.pool_size)
+        .min_connections(postgres_config.min_connections)
+        .acquire_timeout(postgres_config.acquire_timeout())
+        .idle_timeout(postgres_config.idle_timeout())
+        .max_lifetime(postgres_config.max_lifetime())
+}
+
+pub fn connect_options(postgres_config: &PostgresConfig) -> Result<PgConnectOptions, SqlxError> {
+    let mut connect_options: PgConnectOptions = postgres_config.to_connection_string().parse()?;
+    if let Some(statement_timeout_ms) = postgres_config.statement_timeout_ms {
+        connect_options =
+            connect_options.options([("statement_timeout", statement_timeout_ms.to_string())]);
+    }
+
+    Ok(connect_options)
+}
+
+/// Connects to the database, retrying with exponential backoff until the configured maximum wait time has passed.
+///
+/// This is needed because the database is not necessarily ready to accept connections when Rasopus starts,
+/// for example when both are started at the same time using docker compose.
+pub async fn connect(postgres_config: &PostgresConfig) -> Result<Pool<Postgres>, SqlxError> {
+    let connect_options = connect_options(postgres_config)?;
+    let max_wait = postgres_config.connect_max_wait();
+    let started_at = Instant::now();
+    let mut backoff = postgres_config.connect_backoff();
+    let mut attempt: u32 = 1;
+
+    loop {
+        println!(
+            "Connecting to database at {}:{} (attempt {})",
+            postgres_config.host, postgres_config.port, attempt
+        );
+
+        // The pool waits up to the acquire timeout for its first connection, so an attempt is cut short when it
+        // would otherwise run past the maximum wait time.
+        let remaining = max_wait.saturating_sub(started_at.elapsed());
+        let result = tokio::time::timeout(
+            remaining,
+            pool_options(postgres_config).connect_with(connect_options.clone()),
+        )
+        .await;
+
+        let error = match result {
+            Ok(Ok(pool)) => return Ok(pool),
+            Ok(Err(error)) => error,
+            Err(_) => SqlxError::PoolTimedOut,
+        };
+
+        let elapsed = started_at.elapsed();
+        if elapsed + backoff > max_wait {
+            eprintln!(
+                "Failed to connect to database (attempt {}): {}. Giving up after {:.1}s",
+                attempt,
+                error,
+                elapsed.as_secs_f64()
+            );
+            return Err(error);
+        }
+
+        eprintln!(
+            "Failed to connect to database (attempt {}): {}. Retrying in {}ms",
+            attempt,
+            error,
+            backoff.as_millis()
+        );
+        tokio::time::sleep(backoff).await;
+
+        backoff = (backoff * 2).min(MAX_CONNECT_BACKOFF);
+        attempt += 1;
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum CheckMigrationError {
     #[error("Failed to acquire connection from pool")]
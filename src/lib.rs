@@ -1,17 +1,22 @@
+use std::path::Path;
+
 use config::{postgres::PostgresConfig, rasopus::RasopusConfig, rocket::RocketConfig};
 use rocket::Rocket;
 use rocket_okapi::swagger_ui::*;
-use service::ServiceCollection;
-use sqlx::{Pool, Postgres, postgres::PgPoolOptions};
+use service::{ServiceCollection, ServiceCollectionError};
+use sqlx::{Pool, Postgres};
 use thiserror::Error;
 
 pub mod adapter;
+pub mod backup;
+pub mod cli;
 pub mod config;
 pub mod controller;
 pub mod database;
 pub mod macros;
 pub mod model;
 pub mod service;
+pub mod validation;
 
 pub fn build_rocket(
     rocket_config: RocketConfig,
@@ -35,12 +40,20 @@ pub fn build_rocket(
     rocket = rocket.manage(postgres_pool);
     rocket = rocket.manage(service_collection.user);
     rocket = rocket.manage(service_collection.setup);
+    rocket = rocket.manage(service_collection.login_throttle);
+    rocket = rocket.manage(service_collection.two_factor);
+    rocket = rocket.manage(service_collection.oidc);
+    rocket = rocket.manage(service_collection.session);
+    rocket = rocket.manage(service_collection.audit);
 
     rocket
 }
 
 #[derive(Debug, Error)]
 pub enum RuntimeError {
+    #[error("Invalid postgres configuration: {0}")]
+    PostgresConfig(#[from] config::postgres::PostgresConfigError),
+
     #[error("Failed to connect to database: {0}")]
     DatabaseConnect(#[from] sqlx::Error),
 
@@ -50,31 +63,77 @@ pub enum RuntimeError {
     #[error("Failed to run database migrations: {0}")]
     Migrate(#[from] sqlx::migrate::MigrateError),
 
+    #[error("Failed to initialize services: {0}")]
+    ServiceCollection(#[from] ServiceCollectionError),
+
+    #[error("Failed to check whether Rasopus needs to be set up: {0}")]
+    SetupCheck(#[from] service::setup::SetupCheckError),
+
     #[error("Rocket failed: {0}")]
     Rocket(#[from] rocket::Error),
+
+    #[error("Failed to back up Rasopus: {0}")]
+    Backup(#[from] backup::BackupError),
+
+    #[error("Failed to restore Rasopus: {0}")]
+    Restore(#[from] backup::RestoreError),
 }
 
 pub async fn run(rasopus_config: RasopusConfig) -> Result<(), RuntimeError> {
     println!("Connecting to database");
-    let postgres_config = PostgresConfig::from(&rasopus_config);
-    let postgres_pool = PgPoolOptions::new()
-        .max_connections(postgres_config.pool_size)
-        .connect(&postgres_config.to_connection_string())
-        .await?;
+    let postgres_config = PostgresConfig::try_from(&rasopus_config)?;
+    let postgres_pool = database::connect(&postgres_config).await?;
 
     println!("Checking database migrations");
-    let migrator = sqlx::migrate!("./migrations");
-    let needs_migration = database::needs_migration(&postgres_pool, &migrator).await?;
+    let needs_migration = database::needs_migration(&postgres_pool, &database::MIGRATOR).await?;
     if needs_migration {
         println!("Applying missing database migrations");
-        migrator.run(&postgres_pool).await?;
+        database::MIGRATOR.run(&postgres_pool).await?;
         println!("Database migrations applied");
     } else {
         println!("Database is up to date");
     }
 
     println!("Initializing services");
-    let service_collection = ServiceCollection::new(&rasopus_config);
+    let service_collection = ServiceCollection::new(&rasopus_config)?;
+
+    let needs_setup = service_collection
+        .setup
+        .needs_setup(&service_collection.user, &postgres_pool)
+        .await?;
+    if needs_setup {
+        println!("Rasopus needs to be set up");
+        if service_collection.setup.is_setup_token_generated() {
+            println!(
+                "Use the following setup token to set up Rasopus: {}",
+                service_collection.setup.setup_token()
+            );
+        } else {
+            println!("Use the setup token from RASOPUS_SETUP_TOKEN to set up Rasopus");
+        }
+    }
+
+    println!("Starting trash purge job");
+    tokio::spawn(
+        service_collection
+            .trash
+            .clone()
+            .run_purge_job(service_collection.audit.clone(), postgres_pool.clone()),
+    );
+
+    println!("Starting session and login attempt purge jobs");
+    tokio::spawn(
+        service_collection
+            .session
+            .clone()
+            .run_purge_job(postgres_pool.clone()),
+    );
+    tokio::spawn(
+        service_collection
+            .login_throttle
+            .clone()
+            .run_purge_job(postgres_pool.clone()),
+    );
 
     println!("Building Rocket with Rasopus configuration");
     let rocket_config = RocketConfig::from(&rasopus_config);
@@ -90,3 +149,29 @@ pub async fn run(rasopus_config: RasopusConfig) -> Result<(), RuntimeError> {
 
     Ok(())
 }
+
+/// Backs up all data of Rasopus into an archive at the given path.
+pub async fn backup(rasopus_config: RasopusConfig, path: &Path) -> Result<(), RuntimeError> {
+    println!("Connecting to database");
+    let postgres_config = PostgresConfig::try_from(&rasopus_config)?;
+    let postgres_pool = database::connect(&postgres_config).await?;
+
+    println!("Writing backup to {}", path.display());
+    let rows = backup::backup(&postgres_pool, &database::MIGRATOR, path).await?;
+    println!("Backed up {} rows", rows);
+
+    Ok(())
+}
+
+/// Restores all data of Rasopus from an archive at the given path into an empty database.
+pub async fn restore(rasopus_config: RasopusConfig, path: &Path) -> Result<(), RuntimeError> {
+    println!("Connecting to database");
+    let postgres_config = PostgresConfig::try_from(&rasopus_config)?;
+    let postgres_pool = database::connect(&postgres_config).await?;
+
+    println!("Restoring backup from {}", path.display());
+    let rows = backup::restore(&postgres_pool, &database::MIGRATOR, path).await?;
+    println!("Restored {} rows", rows);
+
+    Ok(())
+}
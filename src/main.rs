@@ -1,11 +1,25 @@
 use anyhow::Result;
-use rasopus::{config::rasopus::RasopusConfig, run};
+use rasopus::{
+    backup,
+    cli::{Command, USAGE},
+    config::rasopus::RasopusConfig,
+    restore, run,
+};
 
 pub static APP_NAME: &str = "Rasopus";
 pub static APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[rocket::main]
 async fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let command = match Command::parse(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return Err(e.into());
+        }
+    };
+
     println!("Starting {} v{}", APP_NAME, APP_VERSION);
 
     println!("Loading Rasopus environment variables");
@@ -17,7 +31,11 @@ async fn main() -> Result<()> {
         }
     };
 
-    let result = run(rasopus_config).await;
+    let result = match command {
+        Command::Serve => run(rasopus_config).await,
+        Command::Backup(path) => backup(rasopus_config, &path).await,
+        Command::Restore(path) => restore(rasopus_config, &path).await,
+    };
     if let Err(e) = result {
         eprintln!("Rasopus had a runtime error: {}", e);
         return Err(e.into());
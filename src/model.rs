@@ -1,54 +1,97 @@
 use std::fmt::Debug;
 
 use rocket::async_trait;
-use sqlx::{Pool, Postgres};
+use sqlx::PgExecutor;
+use thiserror::Error;
+
+use crate::database;
 
 pub mod entity;
+pub mod list;
 pub mod payload;
+pub mod trash;
+
+/// Whether persisting an entity inserted a new row or updated an existing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Persisted {
+    Inserted,
+    Updated,
+}
+
+#[derive(Debug, Error)]
+pub enum PersistError {
+    #[error("An entity with the same value already exists, violating the unique constraint {0}")]
+    AlreadyExists(String),
+
+    #[error("The entity is in the trash and has to be restored before it can be changed")]
+    Deleted,
+
+    #[error("Database error: {0}")]
+    Database(sqlx::Error),
+}
 
+impl From<sqlx::Error> for PersistError {
+    fn from(error: sqlx::Error) -> Self {
+        match database::violated_unique_constraint(&error) {
+            Some(constraint) => Self::AlreadyExists(constraint.to_string()),
+            None => Self::Database(error),
+        }
+    }
+}
+
+/// An entity that is stored in the database.
+///
+/// Every operation is a single statement that runs on any executor, so it can be part of a transaction.
 #[async_trait]
-pub trait DbEntity: Sized + Send {
-    type Identifier: Clone + Send + 'static;
+pub trait DbEntity: Sized + Send + Sync {
+    type Identifier: Clone + Send + Sync + 'static;
     type ExistsError: Debug;
     type CreateError: Debug;
     type LoadError: Debug;
     type UpdateError: Debug;
     type DeleteError: Debug;
-    type PersistError: Debug
-        + From<Self::ExistsError>
-        + From<Self::CreateError>
-        + From<Self::UpdateError>;
+    type PersistError: Debug;
 
     fn main_table_name() -> &'static str;
     fn get_identifier(&self) -> &Self::Identifier;
 
-    async fn exists(
+    async fn exists<'e, E>(
         identifier: &Self::Identifier,
-        postgres_pool: &Pool<Postgres>,
-    ) -> Result<bool, Self::ExistsError>;
+        executor: E,
+    ) -> Result<bool, Self::ExistsError>
+    where
+        E: PgExecutor<'e>;
 
-    async fn create(&self, postgres_pool: &Pool<Postgres>) -> Result<(), Self::CreateError>;
+    async fn create<'e, E>(&self, executor: E) -> Result<(), Self::CreateError>
+    where
+        E: PgExecutor<'e>;
 
-    async fn load(
+    async fn load<'e, E>(
         identifier: &Self::Identifier,
-        postgres_pool: &Pool<Postgres>,
-    ) -> Result<Self, Self::LoadError>;
+        executor: E,
+    ) -> Result<Self, Self::LoadError>
+    where
+        E: PgExecutor<'e>;
 
-    async fn update(&self, postgres_pool: &Pool<Postgres>) -> Result<(), Self::UpdateError>;
+    /// Returns whether the entity existed and was updated.
+    async fn update<'e, E>(&self, executor: E) -> Result<bool, Self::UpdateError>
+    where
+        E: PgExecutor<'e>;
 
-    async fn delete(
+    /// Returns whether the entity existed and was deleted.
+    ///
+    /// Entities that implement `DbEntityTrash` are moved to the trash instead of being deleted permanently.
+    async fn delete<'e, E>(
         identifier: &Self::Identifier,
-        postgres_pool: &Pool<Postgres>,
-    ) -> Result<(), Self::DeleteError>;
-
-    async fn persist(&self, postgres_pool: &Pool<Postgres>) -> Result<(), Self::PersistError> {
-        let exists = Self::exists(&self.get_identifier().clone(), postgres_pool).await?;
-        if exists {
-            self.update(postgres_pool).await?;
-        } else {
-            self.create(postgres_pool).await?;
-        }
+        executor: E,
+    ) -> Result<bool, Self::DeleteError>
+    where
+        E: PgExecutor<'e>;
 
-        Ok(())
-    }
+    /// Inserts the entity, or updates it if an entity with the same identifier exists, in one atomic statement.
+    ///
+    /// An entity in the trash is not changed, which is reported as `PersistError::Deleted`.
+    async fn persist<'e, E>(&self, executor: E) -> Result<Persisted, Self::PersistError>
+    where
+        E: PgExecutor<'e>;
 }
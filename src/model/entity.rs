@@ -1 +1,8 @@
+pub mod account_lockout;
+pub mod audit_event;
+pub mod login_attempt;
+pub mod recovery_code;
+pub mod session;
 pub mod user;
+pub mod user_identity;
+pub mod user_totp;
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use rasopus_macros::DbEntity;
+use rocket::serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// The database representation of an account lockout.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, FromRow, DbEntity)]
+#[serde(crate = "rocket::serde")]
+#[db(table = "account_lockouts")]
+pub struct DbAccountLockout {
+    /// The lockout's UUID.
+    #[db(id)]
+    pub uuid: Uuid,
+
+    /// The UUID of the locked user.
+    pub user_uuid: Uuid,
+
+    /// The timestamp at which the user was locked.
+    pub locked_at: DateTime<Utc>,
+
+    /// The timestamp until which the user is locked.
+    pub locked_until: DateTime<Utc>,
+
+    /// The timestamp at which the user was unlocked early.
+    pub unlocked_at: Option<DateTime<Utc>>,
+
+    /// The UUID of the user who unlocked the user early.
+    pub unlocked_by: Option<Uuid>,
+}
+
+/// A temporary lockout of a user after too many failed login attempts.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct AccountLockout {
+    /// The lockout's UUID.
+    pub uuid: Uuid,
+
+    /// The UUID of the locked user.
+    pub user_uuid: Uuid,
+
+    /// The timestamp at which the user was locked.
+    pub locked_at: DateTime<Utc>,
+
+    /// The timestamp until which the user is locked.
+    pub locked_until: DateTime<Utc>,
+
+    /// The timestamp at which the user was unlocked early, if they were.
+    pub unlocked_at: Option<DateTime<Utc>>,
+
+    /// The UUID of the user who unlocked the user early, if anyone did.
+    pub unlocked_by: Option<Uuid>,
+}
+
+impl AccountLockout {
+    /// Whether the lockout is in effect at the given time.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.unlocked_at.is_none() && self.locked_until > now
+    }
+}
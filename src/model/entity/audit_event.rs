@@ -0,0 +1,239 @@
+use std::{fmt::Display, net::IpAddr, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use rasopus_macros::DbEntity;
+use rocket::serde::{Deserialize, Serialize, json::Value};
+use sqlx::FromRow;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// The actions that are recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub enum AuditAction {
+    /// Rasopus was set up and the system user was created.
+    SetupCompleted,
+
+    /// A user was created.
+    UserCreated,
+
+    /// The role of a user was changed.
+    UserRoleChanged,
+
+    /// The password of a user was changed.
+    UserPasswordChanged,
+
+    /// A user was moved to the trash.
+    UserDeleted,
+
+    /// A user was restored from the trash.
+    UserRestored,
+
+    /// A user in the trash was deleted permanently, because their external identity logged in again.
+    UserPurged,
+
+    /// A user was unlocked after too many failed login attempts.
+    UserUnlocked,
+
+    /// An identity of an external system was linked to a user.
+    UserIdentityLinked,
+
+    /// A user logged in and a session was issued to them. The UUID of the session is recorded with it.
+    ///
+    /// Sessions are the only credentials Rasopus issues, there are no API tokens.
+    LoginSucceeded,
+
+    /// A login attempt failed.
+    LoginFailed,
+
+    /// A user enabled two-factor authentication and recovery codes were issued to them.
+    TwoFactorEnabled,
+
+    /// A user disabled their two-factor authentication.
+    TwoFactorDisabled,
+
+    /// An admin reset the two-factor authentication of a user.
+    TwoFactorReset,
+}
+
+impl AuditAction {
+    pub const ALL: [Self; 14] = [
+        Self::SetupCompleted,
+        Self::UserCreated,
+        Self::UserRoleChanged,
+        Self::UserPasswordChanged,
+        Self::UserDeleted,
+        Self::UserRestored,
+        Self::UserPurged,
+        Self::UserUnlocked,
+        Self::UserIdentityLinked,
+        Self::LoginSucceeded,
+        Self::LoginFailed,
+        Self::TwoFactorEnabled,
+        Self::TwoFactorDisabled,
+        Self::TwoFactorReset,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SetupCompleted => "setup.completed",
+            Self::UserCreated => "user.created",
+            Self::UserRoleChanged => "user.role_changed",
+            Self::UserPasswordChanged => "user.password_changed",
+            Self::UserDeleted => "user.deleted",
+            Self::UserRestored => "user.restored",
+            Self::UserPurged => "user.purged",
+            Self::UserUnlocked => "user.unlocked",
+            Self::UserIdentityLinked => "user.identity_linked",
+            Self::LoginSucceeded => "login.succeeded",
+            Self::LoginFailed => "login.failed",
+            Self::TwoFactorEnabled => "two_factor.enabled",
+            Self::TwoFactorDisabled => "two_factor.disabled",
+            Self::TwoFactorReset => "two_factor.reset",
+        }
+    }
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Unknown audit action: {0}")]
+pub struct ParseAuditActionError(String);
+
+impl FromStr for AuditAction {
+    type Err = ParseAuditActionError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.as_str() == value)
+            .ok_or_else(|| ParseAuditActionError(value.to_string()))
+    }
+}
+
+/// The kinds of entities an audit event can refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub enum AuditTargetType {
+    /// The event refers to a user.
+    User,
+}
+
+impl AuditTargetType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+        }
+    }
+}
+
+impl Display for AuditTargetType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Unknown audit target type: {0}")]
+pub struct ParseAuditTargetTypeError(String);
+
+impl FromStr for AuditTargetType {
+    type Err = ParseAuditTargetTypeError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "user" => Ok(Self::User),
+            _ => Err(ParseAuditTargetTypeError(value.to_string())),
+        }
+    }
+}
+
+/// The conditions audit events have to match to be listed. Conditions that are not set match all events.
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    pub action: Option<AuditAction>,
+    pub actor_uuid: Option<Uuid>,
+    pub target_type: Option<AuditTargetType>,
+    pub target_uuid: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// The columns audit events can be listed by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuditSortColumn {
+    #[default]
+    OccurredAt,
+}
+
+/// The database representation of an audit event.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, FromRow, DbEntity)]
+#[serde(crate = "rocket::serde")]
+#[db(table = "audit_events")]
+pub struct DbAuditEvent {
+    /// The audit event's UUID.
+    #[db(id)]
+    pub uuid: Uuid,
+
+    /// The action that was done, represented as a string like `user.role_changed`.
+    pub action: String,
+
+    /// The UUID of the user who did the action, if known.
+    pub actor_uuid: Option<Uuid>,
+
+    /// The kind of entity the action was done to, represented as a string like `user`.
+    pub target_type: Option<String>,
+
+    /// The UUID of the entity the action was done to.
+    pub target_uuid: Option<Uuid>,
+
+    /// A snapshot of the entity before the action.
+    pub before: Option<Value>,
+
+    /// A snapshot of the entity after the action.
+    pub after: Option<Value>,
+
+    /// The IP address the action was done from, if known.
+    pub ip_address: Option<String>,
+
+    /// The timestamp at which the action was done.
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// An entry of the audit log, recording who did what to which entity.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct AuditEvent {
+    /// The audit event's UUID.
+    pub uuid: Uuid,
+
+    /// The action that was done.
+    pub action: AuditAction,
+
+    /// The UUID of the user who did the action, if known.
+    /// This is not set for failed logins, for example.
+    pub actor_uuid: Option<Uuid>,
+
+    /// The kind of entity the action was done to.
+    pub target_type: Option<AuditTargetType>,
+
+    /// The UUID of the entity the action was done to.
+    /// The entity might not exist anymore.
+    pub target_uuid: Option<Uuid>,
+
+    /// A snapshot of the entity before the action.
+    pub before: Option<Value>,
+
+    /// A snapshot of the entity after the action.
+    pub after: Option<Value>,
+
+    /// The IP address the action was done from, if known.
+    pub ip_address: Option<IpAddr>,
+
+    /// The timestamp at which the action was done.
+    pub occurred_at: DateTime<Utc>,
+}
@@ -0,0 +1,50 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use rasopus_macros::DbEntity;
+use rocket::serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// The database representation of a login attempt.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, FromRow, DbEntity)]
+#[serde(crate = "rocket::serde")]
+#[db(table = "login_attempts")]
+pub struct DbLoginAttempt {
+    /// The login attempt's UUID.
+    #[db(id)]
+    pub uuid: Uuid,
+
+    /// The canonical form of the username the login was attempted for.
+    pub username: String,
+
+    /// The IP address the login was attempted from, if known.
+    pub ip_address: Option<String>,
+
+    /// Whether the login attempt succeeded.
+    pub succeeded: bool,
+
+    /// The timestamp at which the login was attempted.
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// An attempt to log in, successful or not.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LoginAttempt {
+    /// The login attempt's UUID.
+    pub uuid: Uuid,
+
+    /// The canonical form of the username the login was attempted for.
+    /// This does not necessarily belong to an existing user.
+    pub username: String,
+
+    /// The IP address the login was attempted from, if known.
+    pub ip_address: Option<IpAddr>,
+
+    /// Whether the login attempt succeeded.
+    pub succeeded: bool,
+
+    /// The timestamp at which the login was attempted.
+    pub attempted_at: DateTime<Utc>,
+}
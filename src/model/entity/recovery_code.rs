@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use rasopus_macros::DbEntity;
+use rocket::serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// The database representation of a recovery code.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, FromRow, DbEntity)]
+#[serde(crate = "rocket::serde")]
+#[db(table = "recovery_codes")]
+pub struct DbRecoveryCode {
+    /// The recovery code's UUID.
+    #[db(id)]
+    pub uuid: Uuid,
+
+    /// The UUID of the user the recovery code belongs to.
+    pub user_uuid: Uuid,
+
+    /// The recovery code's hash.
+    pub code_hash: String,
+
+    /// The timestamp at which the recovery code was used.
+    pub used_at: Option<DateTime<Utc>>,
+
+    /// The timestamp at which the recovery code was created.
+    pub created_at: DateTime<Utc>,
+}
+
+/// A one-time code that can be used instead of a TOTP code, for example when the authenticator got lost.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RecoveryCode {
+    /// The recovery code's UUID.
+    pub uuid: Uuid,
+
+    /// The UUID of the user the recovery code belongs to.
+    pub user_uuid: Uuid,
+
+    /// The recovery code's hash. The recovery code itself is only shown once, when it is generated.
+    pub code_hash: String,
+
+    /// The timestamp at which the recovery code was used, if it was.
+    pub used_at: Option<DateTime<Utc>>,
+
+    /// The timestamp at which the recovery code was created.
+    pub created_at: DateTime<Utc>,
+}
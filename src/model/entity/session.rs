@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use rasopus_macros::DbEntity;
+use rocket::serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// The database representation of a session.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, FromRow, DbEntity)]
+#[serde(crate = "rocket::serde")]
+#[db(table = "sessions")]
+pub struct DbSession {
+    /// The session's UUID.
+    #[db(id)]
+    pub uuid: Uuid,
+
+    /// The UUID of the user the session belongs to.
+    pub user_uuid: Uuid,
+
+    /// The timestamp at which the session was issued.
+    pub created_at: DateTime<Utc>,
+
+    /// The timestamp at which the session expires.
+    pub expires_at: DateTime<Utc>,
+
+    /// The timestamp at which the session was revoked, if it was.
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// A session of a logged in user. The session cookie holds its UUID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Session {
+    /// The session's UUID.
+    pub uuid: Uuid,
+
+    /// The UUID of the user the session belongs to.
+    pub user_uuid: Uuid,
+
+    /// The timestamp at which the session was issued.
+    pub created_at: DateTime<Utc>,
+
+    /// The timestamp at which the session expires. It is not extended while the session is used.
+    pub expires_at: DateTime<Utc>,
+
+    /// The timestamp at which the session was revoked, for example by logging out, if it was.
+    pub revoked_at: Option<DateTime<Utc>>,
+}
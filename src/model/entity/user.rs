@@ -1,8 +1,13 @@
+use std::str::FromStr;
+
 use chrono::{DateTime, Utc};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use orion::pwhash::PasswordHash;
+use rasopus_macros::DbEntity;
 use rocket::serde::{Deserialize, Serialize};
+use rocket_okapi::JsonSchema;
 use sqlx::FromRow;
+use thiserror::Error;
 use uuid::Uuid;
 
 /// The possible roles a user can have.
@@ -17,11 +22,13 @@ use uuid::Uuid;
     Ord,
     Serialize,
     Deserialize,
+    JsonSchema,
     IntoPrimitive,
     TryFromPrimitive,
 )]
 #[repr(i16)]
 #[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
 pub enum Role {
     /// The system role, which has the highest level of access.
     System = 0,
@@ -34,11 +41,68 @@ pub enum Role {
     User = 2,
 }
 
+#[derive(Debug, Error)]
+#[error("Unknown role: {0}")]
+pub struct ParseRoleError(String);
+
+impl FromStr for Role {
+    type Err = ParseRoleError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "system" => Ok(Self::System),
+            "admin" => Ok(Self::Admin),
+            "user" => Ok(Self::User),
+            _ => Err(ParseRoleError(value.to_string())),
+        }
+    }
+}
+
+/// The conditions users have to match to be listed. Conditions that are not set match all users.
+#[derive(Debug, Default)]
+pub struct UserFilter {
+    pub role: Option<Role>,
+    pub created_since: Option<DateTime<Utc>>,
+    pub created_until: Option<DateTime<Utc>>,
+
+    /// Whether to list the users in the trash instead of the active ones.
+    pub deleted: bool,
+
+    /// The user who moved the listed users to the trash.
+    pub deleted_by: Option<Uuid>,
+}
+
+/// The columns users can be listed by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UserSortColumn {
+    #[default]
+    CreatedAt,
+    Username,
+}
+
+#[derive(Debug, Error)]
+#[error("Unknown sort column: {0}")]
+pub struct ParseUserSortColumnError(String);
+
+impl FromStr for UserSortColumn {
+    type Err = ParseUserSortColumnError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "created_at" => Ok(Self::CreatedAt),
+            "username" => Ok(Self::Username),
+            _ => Err(ParseUserSortColumnError(value.to_string())),
+        }
+    }
+}
+
 /// The database representation of a user.
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, FromRow, DbEntity)]
 #[serde(crate = "rocket::serde")]
+#[db(table = "users", soft_delete)]
 pub struct DbUser {
     /// The user's UUID.
+    #[db(id)]
     pub uuid: Uuid,
 
     /// The user's username.
@@ -50,8 +114,18 @@ pub struct DbUser {
     /// The user's password hash.
     pub password_hash: String,
 
-    /// The timestamp at which the user was created, represented as a 64-bit signed integer.
-    pub created_at: i64,
+    /// The timestamp at which the user was created.
+    pub created_at: DateTime<Utc>,
+
+    /// The timestamp at which the user was last changed, maintained by the database.
+    #[db(generated)]
+    pub updated_at: DateTime<Utc>,
+
+    /// The timestamp at which the user was moved to the trash, if they were.
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// The UUID of the user who moved the user to the trash, if they were.
+    pub deleted_by: Option<Uuid>,
 }
 
 /// A user
@@ -72,4 +146,13 @@ pub struct User {
 
     /// The timestamp at which the user was created.
     pub created_at: DateTime<Utc>,
+
+    /// The timestamp at which the user was last changed.
+    pub updated_at: DateTime<Utc>,
+
+    /// The timestamp at which the user was deleted, if they are in the trash.
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// The UUID of the user who deleted the user, if they are in the trash.
+    pub deleted_by: Option<Uuid>,
 }
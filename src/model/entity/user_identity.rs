@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use rasopus_macros::DbEntity;
+use rocket::serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// The database representation of a user identity.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, FromRow, DbEntity)]
+#[serde(crate = "rocket::serde")]
+#[db(table = "user_identities")]
+pub struct DbUserIdentity {
+    /// The user identity's UUID.
+    #[db(id)]
+    pub uuid: Uuid,
+
+    /// The UUID of the user the identity belongs to.
+    pub user_uuid: Uuid,
+
+    /// The issuer of the identity.
+    pub issuer: String,
+
+    /// The subject identifying the user at the issuer.
+    pub subject: String,
+
+    /// The timestamp at which the identity was linked.
+    pub created_at: DateTime<Utc>,
+}
+
+/// An identity of a user at an external identity provider, which the user can log in with.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UserIdentity {
+    /// The user identity's UUID.
+    pub uuid: Uuid,
+
+    /// The UUID of the user the identity belongs to.
+    pub user_uuid: Uuid,
+
+    /// The issuer of the identity.
+    pub issuer: String,
+
+    /// The subject identifying the user at the issuer. It never changes, unlike the username.
+    pub subject: String,
+
+    /// The timestamp at which the identity was linked.
+    pub created_at: DateTime<Utc>,
+}
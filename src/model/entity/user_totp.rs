@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use rasopus_macros::DbEntity;
+use rocket::serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// The database representation of a user's TOTP second factor.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, FromRow, DbEntity)]
+#[serde(crate = "rocket::serde")]
+#[db(table = "user_totps")]
+pub struct DbUserTotp {
+    /// The UUID of the user the TOTP belongs to.
+    #[db(id)]
+    pub user_uuid: Uuid,
+
+    /// The encrypted TOTP secret.
+    pub encrypted_secret: Vec<u8>,
+
+    /// Whether the enrollment was confirmed with a valid code.
+    pub confirmed: bool,
+
+    /// The last time step a code was accepted for, represented as a 64-bit signed integer.
+    pub last_used_step: Option<i64>,
+
+    /// The timestamp at which the TOTP was created.
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user's TOTP second factor, as described in RFC 6238.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UserTotp {
+    /// The UUID of the user the TOTP belongs to.
+    pub user_uuid: Uuid,
+
+    /// The TOTP secret, encrypted with a key derived from the configured secret key.
+    pub encrypted_secret: Vec<u8>,
+
+    /// Whether the enrollment was confirmed with a valid code.
+    /// Unconfirmed TOTPs are not required when logging in.
+    pub confirmed: bool,
+
+    /// The last time step a code was accepted for. Codes for this or earlier steps are rejected, so codes can't be replayed.
+    pub last_used_step: Option<u64>,
+
+    /// The timestamp at which the TOTP was created.
+    pub created_at: DateTime<Utc>,
+}
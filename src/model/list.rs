@@ -0,0 +1,543 @@
+use std::{fmt::Display, str::FromStr};
+
+use base64::{DecodeError, Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use rocket::{
+    async_trait,
+    serde::{Deserialize, Serialize, json::serde_json},
+};
+use sqlx::{FromRow, PgExecutor, Postgres, QueryBuilder, postgres::PgRow};
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::DbEntity;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 500;
+
+/// The order in which entities are listed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub enum SortDirection {
+    #[default]
+    #[serde(rename = "asc")]
+    Ascending,
+    #[serde(rename = "desc")]
+    Descending,
+}
+
+impl SortDirection {
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Ascending => "ASC",
+            Self::Descending => "DESC",
+        }
+    }
+
+    /// The operator that selects the rows after the cursor.
+    fn after_operator(&self) -> &'static str {
+        match self {
+            Self::Ascending => ">",
+            Self::Descending => "<",
+        }
+    }
+}
+
+impl Display for SortDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ascending => f.write_str("asc"),
+            Self::Descending => f.write_str("desc"),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Unknown sort direction: {0}")]
+pub struct ParseSortDirectionError(String);
+
+#[derive(Debug, Error)]
+#[error("Invalid RFC 3339 timestamp: {0}")]
+pub struct ParseTimestampError(pub String);
+
+/// Parses an RFC 3339 timestamp that a listing is filtered by.
+pub fn parse_timestamp(value: Option<&str>) -> Result<Option<DateTime<Utc>>, ParseTimestampError> {
+    value
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|timestamp| timestamp.with_timezone(&Utc))
+                .map_err(|_| ParseTimestampError(value.to_string()))
+        })
+        .transpose()
+}
+
+impl FromStr for SortDirection {
+    type Err = ParseSortDirectionError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "asc" => Ok(Self::Ascending),
+            "desc" => Ok(Self::Descending),
+            _ => Err(ParseSortDirectionError(value.to_string())),
+        }
+    }
+}
+
+/// A column entities can be sorted by.
+///
+/// Implemented by enums of the allowed columns, so a column name from a request never ends up in a query.
+/// The columns must not be nullable, as rows with a null value would be skipped when paging.
+pub trait SortColumn: Copy + Send + Sync {
+    fn column_name(&self) -> &'static str;
+
+    /// The kind of value the column holds, which the value of a cursor has to match.
+    fn value_kind(&self) -> CursorValueKind;
+}
+
+/// The conditions entities have to match to be listed.
+pub trait ListFilter: Send + Sync {
+    /// Pushes the conditions of the filter, each starting with ` AND `, onto a query that already has a `WHERE` clause.
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>);
+
+    /// Whether the entities in the trash are listed instead of the others. Only matters for entities with a trash.
+    fn lists_trash(&self) -> bool {
+        false
+    }
+}
+
+/// A value of the sort column or the identifier of the last entity of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub enum CursorValue {
+    Integer(i64),
+    Timestamp(DateTime<Utc>),
+    Text(String),
+    Uuid(Uuid),
+}
+
+impl CursorValue {
+    pub fn kind(&self) -> CursorValueKind {
+        match self {
+            Self::Integer(_) => CursorValueKind::Integer,
+            Self::Timestamp(_) => CursorValueKind::Timestamp,
+            Self::Text(_) => CursorValueKind::Text,
+            Self::Uuid(_) => CursorValueKind::Uuid,
+        }
+    }
+
+    fn push_bind(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            Self::Integer(value) => query.push_bind(*value),
+            Self::Timestamp(value) => query.push_bind(*value),
+            Self::Text(value) => query.push_bind(value.clone()),
+            Self::Uuid(value) => query.push_bind(*value),
+        };
+    }
+}
+
+/// The kind of a `CursorValue`, matching the type of the column it is compared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorValueKind {
+    Integer,
+    Timestamp,
+    Text,
+    Uuid,
+}
+
+/// The position after the last entity of a page, from which the next page continues.
+///
+/// Cursors are handed out as opaque URL-safe base64 strings, so clients don't depend on what they contain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Cursor {
+    /// The name of the column the listing is sorted by.
+    pub sort: String,
+
+    /// The direction the listing is sorted in.
+    pub direction: SortDirection,
+
+    /// The value of the sort column of the last entity.
+    pub value: CursorValue,
+
+    /// The identifier of the last entity, which orders entities with the same value.
+    pub identifier: CursorValue,
+}
+
+impl Cursor {
+    /// Checks that the cursor was handed out for a listing sorted the same way, and that its values can be compared
+    /// with the sort column and the identifier column.
+    ///
+    /// Cursors come from clients, so a cursor of another listing or a tampered one has to be rejected here instead
+    /// of failing in the database.
+    fn check<S: SortColumn>(
+        &self,
+        sort: S,
+        direction: SortDirection,
+        identifier_kind: CursorValueKind,
+    ) -> Result<(), ListError> {
+        if self.sort != sort.column_name() || self.direction != direction {
+            return Err(ListError::CursorSortMismatch(
+                self.sort.clone(),
+                self.direction,
+            ));
+        }
+
+        if self.value.kind() != sort.value_kind() || self.identifier.kind() != identifier_kind {
+            return Err(ListError::CursorValueMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let json = serde_json::to_vec(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&URL_SAFE_NO_PAD.encode(json))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ParseCursorError {
+    #[error("The cursor is not valid base64: {0}")]
+    Base64(#[from] DecodeError),
+
+    #[error("The cursor is malformed: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl FromStr for Cursor {
+    type Err = ParseCursorError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let json = URL_SAFE_NO_PAD.decode(value)?;
+        let cursor = serde_json::from_slice(&json)?;
+
+        Ok(cursor)
+    }
+}
+
+/// What to list: the entities matching the filter, sorted by a column, starting after the cursor.
+#[derive(Debug)]
+pub struct ListQuery<F, S> {
+    pub filter: F,
+    pub sort: S,
+    pub direction: SortDirection,
+    pub cursor: Option<Cursor>,
+
+    /// The maximum number of entities to list, at most `MAX_PAGE_SIZE`.
+    pub limit: i64,
+}
+
+/// A page of entities and the cursor of the next page, if there is one.
+#[derive(Debug)]
+pub struct Listed<T> {
+    pub entities: Vec<T>,
+    pub next_cursor: Option<Cursor>,
+}
+
+impl<T> Listed<T> {
+    pub fn try_map<U, E>(self, map: impl FnMut(T) -> Result<U, E>) -> Result<Listed<U>, E> {
+        let entities = self
+            .entities
+            .into_iter()
+            .map(map)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Listed {
+            entities,
+            next_cursor: self.next_cursor,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ListError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("The cursor belongs to a listing sorted by {0} {1}")]
+    CursorSortMismatch(String, SortDirection),
+
+    #[error("The cursor holds values that don't match the columns of the listing")]
+    CursorValueMismatch,
+}
+
+/// An entity that can be listed page by page.
+///
+/// Pages are keyset paginated: a page continues after the sort value and identifier of the last entity of the
+/// previous one, so entities that are created or deleted while paging don't shift the following pages.
+#[async_trait]
+pub trait DbEntityList: DbEntity + for<'r> FromRow<'r, PgRow> + Unpin {
+    type Filter: ListFilter;
+    type SortColumn: SortColumn;
+
+    fn identifier_column() -> &'static str;
+    fn identifier_value_kind() -> CursorValueKind;
+
+    /// The column that holds when an entity was moved to the trash, for entities with a trash. Entities in the trash
+    /// are left out of listings, unless the filter lists the trash, in which case only they are listed.
+    fn deleted_at_column() -> Option<&'static str> {
+        None
+    }
+
+    fn cursor_value(&self, sort: Self::SortColumn) -> CursorValue;
+    fn identifier_cursor_value(&self) -> CursorValue;
+
+    async fn list<'e, E>(
+        query: &ListQuery<Self::Filter, Self::SortColumn>,
+        executor: E,
+    ) -> Result<Listed<Self>, ListError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let sort_column = query.sort.column_name();
+        let identifier_column = Self::identifier_column();
+        let limit = query.limit.clamp(1, MAX_PAGE_SIZE);
+
+        let mut builder = QueryBuilder::new(format!(
+            "SELECT * FROM {} WHERE TRUE",
+            Self::main_table_name()
+        ));
+        push_filter(Self::deleted_at_column(), &query.filter, &mut builder);
+
+        if let Some(cursor) = &query.cursor {
+            cursor.check(query.sort, query.direction, Self::identifier_value_kind())?;
+
+            builder.push(format!(
+                " AND ({}, {}) {} (",
+                sort_column,
+                identifier_column,
+                query.direction.after_operator()
+            ));
+            cursor.value.push_bind(&mut builder);
+            builder.push(", ");
+            cursor.identifier.push_bind(&mut builder);
+            builder.push(")");
+        }
+
+        let direction = query.direction.as_sql();
+        builder
+            .push(format!(
+                " ORDER BY {} {}, {} {} LIMIT ",
+                sort_column, direction, identifier_column, direction
+            ))
+            .push_bind(limit + 1);
+
+        let mut entities: Vec<Self> = builder.build_query_as().fetch_all(executor).await?;
+
+        // One more entity than requested was loaded to know whether there is a next page
+        let next_cursor = if entities.len() as i64 > limit {
+            entities.truncate(limit as usize);
+            entities.last().map(|entity| Cursor {
+                sort: sort_column.to_string(),
+                direction: query.direction,
+                value: entity.cursor_value(query.sort),
+                identifier: entity.identifier_cursor_value(),
+            })
+        } else {
+            None
+        };
+
+        Ok(Listed {
+            entities,
+            next_cursor,
+        })
+    }
+
+    async fn count<'e, E>(filter: &Self::Filter, executor: E) -> Result<i64, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        let mut builder = QueryBuilder::new(format!(
+            "SELECT COUNT(*) FROM {} WHERE TRUE",
+            Self::main_table_name()
+        ));
+        push_filter(Self::deleted_at_column(), filter, &mut builder);
+
+        let (count,): (i64,) = builder.build_query_as().fetch_one(executor).await?;
+        Ok(count)
+    }
+}
+
+/// Pushes the conditions of the filter onto the query, leaving out either the entities in the trash or the others.
+fn push_filter<F: ListFilter>(
+    deleted_at_column: Option<&str>,
+    filter: &F,
+    query: &mut QueryBuilder<'_, Postgres>,
+) {
+    if let Some(deleted_at_column) = deleted_at_column {
+        let condition = if filter.lists_trash() {
+            "IS NOT NULL"
+        } else {
+            "IS NULL"
+        };
+        query.push(format!(" AND {} {}", deleted_at_column, condition));
+    }
+
+    filter.push_conditions(query);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy)]
+    enum TestSortColumn {
+        CreatedAt,
+        Name,
+    }
+
+    impl SortColumn for TestSortColumn {
+        fn column_name(&self) -> &'static str {
+            match self {
+                Self::CreatedAt => "created_at",
+                Self::Name => "name",
+            }
+        }
+
+        fn value_kind(&self) -> CursorValueKind {
+            match self {
+                Self::CreatedAt => CursorValueKind::Timestamp,
+                Self::Name => CursorValueKind::Text,
+            }
+        }
+    }
+
+    fn cursor() -> Cursor {
+        Cursor {
+            sort: "created_at".to_string(),
+            direction: SortDirection::Descending,
+            value: CursorValue::Timestamp(Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap()),
+            identifier: CursorValue::Uuid(Uuid::nil()),
+        }
+    }
+
+    #[test]
+    fn decodes_encoded_cursor() {
+        let cursor = cursor();
+        let encoded = cursor.to_string();
+
+        assert!(
+            encoded
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        );
+        assert_eq!(encoded.parse::<Cursor>().unwrap(), cursor);
+    }
+
+    #[test]
+    fn rejects_undecodable_cursors() {
+        assert!(matches!(
+            "not base64!".parse::<Cursor>(),
+            Err(ParseCursorError::Base64(_))
+        ));
+        assert!(matches!(
+            URL_SAFE_NO_PAD.encode("{}").parse::<Cursor>(),
+            Err(ParseCursorError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn accepts_cursor_of_same_listing() {
+        let result = cursor().check(
+            TestSortColumn::CreatedAt,
+            SortDirection::Descending,
+            CursorValueKind::Uuid,
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn rejects_cursor_of_other_sort() {
+        let result = cursor().check(
+            TestSortColumn::Name,
+            SortDirection::Descending,
+            CursorValueKind::Uuid,
+        );
+        assert!(matches!(
+            result,
+            Err(ListError::CursorSortMismatch(sort, SortDirection::Descending)) if sort == "created_at"
+        ));
+
+        let result = cursor().check(
+            TestSortColumn::CreatedAt,
+            SortDirection::Ascending,
+            CursorValueKind::Uuid,
+        );
+        assert!(matches!(result, Err(ListError::CursorSortMismatch(..))));
+    }
+
+    #[test]
+    fn rejects_cursor_with_values_of_wrong_kind() {
+        let mut cursor = cursor();
+        cursor.value = CursorValue::Text("2026-10-18".to_string());
+        let result = cursor.check(
+            TestSortColumn::CreatedAt,
+            SortDirection::Descending,
+            CursorValueKind::Uuid,
+        );
+        assert!(matches!(result, Err(ListError::CursorValueMismatch)));
+
+        let mut cursor = self::cursor();
+        cursor.identifier = CursorValue::Integer(1);
+        let result = cursor.check(
+            TestSortColumn::CreatedAt,
+            SortDirection::Descending,
+            CursorValueKind::Uuid,
+        );
+        assert!(matches!(result, Err(ListError::CursorValueMismatch)));
+    }
+
+    #[test]
+    fn parses_timestamps_in_utc() {
+        assert_eq!(parse_timestamp(None).unwrap(), None);
+        assert_eq!(
+            parse_timestamp(Some("2026-10-18T14:00:00+02:00")).unwrap(),
+            Some(Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap())
+        );
+        assert!(matches!(
+            parse_timestamp(Some("2026-10-18")),
+            Err(ParseTimestampError(value)) if value == "2026-10-18"
+        ));
+    }
+
+    struct TestFilter {
+        trash: bool,
+    }
+
+    impl ListFilter for TestFilter {
+        fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>) {
+            query.push(" AND name = 'alice'");
+        }
+
+        fn lists_trash(&self) -> bool {
+            self.trash
+        }
+    }
+
+    fn filtered_sql(deleted_at_column: Option<&str>, trash: bool) -> String {
+        let mut query = QueryBuilder::new("SELECT * FROM entries WHERE TRUE");
+        push_filter(deleted_at_column, &TestFilter { trash }, &mut query);
+        query.sql().to_string()
+    }
+
+    #[test]
+    fn leaves_out_trash_unless_listed() {
+        assert_eq!(
+            filtered_sql(Some("deleted_at"), false),
+            "SELECT * FROM entries WHERE TRUE AND deleted_at IS NULL AND name = 'alice'"
+        );
+        assert_eq!(
+            filtered_sql(Some("deleted_at"), true),
+            "SELECT * FROM entries WHERE TRUE AND deleted_at IS NOT NULL AND name = 'alice'"
+        );
+        assert_eq!(
+            filtered_sql(None, false),
+            "SELECT * FROM entries WHERE TRUE AND name = 'alice'"
+        );
+    }
+}
@@ -1 +1,8 @@
+pub mod audit;
+pub mod login;
+pub mod oidc;
+pub mod page;
+pub mod password;
 pub mod setup;
+pub mod two_factor;
+pub mod user;
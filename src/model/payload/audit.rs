@@ -0,0 +1,154 @@
+use rocket::{
+    FromForm,
+    serde::{Deserialize, Serialize, json::Value},
+};
+use rocket_okapi::JsonSchema;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    model::{
+        entity::audit_event::AuditEvent,
+        list::{ListError, ParseCursorError, ParseTimestampError},
+    },
+    service,
+};
+
+use super::page::Page;
+
+// ### GET /admin/audit ###
+
+/// The query parameters to filter and page through the audit log.
+#[derive(Debug, FromForm, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct AuditGetQuery {
+    /// Only list events of this action, for example `user.role_changed`.
+    pub action: Option<String>,
+
+    /// Only list events done by the user with this UUID.
+    pub actor_uuid: Option<Uuid>,
+
+    /// Only list events done to entities of this kind, for example `user`.
+    pub target_type: Option<String>,
+
+    /// Only list events done to the entity with this UUID.
+    pub target_uuid: Option<Uuid>,
+
+    /// Only list events that happened at or after this RFC 3339 timestamp.
+    pub since: Option<String>,
+
+    /// Only list events that happened before this RFC 3339 timestamp.
+    pub until: Option<String>,
+
+    /// The cursor of the page to list, as returned with the previous page.
+    pub cursor: Option<String>,
+
+    /// The maximum number of events to list. Defaults to 50, at most 500 events are listed.
+    pub limit: Option<i64>,
+}
+
+/// An entry of the audit log.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct AuditEventResponse {
+    /// The audit event's UUID.
+    pub uuid: Uuid,
+
+    /// The action that was done, for example `user.role_changed`.
+    pub action: String,
+
+    /// The UUID of the user who did the action, if known.
+    pub actor_uuid: Option<Uuid>,
+
+    /// The kind of entity the action was done to, for example `user`.
+    pub target_type: Option<String>,
+
+    /// The UUID of the entity the action was done to.
+    pub target_uuid: Option<Uuid>,
+
+    /// A snapshot of the entity before the action.
+    pub before: Option<Value>,
+
+    /// A snapshot of the entity after the action.
+    pub after: Option<Value>,
+
+    /// The IP address the action was done from, if known.
+    pub ip_address: Option<String>,
+
+    /// When the action was done, as an RFC 3339 timestamp.
+    pub occurred_at: String,
+}
+
+impl From<AuditEvent> for AuditEventResponse {
+    fn from(audit_event: AuditEvent) -> Self {
+        Self {
+            uuid: audit_event.uuid,
+            action: audit_event.action.to_string(),
+            actor_uuid: audit_event.actor_uuid,
+            target_type: audit_event
+                .target_type
+                .map(|target_type| target_type.to_string()),
+            target_uuid: audit_event.target_uuid,
+            before: audit_event.before,
+            after: audit_event.after,
+            ip_address: audit_event
+                .ip_address
+                .map(|ip_address| ip_address.to_string()),
+            occurred_at: audit_event.occurred_at.to_rfc3339(),
+        }
+    }
+}
+
+/// A page of the audit log, newest events first.
+pub type AuditGetResponse = Page<AuditEventResponse>;
+
+/// An error response containing one of the possible errors that can occur while listing the audit log.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum AuditGetErrorResponse {
+    /// The given action is unknown
+    #[error("The given action is unknown: {0}")]
+    InvalidAction(String),
+
+    /// The given target type is unknown
+    #[error("The given target type is unknown: {0}")]
+    InvalidTargetType(String),
+
+    /// The given timestamp is not a valid RFC 3339 timestamp
+    #[error("The given timestamp is not a valid RFC 3339 timestamp: {0}")]
+    InvalidTimestamp(String),
+
+    /// The given cursor is invalid
+    #[error("The given cursor is invalid: {0}")]
+    InvalidCursor(String),
+
+    /// The audit service returned an error while listing the audit events
+    #[error("The audit service returned an error while listing the audit events: {0}")]
+    AuditServiceList(String),
+}
+
+impl From<ParseTimestampError> for AuditGetErrorResponse {
+    fn from(error: ParseTimestampError) -> Self {
+        Self::InvalidTimestamp(error.0)
+    }
+}
+
+impl From<ParseCursorError> for AuditGetErrorResponse {
+    fn from(error: ParseCursorError) -> Self {
+        Self::InvalidCursor(error.to_string())
+    }
+}
+
+impl From<service::audit::ListError> for AuditGetErrorResponse {
+    fn from(error: service::audit::ListError) -> Self {
+        match error {
+            service::audit::ListError::List(
+                ListError::CursorSortMismatch(..) | ListError::CursorValueMismatch,
+            ) => Self::InvalidCursor(error.to_string()),
+            error => Self::AuditServiceList(error.to_string()),
+        }
+    }
+}
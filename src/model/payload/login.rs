@@ -0,0 +1,296 @@
+use rocket::serde::{Deserialize, Serialize};
+use rocket_okapi::JsonSchema;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::service;
+
+// ### POST /login ###
+
+/// The payload to log in with a username and password.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct LoginPostPayload {
+    // The username of the user to log in as.
+    pub username: String,
+
+    // The password of the user to log in as.
+    pub password: String,
+}
+
+/// A response indicating that the username and password are correct.
+///
+/// If the user has two-factor authentication enabled, the login has to be completed through `POST /login/two-factor`.
+/// Otherwise, the session cookie is set alongside it.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct LoginPostResponse {
+    /// The UUID of the user.
+    pub uuid: Uuid,
+
+    /// Whether a second factor is required to complete the login.
+    pub two_factor_required: bool,
+}
+
+/// An error response containing one of the possible errors that can occur while logging in.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum LoginPostErrorResponse {
+    /// The username or password is wrong
+    #[error("The username or password is wrong")]
+    InvalidCredentials,
+
+    /// The user is locked after too many failed login attempts
+    #[error("The user is locked after too many failed login attempts until {0}")]
+    Locked(String),
+
+    /// There were too many failed login attempts, so the next attempt has to wait
+    #[error("There were too many failed login attempts, retry in {0} seconds")]
+    TooManyAttempts(u64),
+
+    /// The password can't be verified right now
+    #[error("The password can't be verified right now")]
+    VerificationUnavailable,
+
+    /// The login throttle service returned an error while checking whether the login attempt is allowed
+    #[error(
+        "The login throttle service returned an error while checking whether the login attempt is allowed: {0}"
+    )]
+    LoginThrottleCheck(String),
+
+    /// The login throttle service returned an error while recording the login attempt
+    #[error("The login throttle service returned an error while recording the login attempt: {0}")]
+    LoginThrottleRecord(String),
+
+    /// The user service returned an error while loading the user
+    #[error("The user service returned an error while loading the user: {0}")]
+    UserServiceLoad(String),
+
+    /// The user service returned an error while authenticating the user
+    #[error("The user service returned an error while authenticating the user: {0}")]
+    UserServiceAuthenticate(String),
+
+    /// The two-factor service returned an error while checking whether the user has two-factor authentication enabled
+    #[error(
+        "The two-factor service returned an error while checking whether the user has two-factor authentication enabled: {0}"
+    )]
+    TwoFactorServiceEnabled(String),
+
+    /// The audit service returned an error while recording the login attempt
+    #[error("The audit service returned an error while recording the login attempt: {0}")]
+    AuditServiceRecord(String),
+
+    /// The session service returned an error while issuing the session
+    #[error("The session service returned an error while issuing the session: {0}")]
+    SessionServiceIssue(String),
+
+    /// The login could not be committed to the database
+    #[error("The login could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::login_throttle::CheckError> for LoginPostErrorResponse {
+    fn from(error: service::login_throttle::CheckError) -> Self {
+        match error {
+            service::login_throttle::CheckError::Locked(locked_until) => {
+                Self::Locked(locked_until.to_rfc3339())
+            }
+            service::login_throttle::CheckError::TooManyAttempts(retry_after) => {
+                Self::TooManyAttempts(retry_after)
+            }
+            error => Self::LoginThrottleCheck(error.to_string()),
+        }
+    }
+}
+
+impl From<tokio::sync::AcquireError> for LoginPostErrorResponse {
+    fn from(_: tokio::sync::AcquireError) -> Self {
+        Self::VerificationUnavailable
+    }
+}
+
+impl From<service::login_throttle::RecordError> for LoginPostErrorResponse {
+    fn from(error: service::login_throttle::RecordError) -> Self {
+        Self::LoginThrottleRecord(error.to_string())
+    }
+}
+
+impl From<service::user::LoadError> for LoginPostErrorResponse {
+    fn from(error: service::user::LoadError) -> Self {
+        Self::UserServiceLoad(error.to_string())
+    }
+}
+
+impl From<service::user::AuthenticateError> for LoginPostErrorResponse {
+    fn from(error: service::user::AuthenticateError) -> Self {
+        Self::UserServiceAuthenticate(error.to_string())
+    }
+}
+
+impl From<service::two_factor::EnabledError> for LoginPostErrorResponse {
+    fn from(error: service::two_factor::EnabledError) -> Self {
+        Self::TwoFactorServiceEnabled(error.to_string())
+    }
+}
+
+impl From<service::audit::RecordError> for LoginPostErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::session::IssueError> for LoginPostErrorResponse {
+    fn from(error: service::session::IssueError) -> Self {
+        Self::SessionServiceIssue(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for LoginPostErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
+
+// ### POST /login/two-factor ###
+
+/// The payload to complete a login with a second factor.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct LoginTwoFactorPostPayload {
+    // A code from the authenticator app or one of the recovery codes.
+    pub code: String,
+}
+
+/// A response indicating that the login was completed. The session cookie is set alongside it.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct LoginTwoFactorPostResponse {
+    /// The UUID of the logged in user.
+    pub uuid: Uuid,
+}
+
+/// An error response containing one of the possible errors that can occur while completing a login with a second factor.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum LoginTwoFactorPostErrorResponse {
+    /// The code is wrong or was already used
+    #[error("The code is wrong or was already used")]
+    InvalidCode,
+
+    /// The user is locked after too many failed login attempts
+    #[error("The user is locked after too many failed login attempts until {0}")]
+    Locked(String),
+
+    /// There were too many failed login attempts, so the next attempt has to wait
+    #[error("There were too many failed login attempts, retry in {0} seconds")]
+    TooManyAttempts(u64),
+
+    /// The TOTP secret can't be decrypted, as the secret key was changed. A recovery code still works, otherwise an admin has to reset two-factor authentication
+    #[error(
+        "The TOTP secret can't be decrypted, as the secret key was changed. A recovery code still works, otherwise an admin has to reset two-factor authentication"
+    )]
+    TotpSecretUnreadable,
+
+    /// The login throttle service returned an error while checking whether the login attempt is allowed
+    #[error(
+        "The login throttle service returned an error while checking whether the login attempt is allowed: {0}"
+    )]
+    LoginThrottleCheck(String),
+
+    /// The login throttle service returned an error while recording the login attempt
+    #[error("The login throttle service returned an error while recording the login attempt: {0}")]
+    LoginThrottleRecord(String),
+
+    /// The two-factor service returned an error while verifying the code
+    #[error("The two-factor service returned an error while verifying the code: {0}")]
+    TwoFactorServiceVerify(String),
+
+    /// The audit service returned an error while recording the login attempt
+    #[error("The audit service returned an error while recording the login attempt: {0}")]
+    AuditServiceRecord(String),
+
+    /// The session service returned an error while issuing the session
+    #[error("The session service returned an error while issuing the session: {0}")]
+    SessionServiceIssue(String),
+
+    /// The login could not be committed to the database
+    #[error("The login could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::login_throttle::CheckError> for LoginTwoFactorPostErrorResponse {
+    fn from(error: service::login_throttle::CheckError) -> Self {
+        match error {
+            service::login_throttle::CheckError::Locked(locked_until) => {
+                Self::Locked(locked_until.to_rfc3339())
+            }
+            service::login_throttle::CheckError::TooManyAttempts(retry_after) => {
+                Self::TooManyAttempts(retry_after)
+            }
+            error => Self::LoginThrottleCheck(error.to_string()),
+        }
+    }
+}
+
+impl From<service::login_throttle::RecordError> for LoginTwoFactorPostErrorResponse {
+    fn from(error: service::login_throttle::RecordError) -> Self {
+        Self::LoginThrottleRecord(error.to_string())
+    }
+}
+
+impl From<service::two_factor::VerifyError> for LoginTwoFactorPostErrorResponse {
+    fn from(error: service::two_factor::VerifyError) -> Self {
+        match error {
+            service::two_factor::VerifyError::SecretUnreadable => Self::TotpSecretUnreadable,
+            error => Self::TwoFactorServiceVerify(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for LoginTwoFactorPostErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::session::IssueError> for LoginTwoFactorPostErrorResponse {
+    fn from(error: service::session::IssueError) -> Self {
+        Self::SessionServiceIssue(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for LoginTwoFactorPostErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
+
+// ### POST /logout ###
+
+/// An empty success response, indicating that the session has been ended and its cookie has been removed.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct LogoutPostResponse {}
+
+/// An error response containing one of the possible errors that can occur while logging out.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum LogoutPostErrorResponse {
+    /// The session service returned an error while revoking the session
+    #[error("The session service returned an error while revoking the session: {0}")]
+    SessionServiceRevoke(String),
+}
+
+impl From<service::session::RevokeError> for LogoutPostErrorResponse {
+    fn from(error: service::session::RevokeError) -> Self {
+        Self::SessionServiceRevoke(error.to_string())
+    }
+}
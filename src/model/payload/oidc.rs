@@ -0,0 +1,245 @@
+use rocket::{
+    FromForm,
+    serde::{Deserialize, Serialize},
+};
+use rocket_okapi::JsonSchema;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::service;
+
+// ### GET /login/oidc ###
+// ### GET /users/me/oidc ###
+
+/// An error response containing one of the possible errors that can occur while starting a login through the identity provider.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum OidcGetErrorResponse {
+    /// OpenID Connect is not configured
+    #[error("OpenID Connect is not configured")]
+    NotConfigured,
+
+    /// The OpenID Connect service returned an error while starting the login
+    #[error("The OpenID Connect service returned an error while starting the login: {0}")]
+    OidcServiceBegin(String),
+}
+
+impl From<service::oidc::BeginError> for OidcGetErrorResponse {
+    fn from(error: service::oidc::BeginError) -> Self {
+        match error {
+            service::oidc::BeginError::NotConfigured => Self::NotConfigured,
+            error => Self::OidcServiceBegin(error.to_string()),
+        }
+    }
+}
+
+// ### GET /login/oidc/callback ###
+
+/// The query parameters the identity provider redirects back with.
+#[derive(Debug, FromForm, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct OidcCallbackQuery {
+    /// The authorization code, if the login was successful.
+    pub code: Option<String>,
+
+    /// The state that was sent to the identity provider.
+    pub state: Option<String>,
+
+    /// The error code, if the login failed.
+    pub error: Option<String>,
+
+    /// A description of the error, if the login failed.
+    pub error_description: Option<String>,
+}
+
+/// A response indicating that the login through the identity provider was successful. Unless a second factor is required, the session cookie is set alongside it.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct OidcCallbackGetResponse {
+    /// The UUID of the logged in user.
+    pub uuid: Uuid,
+
+    /// Whether the user was created during this login.
+    pub created: bool,
+
+    /// Whether the identity was linked to the logged in user during this login.
+    pub linked: bool,
+
+    /// Whether the login has to be completed with a second factor through `POST /login/two-factor`.
+    pub two_factor_required: bool,
+}
+
+/// An error response containing one of the possible errors that can occur while completing a login through the identity provider.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum OidcCallbackGetErrorResponse {
+    /// OpenID Connect is not configured
+    #[error("OpenID Connect is not configured")]
+    NotConfigured,
+
+    /// There is no login in progress
+    #[error("There is no login in progress")]
+    NoPendingLogin,
+
+    /// The identity provider did not return an authorization code or state
+    #[error("The identity provider did not return an authorization code or state")]
+    MissingCode,
+
+    /// The identity provider denied the login
+    #[error("The identity provider denied the login: {0}")]
+    Denied(String),
+
+    /// The login took too long
+    #[error("The login took too long")]
+    Expired,
+
+    /// The state does not match the login in progress
+    #[error("The state does not match the login in progress")]
+    InvalidState,
+
+    /// The identity could not be verified
+    #[error("The identity could not be verified: {0}")]
+    InvalidIdentity(String),
+
+    /// The identity provider did not return a valid username
+    #[error("The identity provider did not return a valid username: {0}")]
+    InvalidUsername(String),
+
+    /// A user with the username from the identity provider already exists
+    #[error("A user with the username from the identity provider already exists")]
+    UsernameTaken,
+
+    /// The identity is not linked to any user and new users are not created automatically
+    #[error("The identity is not linked to any user and new users are not created automatically")]
+    NotLinked,
+
+    /// The claims of the identity don't map to any role
+    #[error("The claims of the identity don't map to any role")]
+    NoMatchingRole,
+
+    /// The identity is already linked to another user
+    #[error("The identity is already linked to another user")]
+    IdentityLinkedToOtherUser,
+
+    /// The user is locked after too many failed login attempts
+    #[error("The user is locked after too many failed login attempts until {0}")]
+    Locked(String),
+
+    /// There were too many failed login attempts, so the next attempt has to wait
+    #[error("There were too many failed login attempts, retry in {0} seconds")]
+    TooManyAttempts(u64),
+
+    /// The identity provider could not be reached
+    #[error("The identity provider could not be reached: {0}")]
+    IdentityProvider(String),
+
+    /// The login throttle service returned an error while checking whether the login attempt is allowed
+    #[error(
+        "The login throttle service returned an error while checking whether the login attempt is allowed: {0}"
+    )]
+    LoginThrottleCheck(String),
+
+    /// The login throttle service returned an error while recording the login attempt
+    #[error("The login throttle service returned an error while recording the login attempt: {0}")]
+    LoginThrottleRecord(String),
+
+    /// The two-factor service returned an error while checking whether the user has two-factor authentication enabled
+    #[error(
+        "The two-factor service returned an error while checking whether the user has two-factor authentication enabled: {0}"
+    )]
+    TwoFactorServiceEnabled(String),
+
+    /// The OpenID Connect service returned an error while completing the login
+    #[error("The OpenID Connect service returned an error while completing the login: {0}")]
+    OidcServiceComplete(String),
+
+    /// The audit service returned an error while recording the login
+    #[error("The audit service returned an error while recording the login: {0}")]
+    AuditServiceRecord(String),
+
+    /// The session service returned an error while issuing the session
+    #[error("The session service returned an error while issuing the session: {0}")]
+    SessionServiceIssue(String),
+
+    /// The login could not be committed to the database
+    #[error("The login could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::oidc::CompleteError> for OidcCallbackGetErrorResponse {
+    fn from(error: service::oidc::CompleteError) -> Self {
+        match error {
+            service::oidc::CompleteError::NotConfigured => Self::NotConfigured,
+            service::oidc::CompleteError::Expired => Self::Expired,
+            service::oidc::CompleteError::InvalidState => Self::InvalidState,
+            service::oidc::CompleteError::MissingIdToken
+            | service::oidc::CompleteError::InvalidIdToken(_) => {
+                Self::InvalidIdentity(error.to_string())
+            }
+            service::oidc::CompleteError::MissingUsernameClaim(_)
+            | service::oidc::CompleteError::InvalidUsername(_) => {
+                Self::InvalidUsername(error.to_string())
+            }
+            service::oidc::CompleteError::UsernameTaken => Self::UsernameTaken,
+            service::oidc::CompleteError::NotLinked => Self::NotLinked,
+            service::oidc::CompleteError::NoMatchingRole => Self::NoMatchingRole,
+            service::oidc::CompleteError::IdentityLinkedToOtherUser => {
+                Self::IdentityLinkedToOtherUser
+            }
+            service::oidc::CompleteError::Discovery(_)
+            | service::oidc::CompleteError::TokenExchange(_)
+            | service::oidc::CompleteError::UserInfo(_) => {
+                Self::IdentityProvider(error.to_string())
+            }
+            error => Self::OidcServiceComplete(error.to_string()),
+        }
+    }
+}
+
+impl From<service::login_throttle::CheckError> for OidcCallbackGetErrorResponse {
+    fn from(error: service::login_throttle::CheckError) -> Self {
+        match error {
+            service::login_throttle::CheckError::Locked(locked_until) => {
+                Self::Locked(locked_until.to_rfc3339())
+            }
+            service::login_throttle::CheckError::TooManyAttempts(retry_after) => {
+                Self::TooManyAttempts(retry_after)
+            }
+            error => Self::LoginThrottleCheck(error.to_string()),
+        }
+    }
+}
+
+impl From<service::login_throttle::RecordError> for OidcCallbackGetErrorResponse {
+    fn from(error: service::login_throttle::RecordError) -> Self {
+        Self::LoginThrottleRecord(error.to_string())
+    }
+}
+
+impl From<service::two_factor::EnabledError> for OidcCallbackGetErrorResponse {
+    fn from(error: service::two_factor::EnabledError) -> Self {
+        Self::TwoFactorServiceEnabled(error.to_string())
+    }
+}
+
+impl From<service::audit::RecordError> for OidcCallbackGetErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::session::IssueError> for OidcCallbackGetErrorResponse {
+    fn from(error: service::session::IssueError) -> Self {
+        Self::SessionServiceIssue(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for OidcCallbackGetErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
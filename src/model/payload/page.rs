@@ -0,0 +1,35 @@
+use rocket::serde::{Deserialize, Serialize};
+use rocket_okapi::JsonSchema;
+
+use crate::model::list::Listed;
+
+/// A page of a listing.
+///
+/// The next page is requested by passing `next_cursor` as the `cursor` query parameter, together with the same
+/// filters and sorting.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct Page<T> {
+    /// The items on this page.
+    pub items: Vec<T>,
+
+    /// The cursor of the next page, or nothing if this is the last page.
+    pub next_cursor: Option<String>,
+
+    /// The number of all items matching the filters.
+    pub total: i64,
+}
+
+impl<T> Page<T> {
+    pub fn new<E>(listed: Listed<E>, total: i64) -> Self
+    where
+        T: From<E>,
+    {
+        Self {
+            items: listed.entities.into_iter().map(T::from).collect(),
+            next_cursor: listed.next_cursor.map(|cursor| cursor.to_string()),
+            total,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use rocket::serde::{Deserialize, Serialize};
+use rocket_okapi::JsonSchema;
+
+use crate::validation::password::PasswordViolation;
+
+/// A rule of the password policy that the given password violates.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum PasswordViolationResponse {
+    /// The password is shorter than the minimum length
+    TooShort { min_length: usize },
+
+    /// The password is longer than the maximum length
+    TooLong { max_length: usize },
+
+    /// The password does not contain a lowercase letter
+    MissingLowercase,
+
+    /// The password does not contain an uppercase letter
+    MissingUppercase,
+
+    /// The password does not contain a digit
+    MissingDigit,
+
+    /// The password does not contain a symbol
+    MissingSymbol,
+
+    /// The password's estimated strength, from 0 to 4, is below the minimum score
+    TooWeak {
+        score: u8,
+        min_score: u8,
+        warning: Option<String>,
+    },
+
+    /// The password is known from a data breach
+    Breached,
+}
+
+impl From<PasswordViolation> for PasswordViolationResponse {
+    fn from(violation: PasswordViolation) -> Self {
+        match violation {
+            PasswordViolation::TooShort { min_length } => Self::TooShort { min_length },
+            PasswordViolation::TooLong { max_length } => Self::TooLong { max_length },
+            PasswordViolation::MissingLowercase => Self::MissingLowercase,
+            PasswordViolation::MissingUppercase => Self::MissingUppercase,
+            PasswordViolation::MissingDigit => Self::MissingDigit,
+            PasswordViolation::MissingSymbol => Self::MissingSymbol,
+            PasswordViolation::TooWeak {
+                score,
+                min_score,
+                warning,
+            } => Self::TooWeak {
+                score,
+                min_score,
+                warning,
+            },
+            PasswordViolation::Breached => Self::Breached,
+        }
+    }
+}
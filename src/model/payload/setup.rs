@@ -2,7 +2,9 @@ use rocket::serde::{Deserialize, Serialize};
 use rocket_okapi::JsonSchema;
 use thiserror::Error;
 
-use crate::service;
+use crate::{service, validation::password::PasswordError};
+
+use super::password::PasswordViolationResponse;
 
 // ### GET ###
 
@@ -40,6 +42,9 @@ impl From<service::setup::SetupCheckError> for SetupGetErrorResponse {
 #[serde(crate = "rocket::serde")]
 #[schemars(crate = "okapi::schemars")]
 pub struct SetupPostPayload {
+    // The setup token, which is printed to the log on boot or set through RASOPUS_SETUP_TOKEN.
+    pub setup_token: String,
+
     // The username of the initial system user to create.
     pub username: String,
 
@@ -64,10 +69,26 @@ pub enum SetupPostErrorResponse {
     )]
     SetupServiceCheck(String),
 
+    /// The given setup token is invalid
+    #[error("The given setup token is invalid")]
+    InvalidSetupToken,
+
     /// The backend is already set up
     #[error("The backend is already set up")]
     AlreadySetup,
 
+    /// The given username is invalid
+    #[error("The given username is invalid: {0}")]
+    InvalidUsername(String),
+
+    /// The given username is already taken
+    #[error("The given username is already taken")]
+    UsernameTaken,
+
+    /// The given password violates the password policy
+    #[error("The given password violates the password policy: {0:?}")]
+    InvalidPassword(Vec<PasswordViolationResponse>),
+
     /// The user service returned an error while generating the system user
     #[error("The user service returned an error while generating the system user: {0}")]
     UserServiceGenerate(String),
@@ -77,6 +98,14 @@ pub enum SetupPostErrorResponse {
         "The user service returned an error while creating the system user inside the database: {0}"
     )]
     UserServiceCreate(String),
+
+    /// The audit service returned an error while recording the setup
+    #[error("The audit service returned an error while recording the setup: {0}")]
+    AuditServiceRecord(String),
+
+    /// The setup could not be committed to the database
+    #[error("The setup could not be committed to the database: {0}")]
+    UnitOfWork(String),
 }
 
 impl From<service::setup::SetupCheckError> for SetupPostErrorResponse {
@@ -85,14 +114,47 @@ impl From<service::setup::SetupCheckError> for SetupPostErrorResponse {
     }
 }
 
+impl From<service::setup::SetupTokenError> for SetupPostErrorResponse {
+    fn from(error: service::setup::SetupTokenError) -> Self {
+        match error {
+            service::setup::SetupTokenError::Invalid => Self::InvalidSetupToken,
+        }
+    }
+}
+
 impl From<service::user::GenerateError> for SetupPostErrorResponse {
     fn from(error: service::user::GenerateError) -> Self {
-        Self::UserServiceGenerate(error.to_string())
+        match error {
+            service::user::GenerateError::InvalidUsername(error) => {
+                Self::InvalidUsername(error.to_string())
+            }
+            service::user::GenerateError::InvalidPassword(PasswordError::PolicyViolation(
+                violations,
+            )) => Self::InvalidPassword(violations.into_iter().map(Into::into).collect()),
+            error => Self::UserServiceGenerate(error.to_string()),
+        }
     }
 }
 
 impl From<service::user::CreateError> for SetupPostErrorResponse {
     fn from(error: service::user::CreateError) -> Self {
-        Self::UserServiceCreate(error.to_string())
+        match error {
+            // Another setup request won the race
+            service::user::CreateError::SystemUserAlreadyExists => Self::AlreadySetup,
+            service::user::CreateError::UsernameTaken => Self::UsernameTaken,
+            error => Self::UserServiceCreate(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for SetupPostErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for SetupPostErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
     }
 }
@@ -0,0 +1,246 @@
+use rocket::serde::{Deserialize, Serialize};
+use rocket_okapi::JsonSchema;
+use thiserror::Error;
+
+use crate::service;
+
+// ### POST /users/me/totp ###
+
+/// A response containing everything needed to add the TOTP to an authenticator app.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct TotpPostResponse {
+    /// The TOTP secret, encoded as base32, for entering it manually.
+    pub secret: String,
+
+    /// The otpauth:// URI containing all TOTP parameters.
+    pub provisioning_uri: String,
+
+    /// A QR code of the provisioning URI as a PNG data URI.
+    pub qr_code: String,
+}
+
+/// An error response containing one of the possible errors that can occur while starting the TOTP enrollment.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum TotpPostErrorResponse {
+    /// The user already has two-factor authentication enabled
+    #[error("The user already has two-factor authentication enabled")]
+    AlreadyEnrolled,
+
+    /// The two-factor service returned an error while starting the enrollment
+    #[error("The two-factor service returned an error while starting the enrollment: {0}")]
+    TwoFactorServiceEnroll(String),
+}
+
+impl From<service::two_factor::EnrollError> for TotpPostErrorResponse {
+    fn from(error: service::two_factor::EnrollError) -> Self {
+        match error {
+            service::two_factor::EnrollError::AlreadyEnrolled => Self::AlreadyEnrolled,
+            error => Self::TwoFactorServiceEnroll(error.to_string()),
+        }
+    }
+}
+
+// ### POST /users/me/totp/confirm ###
+
+/// The payload to confirm the TOTP enrollment.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct TotpConfirmPostPayload {
+    // A code from the authenticator app.
+    pub code: String,
+}
+
+/// A response indicating that two-factor authentication is now enabled.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct TotpConfirmPostResponse {
+    /// Single-use codes for logging in without the authenticator app. They are only shown once.
+    pub recovery_codes: Vec<String>,
+}
+
+/// An error response containing one of the possible errors that can occur while confirming the TOTP enrollment.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum TotpConfirmPostErrorResponse {
+    /// There is no TOTP enrollment to confirm
+    #[error("There is no TOTP enrollment to confirm")]
+    NotEnrolling,
+
+    /// The code is wrong
+    #[error("The code is wrong")]
+    InvalidCode,
+
+    /// The TOTP secret can't be decrypted, as the secret key was changed. The enrollment has to be started again
+    #[error(
+        "The TOTP secret can't be decrypted, as the secret key was changed. The enrollment has to be started again"
+    )]
+    TotpSecretUnreadable,
+
+    /// The two-factor service returned an error while confirming the enrollment
+    #[error("The two-factor service returned an error while confirming the enrollment: {0}")]
+    TwoFactorServiceConfirm(String),
+
+    /// The audit service returned an error while recording that two-factor authentication was enabled
+    #[error(
+        "The audit service returned an error while recording that two-factor authentication was enabled: {0}"
+    )]
+    AuditServiceRecord(String),
+
+    /// The confirmation could not be committed to the database
+    #[error("The confirmation could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::two_factor::ConfirmError> for TotpConfirmPostErrorResponse {
+    fn from(error: service::two_factor::ConfirmError) -> Self {
+        match error {
+            service::two_factor::ConfirmError::NotEnrolling => Self::NotEnrolling,
+            service::two_factor::ConfirmError::InvalidCode => Self::InvalidCode,
+            service::two_factor::ConfirmError::SecretUnreadable => Self::TotpSecretUnreadable,
+            error => Self::TwoFactorServiceConfirm(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for TotpConfirmPostErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for TotpConfirmPostErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
+
+// ### POST /users/me/totp/disable ###
+
+/// The payload to disable two-factor authentication.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct TotpDisablePostPayload {
+    // A code from the authenticator app or one of the recovery codes.
+    pub code: String,
+}
+
+/// An empty success response, indicating that two-factor authentication has been disabled.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct TotpDisablePostResponse {}
+
+/// An error response containing one of the possible errors that can occur while disabling two-factor authentication.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum TotpDisablePostErrorResponse {
+    /// The user has no two-factor authentication enabled
+    #[error("The user has no two-factor authentication enabled")]
+    NotEnrolled,
+
+    /// The code is wrong or was already used
+    #[error("The code is wrong or was already used")]
+    InvalidCode,
+
+    /// The TOTP secret can't be decrypted, as the secret key was changed. A recovery code still works, otherwise an admin has to reset two-factor authentication
+    #[error(
+        "The TOTP secret can't be decrypted, as the secret key was changed. A recovery code still works, otherwise an admin has to reset two-factor authentication"
+    )]
+    TotpSecretUnreadable,
+
+    /// The user is locked after too many failed attempts
+    #[error("The user is locked after too many failed attempts until {0}")]
+    Locked(String),
+
+    /// There were too many failed attempts, so the next attempt has to wait
+    #[error("There were too many failed attempts, retry in {0} seconds")]
+    TooManyAttempts(u64),
+
+    /// The login throttle service returned an error while checking whether the attempt is allowed
+    #[error(
+        "The login throttle service returned an error while checking whether the attempt is allowed: {0}"
+    )]
+    LoginThrottleCheck(String),
+
+    /// The login throttle service returned an error while recording the attempt
+    #[error("The login throttle service returned an error while recording the attempt: {0}")]
+    LoginThrottleRecord(String),
+
+    /// The two-factor service returned an error while verifying the code
+    #[error("The two-factor service returned an error while verifying the code: {0}")]
+    TwoFactorServiceVerify(String),
+
+    /// The two-factor service returned an error while removing the TOTP
+    #[error("The two-factor service returned an error while removing the TOTP: {0}")]
+    TwoFactorServiceDisable(String),
+
+    /// The audit service returned an error while recording that two-factor authentication was disabled
+    #[error(
+        "The audit service returned an error while recording that two-factor authentication was disabled: {0}"
+    )]
+    AuditServiceRecord(String),
+
+    /// The change could not be committed to the database
+    #[error("The change could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::login_throttle::CheckError> for TotpDisablePostErrorResponse {
+    fn from(error: service::login_throttle::CheckError) -> Self {
+        match error {
+            service::login_throttle::CheckError::Locked(locked_until) => {
+                Self::Locked(locked_until.to_rfc3339())
+            }
+            service::login_throttle::CheckError::TooManyAttempts(retry_after) => {
+                Self::TooManyAttempts(retry_after)
+            }
+            error => Self::LoginThrottleCheck(error.to_string()),
+        }
+    }
+}
+
+impl From<service::login_throttle::RecordError> for TotpDisablePostErrorResponse {
+    fn from(error: service::login_throttle::RecordError) -> Self {
+        Self::LoginThrottleRecord(error.to_string())
+    }
+}
+
+impl From<service::two_factor::VerifyError> for TotpDisablePostErrorResponse {
+    fn from(error: service::two_factor::VerifyError) -> Self {
+        match error {
+            service::two_factor::VerifyError::NotEnrolled => Self::NotEnrolled,
+            service::two_factor::VerifyError::SecretUnreadable => Self::TotpSecretUnreadable,
+            error => Self::TwoFactorServiceVerify(error.to_string()),
+        }
+    }
+}
+
+impl From<service::two_factor::DisableError> for TotpDisablePostErrorResponse {
+    fn from(error: service::two_factor::DisableError) -> Self {
+        match error {
+            service::two_factor::DisableError::NotEnrolled => Self::NotEnrolled,
+            error => Self::TwoFactorServiceDisable(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for TotpDisablePostErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for TotpDisablePostErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
@@ -0,0 +1,752 @@
+use rocket::{
+    FromForm,
+    serde::{Deserialize, Serialize},
+};
+use rocket_okapi::JsonSchema;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    model::{
+        entity::user::{Role, User},
+        list::{ListError, ParseCursorError, ParseTimestampError},
+    },
+    service,
+    validation::password::PasswordError,
+};
+
+use super::{page::Page, password::PasswordViolationResponse};
+
+// ### GET /admin/users and GET /admin/users/trash ###
+
+/// The query parameters to filter, sort and page through the users.
+#[derive(Debug, FromForm, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UsersGetQuery {
+    /// Only list users with this role, for example `admin`.
+    pub role: Option<String>,
+
+    /// Only list users created at or after this RFC 3339 timestamp.
+    pub created_since: Option<String>,
+
+    /// Only list users created before this RFC 3339 timestamp.
+    pub created_until: Option<String>,
+
+    /// The column to sort by, either `created_at` or `username`. Defaults to `created_at`.
+    pub sort: Option<String>,
+
+    /// The sort direction, either `asc` or `desc`. Defaults to `asc`.
+    pub direction: Option<String>,
+
+    /// The cursor of the page to list, as returned with the previous page.
+    pub cursor: Option<String>,
+
+    /// The maximum number of users to list. Defaults to 50, at most 500 users are listed.
+    pub limit: Option<i64>,
+}
+
+/// A user, without their credentials.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserResponse {
+    /// The user's UUID.
+    pub uuid: Uuid,
+
+    /// The user's username.
+    pub username: String,
+
+    /// The user's role.
+    pub role: Role,
+
+    /// When the user was created, as an RFC 3339 timestamp.
+    pub created_at: String,
+
+    /// When the user was last changed, as an RFC 3339 timestamp.
+    pub updated_at: String,
+
+    /// When the user was moved to the trash, as an RFC 3339 timestamp. Only set for users in the trash.
+    pub deleted_at: Option<String>,
+
+    /// The UUID of the user who moved the user to the trash. Only set for users in the trash.
+    pub deleted_by: Option<Uuid>,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            uuid: user.uuid,
+            username: user.username,
+            role: user.role,
+            created_at: user.created_at.to_rfc3339(),
+            updated_at: user.updated_at.to_rfc3339(),
+            deleted_at: user.deleted_at.map(|deleted_at| deleted_at.to_rfc3339()),
+            deleted_by: user.deleted_by,
+        }
+    }
+}
+
+/// A page of the users.
+pub type UsersGetResponse = Page<UserResponse>;
+
+/// An error response containing one of the possible errors that can occur while listing the users.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum UsersGetErrorResponse {
+    /// The given role is unknown
+    #[error("The given role is unknown: {0}")]
+    InvalidRole(String),
+
+    /// The given timestamp is not a valid RFC 3339 timestamp
+    #[error("The given timestamp is not a valid RFC 3339 timestamp: {0}")]
+    InvalidTimestamp(String),
+
+    /// The given sort column is unknown
+    #[error("The given sort column is unknown: {0}")]
+    InvalidSort(String),
+
+    /// The given sort direction is unknown
+    #[error("The given sort direction is unknown: {0}")]
+    InvalidDirection(String),
+
+    /// The given cursor is invalid
+    #[error("The given cursor is invalid: {0}")]
+    InvalidCursor(String),
+
+    /// The user service returned an error while listing the users
+    #[error("The user service returned an error while listing the users: {0}")]
+    UserServiceList(String),
+}
+
+impl From<ParseTimestampError> for UsersGetErrorResponse {
+    fn from(error: ParseTimestampError) -> Self {
+        Self::InvalidTimestamp(error.0)
+    }
+}
+
+impl From<ParseCursorError> for UsersGetErrorResponse {
+    fn from(error: ParseCursorError) -> Self {
+        Self::InvalidCursor(error.to_string())
+    }
+}
+
+impl From<service::user::ListError> for UsersGetErrorResponse {
+    fn from(error: service::user::ListError) -> Self {
+        match error {
+            service::user::ListError::List(
+                ListError::CursorSortMismatch(..) | ListError::CursorValueMismatch,
+            ) => Self::InvalidCursor(error.to_string()),
+            error => Self::UserServiceList(error.to_string()),
+        }
+    }
+}
+
+// ### POST /users ###
+
+/// The payload to create a user.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserPostPayload {
+    // The username of the user to create.
+    pub username: String,
+
+    // The password of the user to create.
+    pub password: String,
+
+    // The role of the user to create. There is only one system user, so it has to be `Admin` or `User`.
+    pub role: Role,
+}
+
+/// The user that was created.
+pub type UserPostResponse = UserResponse;
+
+/// An error response containing one of the possible errors that can occur while creating a user.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum UserPostErrorResponse {
+    /// The role is higher than the role of the requesting user
+    #[error("The role is higher than the role of the requesting user")]
+    InsufficientRole,
+
+    /// There is only one system user, who is created during the setup
+    #[error("There is only one system user, who is created during the setup")]
+    SystemRole,
+
+    /// The given username is invalid
+    #[error("The given username is invalid: {0}")]
+    InvalidUsername(String),
+
+    /// The given username is already taken
+    #[error("The given username is already taken")]
+    UsernameTaken,
+
+    /// The given password violates the password policy
+    #[error("The given password violates the password policy: {0:?}")]
+    InvalidPassword(Vec<PasswordViolationResponse>),
+
+    /// The user service returned an error while generating the user
+    #[error("The user service returned an error while generating the user: {0}")]
+    UserServiceGenerate(String),
+
+    /// The user service returned an error while creating the user inside the database
+    #[error("The user service returned an error while creating the user inside the database: {0}")]
+    UserServiceCreate(String),
+
+    /// The audit service returned an error while recording the creation
+    #[error("The audit service returned an error while recording the creation: {0}")]
+    AuditServiceRecord(String),
+
+    /// The creation could not be committed to the database
+    #[error("The creation could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::user::GenerateError> for UserPostErrorResponse {
+    fn from(error: service::user::GenerateError) -> Self {
+        match error {
+            service::user::GenerateError::InvalidUsername(error) => {
+                Self::InvalidUsername(error.to_string())
+            }
+            service::user::GenerateError::InvalidPassword(PasswordError::PolicyViolation(
+                violations,
+            )) => Self::InvalidPassword(violations.into_iter().map(Into::into).collect()),
+            error => Self::UserServiceGenerate(error.to_string()),
+        }
+    }
+}
+
+impl From<service::user::CreateError> for UserPostErrorResponse {
+    fn from(error: service::user::CreateError) -> Self {
+        match error {
+            service::user::CreateError::UsernameTaken => Self::UsernameTaken,
+            error => Self::UserServiceCreate(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for UserPostErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for UserPostErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
+
+// ### PUT /users/me/password ###
+
+/// The payload to change the password of the logged in user.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserPasswordPutPayload {
+    // The current password of the user, to confirm that it's really them.
+    pub current_password: String,
+
+    // The new password of the user.
+    pub new_password: String,
+}
+
+/// An empty success response, indicating that the password has been changed.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserPasswordPutResponse {}
+
+/// An error response containing one of the possible errors that can occur while changing the password.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum UserPasswordPutErrorResponse {
+    /// The given current password is wrong
+    #[error("The given current password is wrong")]
+    InvalidCurrentPassword,
+
+    /// The given new password violates the password policy
+    #[error("The given new password violates the password policy: {0:?}")]
+    InvalidPassword(Vec<PasswordViolationResponse>),
+
+    /// The user is locked after too many failed login attempts
+    #[error("The user is locked after too many failed login attempts until {0}")]
+    Locked(String),
+
+    /// There were too many failed login attempts, so the next attempt has to wait
+    #[error("There were too many failed login attempts, retry in {0} seconds")]
+    TooManyAttempts(u64),
+
+    /// The password can't be verified right now
+    #[error("The password can't be verified right now")]
+    VerificationUnavailable,
+
+    /// The login throttle service returned an error while checking whether the attempt is allowed
+    #[error(
+        "The login throttle service returned an error while checking whether the attempt is allowed: {0}"
+    )]
+    LoginThrottleCheck(String),
+
+    /// The login throttle service returned an error while recording the attempt
+    #[error("The login throttle service returned an error while recording the attempt: {0}")]
+    LoginThrottleRecord(String),
+
+    /// The user service returned an error while verifying the current password
+    #[error("The user service returned an error while verifying the current password: {0}")]
+    UserServiceVerify(String),
+
+    /// The user service returned an error while setting the new password
+    #[error("The user service returned an error while setting the new password: {0}")]
+    UserServiceSetPassword(String),
+
+    /// The user service returned an error while updating the user
+    #[error("The user service returned an error while updating the user: {0}")]
+    UserServiceUpdate(String),
+
+    /// The audit service returned an error while recording the change
+    #[error("The audit service returned an error while recording the change: {0}")]
+    AuditServiceRecord(String),
+
+    /// The change could not be committed to the database
+    #[error("The change could not be committed to the database: {0}")]
+    UnitOfWork(String),
+
+    /// The session service returned an error while revoking the other sessions
+    #[error("The session service returned an error while revoking the other sessions: {0}")]
+    SessionServiceRevoke(String),
+}
+
+impl From<service::login_throttle::CheckError> for UserPasswordPutErrorResponse {
+    fn from(error: service::login_throttle::CheckError) -> Self {
+        match error {
+            service::login_throttle::CheckError::Locked(locked_until) => {
+                Self::Locked(locked_until.to_rfc3339())
+            }
+            service::login_throttle::CheckError::TooManyAttempts(retry_after) => {
+                Self::TooManyAttempts(retry_after)
+            }
+            error => Self::LoginThrottleCheck(error.to_string()),
+        }
+    }
+}
+
+impl From<tokio::sync::AcquireError> for UserPasswordPutErrorResponse {
+    fn from(_: tokio::sync::AcquireError) -> Self {
+        Self::VerificationUnavailable
+    }
+}
+
+impl From<service::login_throttle::RecordError> for UserPasswordPutErrorResponse {
+    fn from(error: service::login_throttle::RecordError) -> Self {
+        Self::LoginThrottleRecord(error.to_string())
+    }
+}
+
+impl From<orion::errors::UnknownCryptoError> for UserPasswordPutErrorResponse {
+    fn from(error: orion::errors::UnknownCryptoError) -> Self {
+        Self::UserServiceVerify(error.to_string())
+    }
+}
+
+impl From<service::user::SetPasswordError> for UserPasswordPutErrorResponse {
+    fn from(error: service::user::SetPasswordError) -> Self {
+        match error {
+            service::user::SetPasswordError::InvalidPassword(PasswordError::PolicyViolation(
+                violations,
+            )) => Self::InvalidPassword(violations.into_iter().map(Into::into).collect()),
+            error => Self::UserServiceSetPassword(error.to_string()),
+        }
+    }
+}
+
+impl From<service::user::UpdateError> for UserPasswordPutErrorResponse {
+    fn from(error: service::user::UpdateError) -> Self {
+        Self::UserServiceUpdate(error.to_string())
+    }
+}
+
+impl From<service::audit::RecordError> for UserPasswordPutErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for UserPasswordPutErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
+
+impl From<service::session::RevokeError> for UserPasswordPutErrorResponse {
+    fn from(error: service::session::RevokeError) -> Self {
+        Self::SessionServiceRevoke(error.to_string())
+    }
+}
+
+// ### PUT /users/<uuid>/role ###
+
+/// The payload to change the role of a user.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserRolePutPayload {
+    // The new role of the user. There is only one system user, so it has to be `Admin` or `User`.
+    pub role: Role,
+}
+
+/// An empty success response, indicating that the role has been changed.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserRolePutResponse {}
+
+/// An error response containing one of the possible errors that can occur while changing the role of a user.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum UserRolePutErrorResponse {
+    /// The user does not exist
+    #[error("The user does not exist")]
+    UserNotFound,
+
+    /// The user or the new role is higher than the role of the requesting user
+    #[error("The user or the new role is higher than the role of the requesting user")]
+    InsufficientRole,
+
+    /// There is only one system user, who is created during the setup
+    #[error("There is only one system user, who is created during the setup")]
+    SystemRole,
+
+    /// Users can't change their own role
+    #[error("Users can't change their own role")]
+    CannotChangeOwnRole,
+
+    /// The user service returned an error while loading the user
+    #[error("The user service returned an error while loading the user: {0}")]
+    UserServiceLoad(String),
+
+    /// The user service returned an error while updating the user
+    #[error("The user service returned an error while updating the user: {0}")]
+    UserServiceUpdate(String),
+
+    /// The audit service returned an error while recording the role change
+    #[error("The audit service returned an error while recording the role change: {0}")]
+    AuditServiceRecord(String),
+
+    /// The role change could not be committed to the database
+    #[error("The role change could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::user::LoadError> for UserRolePutErrorResponse {
+    fn from(error: service::user::LoadError) -> Self {
+        Self::UserServiceLoad(error.to_string())
+    }
+}
+
+impl From<service::user::UpdateError> for UserRolePutErrorResponse {
+    fn from(error: service::user::UpdateError) -> Self {
+        match error {
+            service::user::UpdateError::NotFound => Self::UserNotFound,
+            error => Self::UserServiceUpdate(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for UserRolePutErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for UserRolePutErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
+
+// ### DELETE /users/<uuid> ###
+
+/// An empty success response, indicating that the user has been moved to the trash.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserDeleteResponse {}
+
+/// An error response containing one of the possible errors that can occur while deleting a user.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum UserDeleteErrorResponse {
+    /// The user does not exist
+    #[error("The user does not exist")]
+    UserNotFound,
+
+    /// The user has a higher role than the requesting user
+    #[error("The user has a higher role than the requesting user")]
+    InsufficientRole,
+
+    /// Users can't delete themselves
+    #[error("Users can't delete themselves")]
+    CannotDeleteSelf,
+
+    /// The user service returned an error while loading the user
+    #[error("The user service returned an error while loading the user: {0}")]
+    UserServiceLoad(String),
+
+    /// The user service returned an error while deleting the user
+    #[error("The user service returned an error while deleting the user: {0}")]
+    UserServiceDelete(String),
+
+    /// The audit service returned an error while recording the deletion
+    #[error("The audit service returned an error while recording the deletion: {0}")]
+    AuditServiceRecord(String),
+
+    /// The session service returned an error while revoking the sessions of the user
+    #[error("The session service returned an error while revoking the sessions of the user: {0}")]
+    SessionServiceRevoke(String),
+
+    /// The deletion could not be committed to the database
+    #[error("The deletion could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::user::LoadError> for UserDeleteErrorResponse {
+    fn from(error: service::user::LoadError) -> Self {
+        Self::UserServiceLoad(error.to_string())
+    }
+}
+
+impl From<service::user::DeleteError> for UserDeleteErrorResponse {
+    fn from(error: service::user::DeleteError) -> Self {
+        match error {
+            service::user::DeleteError::NotFound => Self::UserNotFound,
+            error => Self::UserServiceDelete(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for UserDeleteErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::session::RevokeError> for UserDeleteErrorResponse {
+    fn from(error: service::session::RevokeError) -> Self {
+        Self::SessionServiceRevoke(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for UserDeleteErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
+
+// ### POST /users/<uuid>/restore ###
+
+/// An empty success response, indicating that the user has been restored from the trash.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserRestorePostResponse {}
+
+/// An error response containing one of the possible errors that can occur while restoring a user.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum UserRestorePostErrorResponse {
+    /// The user is not in the trash
+    #[error("The user is not in the trash")]
+    UserNotFound,
+
+    /// The user has a higher role than the requesting user
+    #[error("The user has a higher role than the requesting user")]
+    InsufficientRole,
+
+    /// The user service returned an error while loading the user
+    #[error("The user service returned an error while loading the user: {0}")]
+    UserServiceLoad(String),
+
+    /// The user service returned an error while restoring the user
+    #[error("The user service returned an error while restoring the user: {0}")]
+    UserServiceRestore(String),
+
+    /// The audit service returned an error while recording the restoration
+    #[error("The audit service returned an error while recording the restoration: {0}")]
+    AuditServiceRecord(String),
+
+    /// The restoration could not be committed to the database
+    #[error("The restoration could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::user::LoadError> for UserRestorePostErrorResponse {
+    fn from(error: service::user::LoadError) -> Self {
+        Self::UserServiceLoad(error.to_string())
+    }
+}
+
+impl From<service::user::RestoreError> for UserRestorePostErrorResponse {
+    fn from(error: service::user::RestoreError) -> Self {
+        match error {
+            service::user::RestoreError::NotFound => Self::UserNotFound,
+            error => Self::UserServiceRestore(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for UserRestorePostErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for UserRestorePostErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
+
+// ### DELETE /users/<uuid>/lockout ###
+
+/// An empty success response, indicating that the user has been unlocked.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserLockoutDeleteResponse {}
+
+/// An error response containing one of the possible errors that can occur while unlocking a user.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum UserLockoutDeleteErrorResponse {
+    /// The user does not exist
+    #[error("The user does not exist")]
+    UserNotFound,
+
+    /// The user has a higher role than the requesting user
+    #[error("The user has a higher role than the requesting user")]
+    InsufficientRole,
+
+    /// The user is not locked
+    #[error("The user is not locked")]
+    NotLocked,
+
+    /// The user service returned an error while loading the user
+    #[error("The user service returned an error while loading the user: {0}")]
+    UserServiceLoad(String),
+
+    /// The login throttle service returned an error while unlocking the user
+    #[error("The login throttle service returned an error while unlocking the user: {0}")]
+    LoginThrottleUnlock(String),
+
+    /// The audit service returned an error while recording the unlock
+    #[error("The audit service returned an error while recording the unlock: {0}")]
+    AuditServiceRecord(String),
+
+    /// The unlock could not be committed to the database
+    #[error("The unlock could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::user::LoadError> for UserLockoutDeleteErrorResponse {
+    fn from(error: service::user::LoadError) -> Self {
+        Self::UserServiceLoad(error.to_string())
+    }
+}
+
+impl From<service::login_throttle::UnlockError> for UserLockoutDeleteErrorResponse {
+    fn from(error: service::login_throttle::UnlockError) -> Self {
+        match error {
+            service::login_throttle::UnlockError::NotLocked => Self::NotLocked,
+            error => Self::LoginThrottleUnlock(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for UserLockoutDeleteErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for UserLockoutDeleteErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
+
+// ### DELETE /users/<uuid>/totp ###
+
+/// An empty success response, indicating that the two-factor authentication of the user has been reset.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub struct UserTotpDeleteResponse {}
+
+/// An error response containing one of the possible errors that can occur while resetting the two-factor authentication of a user.
+#[derive(Debug, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+#[schemars(crate = "okapi::schemars")]
+pub enum UserTotpDeleteErrorResponse {
+    /// The user does not exist
+    #[error("The user does not exist")]
+    UserNotFound,
+
+    /// The user has a higher role than the requesting user
+    #[error("The user has a higher role than the requesting user")]
+    InsufficientRole,
+
+    /// The user has no two-factor authentication
+    #[error("The user has no two-factor authentication")]
+    NotEnrolled,
+
+    /// The user service returned an error while loading the user
+    #[error("The user service returned an error while loading the user: {0}")]
+    UserServiceLoad(String),
+
+    /// The two-factor service returned an error while removing the TOTP
+    #[error("The two-factor service returned an error while removing the TOTP: {0}")]
+    TwoFactorServiceDisable(String),
+
+    /// The audit service returned an error while recording the reset
+    #[error("The audit service returned an error while recording the reset: {0}")]
+    AuditServiceRecord(String),
+
+    /// The reset could not be committed to the database
+    #[error("The reset could not be committed to the database: {0}")]
+    UnitOfWork(String),
+}
+
+impl From<service::user::LoadError> for UserTotpDeleteErrorResponse {
+    fn from(error: service::user::LoadError) -> Self {
+        Self::UserServiceLoad(error.to_string())
+    }
+}
+
+impl From<service::two_factor::DisableError> for UserTotpDeleteErrorResponse {
+    fn from(error: service::two_factor::DisableError) -> Self {
+        match error {
+            service::two_factor::DisableError::NotEnrolled => Self::NotEnrolled,
+            error => Self::TwoFactorServiceDisable(error.to_string()),
+        }
+    }
+}
+
+impl From<service::audit::RecordError> for UserTotpDeleteErrorResponse {
+    fn from(error: service::audit::RecordError) -> Self {
+        Self::AuditServiceRecord(error.to_string())
+    }
+}
+
+impl From<service::unit_of_work::UnitOfWorkError> for UserTotpDeleteErrorResponse {
+    fn from(error: service::unit_of_work::UnitOfWorkError) -> Self {
+        Self::UnitOfWork(error.to_string())
+    }
+}
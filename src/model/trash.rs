@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use rocket::async_trait;
+use sqlx::PgExecutor;
+
+use super::DbEntity;
+
+/// An entity that is moved to a trash when it is deleted, so it can be restored until the trash is purged.
+///
+/// Deleted entities are left out by `exists`, `load`, `update` and `delete`. Their rows, and with them the values of
+/// their unique columns, are kept until they are purged, so nobody else can take over for example a deleted username
+/// while it can still be restored.
+#[async_trait]
+pub trait DbEntityTrash: DbEntity {
+    /// The column that holds when an entity was moved to the trash. It is null for entities that are not deleted.
+    const DELETED_AT_COLUMN: &'static str;
+
+    /// Loads an entity from the trash.
+    async fn load_deleted<'e, E>(
+        identifier: &Self::Identifier,
+        executor: E,
+    ) -> Result<Self, sqlx::Error>
+    where
+        E: PgExecutor<'e>;
+
+    /// Returns whether the entity was in the trash and was restored.
+    async fn restore<'e, E>(
+        identifier: &Self::Identifier,
+        executor: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: PgExecutor<'e>;
+
+    /// Deletes the entity permanently, whether it is in the trash or not. Returns whether the entity existed.
+    async fn purge<'e, E>(identifier: &Self::Identifier, executor: E) -> Result<bool, sqlx::Error>
+    where
+        E: PgExecutor<'e>;
+
+    /// Permanently deletes the entities that were moved to the trash before the given time and returns them.
+    async fn purge_deleted_before<'e, E>(
+        deleted_before: DateTime<Utc>,
+        executor: E,
+    ) -> Result<Vec<Self>, sqlx::Error>
+    where
+        E: PgExecutor<'e>;
+}
@@ -1,27 +1,98 @@
+pub mod audit;
+pub mod auth_backend;
+pub mod login_throttle;
+pub mod oidc;
+pub mod password_hasher;
+pub mod session;
 pub mod setup;
+pub mod trash;
+pub mod two_factor;
+pub mod unit_of_work;
 pub mod user;
 
+pub use audit::AuditService;
+pub use login_throttle::LoginThrottleService;
+pub use oidc::OidcService;
+pub use session::SessionService;
 pub use setup::SetupService;
+pub use trash::TrashService;
+pub use two_factor::TwoFactorService;
+pub use unit_of_work::UnitOfWork;
 pub use user::UserService;
 
-use crate::config::{rasopus::RasopusConfig, user_service::UserServiceConfig};
+use orion::errors::UnknownCryptoError;
+use thiserror::Error;
+
+use crate::config::{
+    login_throttle_service::LoginThrottleServiceConfig, oidc_service::OidcServiceConfig,
+    rasopus::RasopusConfig, session_service::SessionServiceConfig,
+    setup_service::SetupServiceConfig, trash_service::TrashServiceConfig,
+    two_factor_service::TwoFactorServiceConfig, user_service::UserServiceConfig,
+};
+
+#[derive(Debug, Error)]
+pub enum ServiceCollectionError {
+    #[error("Failed to initialize the setup service: {0}")]
+    Setup(UnknownCryptoError),
+
+    #[error("Failed to initialize the two-factor service: {0}")]
+    TwoFactor(UnknownCryptoError),
+
+    #[error("Failed to initialize the OpenID Connect service: {0}")]
+    Oidc(#[from] oidc::ConfigError),
+
+    #[error("Failed to initialize the user service: {0}")]
+    User(#[from] user::ConfigError),
+}
 
 #[derive(Debug)]
 pub struct ServiceCollection {
+    pub audit: AuditService,
+    pub login_throttle: LoginThrottleService,
+    pub oidc: OidcService,
+    pub session: SessionService,
     pub setup: SetupService,
+    pub trash: TrashService,
+    pub two_factor: TwoFactorService,
     pub user: UserService,
 }
 
 impl ServiceCollection {
-    pub fn new(config: &RasopusConfig) -> Self {
-        let setup_service = SetupService::new();
+    pub fn new(config: &RasopusConfig) -> Result<Self, ServiceCollectionError> {
+        let setup_service_config = SetupServiceConfig::from(config);
+        let setup_service =
+            SetupService::new(setup_service_config).map_err(ServiceCollectionError::Setup)?;
 
         let user_service_config = UserServiceConfig::from(config);
-        let user_service = UserService::new(user_service_config);
+        let user_service = UserService::new(user_service_config)?;
+
+        let login_throttle_service_config = LoginThrottleServiceConfig::from(config);
+        let login_throttle_service = LoginThrottleService::new(login_throttle_service_config);
+
+        let two_factor_service_config = TwoFactorServiceConfig::from(config);
+        let two_factor_service = TwoFactorService::new(two_factor_service_config)
+            .map_err(ServiceCollectionError::TwoFactor)?;
+
+        let oidc_service_config = OidcServiceConfig::from(config);
+        let oidc_service = OidcService::new(oidc_service_config)?;
+
+        let session_service_config = SessionServiceConfig::from(config);
+        let session_service = SessionService::new(session_service_config);
+
+        let audit_service = AuditService::new();
+
+        let trash_service_config = TrashServiceConfig::from(config);
+        let trash_service = TrashService::new(trash_service_config);
 
-        Self {
+        Ok(Self {
+            audit: audit_service,
+            login_throttle: login_throttle_service,
+            oidc: oidc_service,
+            session: session_service,
             setup: setup_service,
+            trash: trash_service,
+            two_factor: two_factor_service,
             user: user_service,
-        }
+        })
     }
 }
@@ -0,0 +1,120 @@
+use std::net::IpAddr;
+
+use chrono::Utc;
+use rocket::serde::json::{Value, serde_json};
+use sqlx::{PgExecutor, Pool, Postgres};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    adapter::audit_event::UnadaptAuditEventError,
+    model::{
+        DbEntity, PersistError,
+        entity::{
+            audit_event::{
+                AuditAction, AuditEvent, AuditFilter, AuditSortColumn, AuditTargetType,
+                DbAuditEvent,
+            },
+            user::User,
+        },
+        list::{self, DbEntityList, ListQuery, Listed},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum RecordError {
+    #[error("Failed to persist audit event: {0}")]
+    Persist(#[from] PersistError),
+}
+
+#[derive(Debug, Error)]
+pub enum ListError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Failed to list audit events: {0}")]
+    List(#[from] list::ListError),
+
+    #[error("Failed to unadapt audit event from database audit event: {0}")]
+    Unadapt(#[from] UnadaptAuditEventError),
+}
+
+/// What to record about an action. The UUID and time of the audit event are filled in when it is recorded.
+#[derive(Debug, Default)]
+pub struct AuditRecord {
+    /// The UUID of the user who did the action, if known.
+    pub actor_uuid: Option<Uuid>,
+
+    /// The kind and UUID of the entity the action was done to.
+    pub target: Option<(AuditTargetType, Uuid)>,
+
+    /// A snapshot of the entity before the action.
+    pub before: Option<Value>,
+
+    /// A snapshot of the entity after the action.
+    pub after: Option<Value>,
+
+    /// The IP address the action was done from, if known.
+    pub ip_address: Option<IpAddr>,
+}
+
+/// Records administrative and security relevant actions, so it can be traced who did what to which entity.
+#[derive(Debug, Default, Clone)]
+pub struct AuditService;
+
+impl AuditService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Returns the snapshot of a user that is stored in audit events.
+    ///
+    /// The password hash is left out, as the audit log is readable by all admins.
+    pub fn user_snapshot(user: &User) -> Value {
+        serde_json::json!({
+            "uuid": user.uuid,
+            "username": user.username,
+            "role": user.role,
+            "created_at": user.created_at.to_rfc3339(),
+        })
+    }
+
+    pub async fn record<'e, E>(
+        &self,
+        action: AuditAction,
+        record: AuditRecord,
+        executor: E,
+    ) -> Result<AuditEvent, RecordError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let audit_event = AuditEvent {
+            uuid: Uuid::new_v4(),
+            action,
+            actor_uuid: record.actor_uuid,
+            target_type: record.target.map(|(target_type, _)| target_type),
+            target_uuid: record.target.map(|(_, target_uuid)| target_uuid),
+            before: record.before,
+            after: record.after,
+            ip_address: record.ip_address,
+            occurred_at: Utc::now(),
+        };
+
+        DbAuditEvent::from(&audit_event).create(executor).await?;
+
+        Ok(audit_event)
+    }
+
+    /// Lists a page of the audit events matching the filter. Also returns the number of all matching events.
+    pub async fn list(
+        &self,
+        query: &ListQuery<AuditFilter, AuditSortColumn>,
+        postgres_pool: &Pool<Postgres>,
+    ) -> Result<(Listed<AuditEvent>, i64), ListError> {
+        let total = DbAuditEvent::count(&query.filter, postgres_pool).await?;
+        let db_audit_events = DbAuditEvent::list(query, postgres_pool).await?;
+        let audit_events = db_audit_events.try_map(AuditEvent::try_from)?;
+
+        Ok((audit_events, total))
+    }
+}
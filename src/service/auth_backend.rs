@@ -0,0 +1,66 @@
+pub mod ldap;
+pub mod password;
+
+pub use ldap::LdapAuthBackend;
+pub use password::PasswordAuthBackend;
+
+use std::fmt::Debug;
+
+use orion::errors::UnknownCryptoError;
+use rocket::async_trait;
+use thiserror::Error;
+
+use crate::model::entity::user::{Role, User};
+
+#[derive(Debug, Error)]
+pub enum AuthBackendError {
+    #[error("Cryptography error: {0}")]
+    Cryptography(#[from] UnknownCryptoError),
+
+    #[error("LDAP error: {0}")]
+    Ldap(#[from] ldap3::LdapError),
+}
+
+/// An identity that was authenticated by an external system, like an LDAP directory.
+#[derive(Debug)]
+pub struct ExternalIdentity {
+    /// Identifies the external system.
+    pub issuer: String,
+
+    /// Identifies the user in the external system. It should never change, unlike the username.
+    pub subject: String,
+
+    /// The username for the user that is created on the first login.
+    pub username: String,
+
+    /// The role the user should have, if the external system manages roles.
+    pub role: Option<Role>,
+}
+
+/// The result of asking an authentication backend to authenticate a login.
+#[derive(Debug)]
+pub enum AuthResult {
+    /// The backend did not authenticate the login. The next backend is asked.
+    Rejected,
+
+    /// The backend authenticated the given local user.
+    Authenticated,
+
+    /// The backend authenticated an external identity, which is mapped to a local user.
+    External(ExternalIdentity),
+}
+
+/// A way to verify the username and password of a login, which `UserService` consults in the configured order.
+#[async_trait]
+pub trait AuthBackend: Debug + Send + Sync {
+    /// The name to select the backend with in `RASOPUS_AUTH_BACKENDS`.
+    fn name(&self) -> &'static str;
+
+    /// Verifies the given username and password. The user is the local user with that username, if there is one.
+    async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        user: Option<&User>,
+    ) -> Result<AuthResult, AuthBackendError>;
+}
@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry, dn_escape, ldap_escape};
+use rocket::async_trait;
+use thiserror::Error;
+
+use crate::{
+    config::ldap_auth_backend::LdapAuthBackendConfig,
+    model::entity::user::{Role, User},
+};
+
+use super::{AuthBackend, AuthBackendError, AuthResult, ExternalIdentity};
+
+const INVALID_CREDENTIALS_RESULT_CODE: u32 = 49;
+const GROUP_NAME_ATTRIBUTE: &str = "cn";
+
+#[derive(Debug, Error)]
+pub enum LdapConfigError {
+    #[error("{0} has to be set when the LDAP authentication backend is enabled")]
+    MissingSetting(&'static str),
+
+    #[error("RASOPUS_LDAP_USER_DN_TEMPLATE has to contain {{username}}")]
+    InvalidUserDnTemplate,
+}
+
+/// Authenticates users by binding to an LDAP directory as them.
+///
+/// The role of the user is taken from their group memberships on every login. Users who are in none of the
+/// configured groups are rejected.
+#[derive(Debug)]
+pub struct LdapAuthBackend {
+    url: String,
+    config: LdapAuthBackendConfig,
+    user_dn_template: String,
+}
+
+impl LdapAuthBackend {
+    pub fn new(config: LdapAuthBackendConfig) -> Result<Self, LdapConfigError> {
+        let url = config
+            .url
+            .clone()
+            .ok_or(LdapConfigError::MissingSetting("RASOPUS_LDAP_URL"))?;
+        let user_dn_template =
+            config
+                .user_dn_template
+                .clone()
+                .ok_or(LdapConfigError::MissingSetting(
+                    "RASOPUS_LDAP_USER_DN_TEMPLATE",
+                ))?;
+
+        if !user_dn_template.contains("{username}") {
+            return Err(LdapConfigError::InvalidUserDnTemplate);
+        }
+
+        Ok(Self {
+            url,
+            config,
+            user_dn_template,
+        })
+    }
+
+    async fn connect(&self) -> Result<Ldap, ldap3::LdapError> {
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(self.timeout())
+            .set_starttls(self.config.starttls);
+
+        let (connection, ldap) = LdapConnAsync::with_settings(settings, &self.url).await?;
+        ldap3::drive!(connection);
+
+        Ok(ldap)
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout_secs)
+    }
+
+    /// Reads the username of the bound user from their entry, so it has the same case as in the directory.
+    async fn username(
+        &self,
+        ldap: &mut Ldap,
+        user_dn: &str,
+    ) -> Result<Option<String>, ldap3::LdapError> {
+        let (entries, _) = ldap
+            .with_timeout(self.timeout())
+            .search(
+                user_dn,
+                Scope::Base,
+                "(objectClass=*)",
+                vec![self.config.username_attribute.as_str()],
+            )
+            .await?
+            .success()?;
+
+        let username = entries.into_iter().next().and_then(|entry| {
+            SearchEntry::construct(entry)
+                .attrs
+                .remove(&self.config.username_attribute)
+                .and_then(|values| values.into_iter().next())
+        });
+
+        Ok(username)
+    }
+
+    /// Returns the names of the groups the bound user is a member of.
+    async fn groups(
+        &self,
+        ldap: &mut Ldap,
+        group_base_dn: &str,
+        user_dn: &str,
+    ) -> Result<Vec<String>, ldap3::LdapError> {
+        let filter = self
+            .config
+            .group_filter
+            .replace("{dn}", &ldap_escape(user_dn));
+
+        let (entries, _) = ldap
+            .with_timeout(self.timeout())
+            .search(
+                group_base_dn,
+                Scope::Subtree,
+                &filter,
+                vec![GROUP_NAME_ATTRIBUTE],
+            )
+            .await?
+            .success()?;
+
+        let groups = entries
+            .into_iter()
+            .filter_map(|entry| {
+                SearchEntry::construct(entry)
+                    .attrs
+                    .remove(GROUP_NAME_ATTRIBUTE)
+            })
+            .flatten()
+            .collect();
+
+        Ok(groups)
+    }
+
+    fn role(&self, groups: &[String]) -> Option<Role> {
+        let is_member = |configured_groups: &[String]| {
+            configured_groups.iter().any(|configured_group| {
+                groups
+                    .iter()
+                    .any(|group| group.eq_ignore_ascii_case(configured_group))
+            })
+        };
+
+        if is_member(&self.config.admin_groups) {
+            return Some(Role::Admin);
+        }
+
+        // Without user groups, everyone in the directory may log in
+        if self.config.user_groups.is_empty() || is_member(&self.config.user_groups) {
+            return Some(Role::User);
+        }
+
+        None
+    }
+}
+
+#[async_trait]
+impl AuthBackend for LdapAuthBackend {
+    fn name(&self) -> &'static str {
+        "ldap"
+    }
+
+    async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        _user: Option<&User>,
+    ) -> Result<AuthResult, AuthBackendError> {
+        // LDAP servers treat a bind without a password as an anonymous bind, which succeeds
+        if username.is_empty() || password.is_empty() {
+            return Ok(AuthResult::Rejected);
+        }
+
+        let user_dn = self
+            .user_dn_template
+            .replace("{username}", &dn_escape(username));
+
+        let mut ldap = self.connect().await?;
+        let bind_result = ldap
+            .with_timeout(self.timeout())
+            .simple_bind(&user_dn, password)
+            .await?;
+
+        if bind_result.rc == INVALID_CREDENTIALS_RESULT_CODE {
+            let _ = ldap.unbind().await;
+            return Ok(AuthResult::Rejected);
+        }
+        bind_result.success()?;
+
+        let directory_username = self.username(&mut ldap, &user_dn).await?;
+        let groups = match &self.config.group_base_dn {
+            Some(group_base_dn) => self.groups(&mut ldap, group_base_dn, &user_dn).await?,
+            None => Vec::new(),
+        };
+        let _ = ldap.unbind().await;
+
+        let Some(role) = self.role(&groups) else {
+            println!(
+                "Rejected LDAP login of {} because they are in none of the configured groups",
+                user_dn
+            );
+            return Ok(AuthResult::Rejected);
+        };
+
+        // DNs are case-insensitive, but the subject is compared exactly
+        Ok(AuthResult::External(ExternalIdentity {
+            issuer: self.url.clone(),
+            subject: user_dn.to_lowercase(),
+            username: directory_username.unwrap_or(username.to_string()),
+            role: Some(role),
+        }))
+    }
+}
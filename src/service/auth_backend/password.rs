@@ -0,0 +1,47 @@
+use rocket::async_trait;
+
+use crate::{model::entity::user::User, service::password_hasher::PasswordHasher};
+
+use super::{AuthBackend, AuthBackendError, AuthResult};
+
+/// Verifies passwords against the Argon2 password hashes of local users.
+#[derive(Debug)]
+pub struct PasswordAuthBackend {
+    password_hasher: PasswordHasher,
+}
+
+impl PasswordAuthBackend {
+    pub fn new(password_hasher: PasswordHasher) -> Self {
+        Self { password_hasher }
+    }
+}
+
+#[async_trait]
+impl AuthBackend for PasswordAuthBackend {
+    fn name(&self) -> &'static str {
+        "password"
+    }
+
+    /// When there is no user, a password hash is computed anyway and the login is rejected.
+    /// This way, the response time does not reveal whether a user exists.
+    async fn authenticate(
+        &self,
+        _username: &str,
+        password: &str,
+        user: Option<&User>,
+    ) -> Result<AuthResult, AuthBackendError> {
+        let Some(user) = user else {
+            self.password_hasher.hash_in_vain(password).await?;
+            return Ok(AuthResult::Rejected);
+        };
+
+        match self
+            .password_hasher
+            .verify(&user.password_hash, password)
+            .await?
+        {
+            true => Ok(AuthResult::Authenticated),
+            false => Ok(AuthResult::Rejected),
+        }
+    }
+}
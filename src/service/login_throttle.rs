@@ -0,0 +1,456 @@
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor, Pool, Postgres};
+use thiserror::Error;
+use tokio::sync::{AcquireError, Semaphore, SemaphorePermit};
+use uuid::Uuid;
+
+use crate::{
+    config::login_throttle_service::LoginThrottleServiceConfig,
+    model::{
+        DbEntity, PersistError,
+        entity::{
+            account_lockout::{AccountLockout, DbAccountLockout},
+            login_attempt::{DbLoginAttempt, LoginAttempt},
+            user::User,
+        },
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum LockoutError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum CheckError {
+    #[error("The account is locked until {0}")]
+    Locked(DateTime<Utc>),
+
+    #[error("Too many failed login attempts, retry in {0} seconds")]
+    TooManyAttempts(u64),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Failed to persist login attempt: {0}")]
+    Persist(#[from] PersistError),
+
+    #[error("Failed to load the account lockout: {0}")]
+    Lockout(#[from] LockoutError),
+}
+
+#[derive(Debug, Error)]
+pub enum RecordError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Failed to persist account lockout: {0}")]
+    Persist(#[from] PersistError),
+
+    #[error("Failed to load the account lockout: {0}")]
+    Lockout(#[from] LockoutError),
+}
+
+#[derive(Debug, Error)]
+pub enum PurgeError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum UnlockError {
+    #[error("The user is not locked")]
+    NotLocked,
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Failed to persist account lockout: {0}")]
+    Persist(#[from] PersistError),
+
+    #[error("Failed to load the account lockout: {0}")]
+    Lockout(#[from] LockoutError),
+}
+
+const PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+/// A login attempt that passed the throttling check and counts as failed until it is finished.
+#[derive(Debug)]
+#[must_use = "a reserved attempt counts as failed until it is finished"]
+pub struct ReservedAttempt {
+    attempt: LoginAttempt,
+    user_uuid: Option<Uuid>,
+}
+
+/// Protects logins against brute-force attacks.
+///
+/// Failed attempts are tracked per username and per IP address. Every failed attempt for a username doubles the time
+/// that has to pass before the next attempt is allowed, and too many failed attempts lock the user temporarily.
+/// Additionally, the number of concurrent password verifications is limited, as each one needs a lot of memory.
+#[derive(Debug, Clone)]
+pub struct LoginThrottleService {
+    config: LoginThrottleServiceConfig,
+    verification_permits: Arc<Semaphore>,
+}
+
+impl LoginThrottleService {
+    pub fn new(config: LoginThrottleServiceConfig) -> Self {
+        let verification_permits =
+            Arc::new(Semaphore::new(config.max_concurrent_verifications.max(1)));
+
+        Self {
+            config,
+            verification_permits,
+        }
+    }
+
+    /// Checks whether a login attempt for the given username from the given IP address is allowed right now.
+    ///
+    /// This has to be called before verifying any credentials. If the attempt is allowed, it is reserved by recording
+    /// it as failed right away, so concurrent attempts are throttled as if it had already failed. Once the credentials
+    /// are verified, the attempt has to be finished with `succeed`, `fail` or `discard`.
+    pub async fn check(
+        &self,
+        username: &str,
+        user: Option<&User>,
+        ip_address: Option<IpAddr>,
+        postgres_pool: &Pool<Postgres>,
+    ) -> Result<ReservedAttempt, CheckError> {
+        let now = Utc::now();
+
+        // Concurrent checks for the same username or IP address wait for each other, so each one sees the attempts
+        // the others reserved
+        let mut transaction = postgres_pool.begin().await?;
+        lock(&username_lock_key(username), &mut *transaction).await?;
+        if let Some(ip_address) = ip_address {
+            lock(&ip_address_lock_key(ip_address), &mut *transaction).await?;
+        }
+
+        if let Some(user) = user {
+            if let Some(lockout) = self.active_lockout(&user.uuid, &mut *transaction).await? {
+                return Err(CheckError::Locked(lockout.locked_until));
+            }
+        }
+
+        let (failures, last_failure_at) = self
+            .recent_failures(username, user, now, &mut *transaction)
+            .await?;
+        if let Some(last_failure_at) = last_failure_at {
+            let exponent = failures.saturating_sub(1).min(32) as u32;
+            let delay_ms = self
+                .config
+                .delay_base_ms
+                .saturating_mul(2u64.saturating_pow(exponent))
+                .min(self.config.delay_max_secs * 1000);
+
+            let allowed_at = last_failure_at + chrono::Duration::milliseconds(delay_ms as i64);
+            let remaining_ms = (allowed_at - now).num_milliseconds();
+            if remaining_ms > 0 {
+                return Err(CheckError::TooManyAttempts(
+                    (remaining_ms as u64).div_ceil(1000),
+                ));
+            }
+        }
+
+        if let Some(ip_address) = ip_address {
+            let ip_failures = self
+                .recent_ip_failures(ip_address, now, &mut *transaction)
+                .await?;
+            if ip_failures >= self.config.ip_threshold as i64 {
+                return Err(CheckError::TooManyAttempts(self.config.window_secs));
+            }
+        }
+
+        let attempt = LoginAttempt {
+            uuid: Uuid::new_v4(),
+            username: username.to_string(),
+            ip_address,
+            succeeded: false,
+            attempted_at: now,
+        };
+
+        DbLoginAttempt::from(&attempt)
+            .create(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(ReservedAttempt {
+            attempt,
+            user_uuid: user.map(|user| user.uuid),
+        })
+    }
+
+    /// Waits until a password verification may be started.
+    ///
+    /// The returned permit has to be held for as long as the verification runs.
+    pub async fn acquire_verification_permit(&self) -> Result<SemaphorePermit<'_>, AcquireError> {
+        self.verification_permits.acquire().await
+    }
+
+    /// Records the reserved attempt as successful, which resets the failed attempts of the username.
+    pub async fn succeed<'e, E>(
+        &self,
+        attempt: ReservedAttempt,
+        executor: E,
+    ) -> Result<(), RecordError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "UPDATE {} SET succeeded = TRUE WHERE uuid = $1",
+            DbLoginAttempt::main_table_name()
+        );
+
+        sqlx::query(&query)
+            .bind(attempt.attempt.uuid)
+            .execute(executor)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Keeps the reserved attempt recorded as failed and locks the user if they failed too often.
+    ///
+    /// The connection should belong to a unit of work, as the username stays locked against concurrent checks until
+    /// it is committed. Returns the lockout, if one was created.
+    pub async fn fail(
+        &self,
+        attempt: ReservedAttempt,
+        connection: &mut PgConnection,
+    ) -> Result<Option<AccountLockout>, RecordError> {
+        let Some(user_uuid) = attempt.user_uuid else {
+            return Ok(None);
+        };
+
+        let username = &attempt.attempt.username;
+        lock(&username_lock_key(username), &mut *connection).await?;
+
+        let now = Utc::now();
+        let (failures, _) = self
+            .recent_failures_of(username, Some(user_uuid), now, &mut *connection)
+            .await?;
+        if failures < self.config.lockout_threshold as i64 {
+            return Ok(None);
+        }
+
+        // Another failed attempt that was checked at the same time may have locked the user already
+        if self
+            .active_lockout(&user_uuid, &mut *connection)
+            .await?
+            .is_some()
+        {
+            return Ok(None);
+        }
+
+        let lockout = AccountLockout {
+            uuid: Uuid::new_v4(),
+            user_uuid,
+            locked_at: now,
+            locked_until: now + chrono::Duration::seconds(self.config.lockout_secs as i64),
+            unlocked_at: None,
+            unlocked_by: None,
+        };
+
+        DbAccountLockout::from(&lockout)
+            .create(&mut *connection)
+            .await?;
+
+        println!(
+            "Locked user {} until {} after {} failed login attempts",
+            user_uuid, lockout.locked_until, failures
+        );
+
+        Ok(Some(lockout))
+    }
+
+    /// Forgets the reserved attempt, as if it was never made.
+    ///
+    /// This is for attempts that neither failed nor completed a login, like a correct password that still needs the
+    /// second factor. Recording those as successful would reset the failed attempts of the second factor.
+    pub async fn discard<'e, E>(
+        &self,
+        attempt: ReservedAttempt,
+        executor: E,
+    ) -> Result<(), RecordError>
+    where
+        E: PgExecutor<'e>,
+    {
+        DbLoginAttempt::delete(&attempt.attempt.uuid, executor).await?;
+
+        Ok(())
+    }
+
+    /// Returns the lockout that is currently in effect for the given user, if any.
+    pub async fn active_lockout<'e, E>(
+        &self,
+        user_uuid: &Uuid,
+        executor: E,
+    ) -> Result<Option<AccountLockout>, LockoutError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "SELECT * FROM {} WHERE user_uuid = $1 AND unlocked_at IS NULL AND locked_until > $2 ORDER BY locked_until DESC LIMIT 1",
+            DbAccountLockout::main_table_name()
+        );
+
+        let db_lockout: Option<DbAccountLockout> = sqlx::query_as(&query)
+            .bind(user_uuid)
+            .bind(Utc::now())
+            .fetch_optional(executor)
+            .await?;
+
+        let lockout = db_lockout.map(AccountLockout::from);
+        Ok(lockout)
+    }
+
+    /// Lifts the lockout that is currently in effect for the given user.
+    pub async fn unlock(
+        &self,
+        user_uuid: &Uuid,
+        unlocked_by: &Uuid,
+        connection: &mut PgConnection,
+    ) -> Result<AccountLockout, UnlockError> {
+        let Some(mut lockout) = self.active_lockout(user_uuid, &mut *connection).await? else {
+            return Err(UnlockError::NotLocked);
+        };
+
+        lockout.unlocked_at = Some(Utc::now());
+        lockout.unlocked_by = Some(*unlocked_by);
+        DbAccountLockout::from(&lockout).update(connection).await?;
+
+        println!("User {} was unlocked by user {}", user_uuid, unlocked_by);
+
+        Ok(lockout)
+    }
+
+    /// Deletes the login attempts that don't count towards the throttling anymore. Returns how many were deleted.
+    pub async fn purge_expired_attempts<'e, E>(&self, executor: E) -> Result<u64, PurgeError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "DELETE FROM {} WHERE attempted_at < $1",
+            DbLoginAttempt::main_table_name()
+        );
+
+        let window_start = Utc::now() - chrono::Duration::seconds(self.config.window_secs as i64);
+        let result = sqlx::query(&query)
+            .bind(window_start)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes the expired login attempts once an hour, for as long as Rasopus runs, so they don't pile up.
+    pub async fn run_purge_job(self, postgres_pool: Pool<Postgres>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(PURGE_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            match self.purge_expired_attempts(&postgres_pool).await {
+                Ok(0) => {}
+                Ok(login_attempts) => println!("Purged {} old login attempts", login_attempts),
+                Err(error) => eprintln!("Failed to purge the old login attempts: {}", error),
+            }
+        }
+    }
+
+    /// Counts the failed attempts for the given username since the window started, the last successful login
+    /// or the last lockout, whichever happened last. Also returns the timestamp of the last failed attempt.
+    async fn recent_failures<'e, E>(
+        &self,
+        username: &str,
+        user: Option<&User>,
+        now: DateTime<Utc>,
+        executor: E,
+    ) -> Result<(i64, Option<DateTime<Utc>>), sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        self.recent_failures_of(username, user.map(|user| user.uuid), now, executor)
+            .await
+    }
+
+    async fn recent_failures_of<'e, E>(
+        &self,
+        username: &str,
+        user_uuid: Option<Uuid>,
+        now: DateTime<Utc>,
+        executor: E,
+    ) -> Result<(i64, Option<DateTime<Utc>>), sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        // GREATEST ignores nulls, so a missing successful login or lockout doesn't move the start
+        let query = format!(
+            "SELECT COUNT(*), MAX(attempted_at) FROM {attempts} WHERE username = $1 AND NOT succeeded AND attempted_at > GREATEST(
+                $2,
+                (SELECT MAX(attempted_at) FROM {attempts} WHERE username = $1 AND succeeded),
+                (SELECT MAX(locked_at) FROM {lockouts} WHERE user_uuid = $3)
+            )",
+            attempts = DbLoginAttempt::main_table_name(),
+            lockouts = DbAccountLockout::main_table_name(),
+        );
+
+        let window_start = now - chrono::Duration::seconds(self.config.window_secs as i64);
+        let result = sqlx::query_as(&query)
+            .bind(username)
+            .bind(window_start)
+            .bind(user_uuid)
+            .fetch_one(executor)
+            .await?;
+
+        Ok(result)
+    }
+
+    async fn recent_ip_failures<'e, E>(
+        &self,
+        ip_address: IpAddr,
+        now: DateTime<Utc>,
+        executor: E,
+    ) -> Result<i64, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "SELECT COUNT(*) FROM {} WHERE ip_address = $1 AND NOT succeeded AND attempted_at > $2",
+            DbLoginAttempt::main_table_name()
+        );
+
+        let window_start = now - chrono::Duration::seconds(self.config.window_secs as i64);
+        let (failures,) = sqlx::query_as(&query)
+            .bind(ip_address.to_string())
+            .bind(window_start)
+            .fetch_one(executor)
+            .await?;
+
+        Ok(failures)
+    }
+}
+
+fn username_lock_key(username: &str) -> String {
+    format!("login_attempts:username:{}", username)
+}
+
+fn ip_address_lock_key(ip_address: IpAddr) -> String {
+    format!("login_attempts:ip_address:{}", ip_address)
+}
+
+/// Takes a transaction-level advisory lock on the given key, waiting until no other transaction holds it.
+async fn lock<'e, E>(key: &str, executor: E) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1, 0))")
+        .bind(key)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
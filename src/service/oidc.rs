@@ -0,0 +1,750 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use openidconnect::{
+    AdditionalClaims, AuthenticationFlow, AuthorizationCode, Client, ClientId, ClientSecret,
+    CsrfToken, EmptyExtraTokenFields, EndpointMaybeSet, EndpointNotSet, EndpointSet, IdTokenFields,
+    IssuerUrl, Nonce, OAuth2TokenResponse, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
+    StandardErrorResponse, StandardTokenResponse, UserInfoClaims,
+    core::{
+        CoreAuthDisplay, CoreAuthPrompt, CoreErrorResponseType, CoreGenderClaim, CoreJsonWebKey,
+        CoreJweContentEncryptionAlgorithm, CoreJwsSigningAlgorithm, CoreProviderMetadata,
+        CoreResponseType, CoreRevocableToken, CoreRevocationErrorResponse,
+        CoreTokenIntrospectionResponse, CoreTokenType,
+    },
+    reqwest, url,
+};
+use orion::util;
+use rocket::serde::{
+    Deserialize, Serialize,
+    json::{Value, serde_json},
+};
+use sqlx::PgConnection;
+use thiserror::Error;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    config::oidc_service::OidcServiceConfig,
+    model::entity::{
+        audit_event::{AuditAction, AuditTargetType},
+        user::{ParseRoleError, Role, User},
+    },
+    validation::username::UsernameError,
+};
+
+use super::{
+    AuditService, UserService,
+    audit::{AuditRecord, RecordError},
+    user::{CreateError, GenerateError, LinkIdentityError, LoadError, UpdateError},
+};
+
+const HTTP_TIMEOUT_SECS: u64 = 10;
+const METADATA_REFRESH_SECS: u64 = 3600;
+
+/// The claims of an ID token or user info response that are not part of the OpenID Connect standard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct ExtraClaims {
+    #[serde(flatten)]
+    claims: HashMap<String, Value>,
+}
+
+impl AdditionalClaims for ExtraClaims {}
+
+type ExtraIdTokenFields = IdTokenFields<
+    ExtraClaims,
+    EmptyExtraTokenFields,
+    CoreGenderClaim,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJwsSigningAlgorithm,
+>;
+
+type OidcClient = Client<
+    ExtraClaims,
+    CoreAuthDisplay,
+    CoreGenderClaim,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJsonWebKey,
+    CoreAuthPrompt,
+    StandardErrorResponse<CoreErrorResponseType>,
+    StandardTokenResponse<ExtraIdTokenFields, CoreTokenType>,
+    CoreTokenIntrospectionResponse,
+    CoreRevocableToken,
+    CoreRevocationErrorResponse,
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("{0} has to be set when OpenID Connect is enabled")]
+    MissingSetting(&'static str),
+
+    #[error("The issuer URL is invalid: {0}")]
+    InvalidIssuerUrl(url::ParseError),
+
+    #[error("The redirect URL is invalid: {0}")]
+    InvalidRedirectUrl(url::ParseError),
+
+    #[error("The default role is invalid: {0}")]
+    InvalidDefaultRole(ParseRoleError),
+
+    #[error("The role mapping entry {0:?} is invalid, it has to look like \"claim value=role\"")]
+    InvalidRoleMapping(String),
+
+    #[error("The system role can't be assigned through OpenID Connect")]
+    SystemRole,
+
+    #[error("Failed to build the HTTP client: {0}")]
+    HttpClient(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum BeginError {
+    #[error("OpenID Connect is not configured")]
+    NotConfigured,
+
+    #[error("Failed to discover the identity provider: {0}")]
+    Discovery(String),
+}
+
+#[derive(Debug, Error)]
+pub enum CompleteError {
+    #[error("OpenID Connect is not configured")]
+    NotConfigured,
+
+    #[error("The login took too long")]
+    Expired,
+
+    #[error("The state does not match the login")]
+    InvalidState,
+
+    #[error("Failed to discover the identity provider: {0}")]
+    Discovery(String),
+
+    #[error("Failed to exchange the authorization code: {0}")]
+    TokenExchange(String),
+
+    #[error("The identity provider did not return an ID token")]
+    MissingIdToken,
+
+    #[error("The ID token is invalid: {0}")]
+    InvalidIdToken(String),
+
+    #[error("Failed to request the user info: {0}")]
+    UserInfo(String),
+
+    #[error("The identity provider did not return the {0} claim")]
+    MissingUsernameClaim(String),
+
+    #[error("The username from the identity provider is invalid: {0}")]
+    InvalidUsername(UsernameError),
+
+    #[error("A user with the username from the identity provider already exists")]
+    UsernameTaken,
+
+    #[error("The identity is not linked to any user and new users are not created automatically")]
+    NotLinked,
+
+    #[error("The claims of the identity don't map to any role")]
+    NoMatchingRole,
+
+    #[error("The identity is already linked to another user")]
+    IdentityLinkedToOtherUser,
+
+    #[error("The user does not exist anymore")]
+    UserNotFound,
+
+    #[error("The user service returned an error while generating the user: {0}")]
+    UserServiceGenerate(GenerateError),
+
+    #[error("The user service returned an error while creating the user: {0}")]
+    UserServiceCreate(CreateError),
+
+    #[error("The user service returned an error while loading the user: {0}")]
+    UserServiceLoad(#[from] LoadError),
+
+    #[error("The user service returned an error while updating the user: {0}")]
+    UserServiceUpdate(#[from] UpdateError),
+
+    #[error("The user service returned an error while linking the identity: {0}")]
+    UserServiceLinkIdentity(#[from] LinkIdentityError),
+
+    #[error("The audit service returned an error while recording the changes to the user: {0}")]
+    AuditServiceRecord(#[from] RecordError),
+}
+
+impl From<GenerateError> for CompleteError {
+    fn from(error: GenerateError) -> Self {
+        match error {
+            GenerateError::InvalidUsername(error) => Self::InvalidUsername(error),
+            error => Self::UserServiceGenerate(error),
+        }
+    }
+}
+
+impl From<CreateError> for CompleteError {
+    fn from(error: CreateError) -> Self {
+        match error {
+            CreateError::UsernameTaken => Self::UsernameTaken,
+            error => Self::UserServiceCreate(error),
+        }
+    }
+}
+
+/// The state of a login that was started with `begin`, which has to be kept by the client until the identity provider
+/// redirects back.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct OidcFlow {
+    pub state: String,
+    pub nonce: String,
+    pub pkce_verifier: String,
+    pub link_user_uuid: Option<Uuid>,
+    pub started_at: i64,
+}
+
+/// An identity verified by the identity provider, which still has to be resolved to a user with `complete`.
+#[derive(Debug)]
+pub struct OidcIdentity {
+    issuer: String,
+    subject: String,
+    claims: HashMap<String, Value>,
+    mapped_role: Option<Role>,
+    link_user_uuid: Option<Uuid>,
+}
+
+/// The result of a completed login.
+#[derive(Debug)]
+pub struct OidcLogin {
+    pub user: User,
+
+    /// Whether the user was created during this login.
+    pub created: bool,
+
+    /// Whether the identity was linked to an existing user during this login.
+    pub linked: bool,
+}
+
+#[derive(Debug)]
+struct OidcProvider {
+    issuer_url: IssuerUrl,
+    client_id: ClientId,
+    client_secret: Option<ClientSecret>,
+    redirect_url: RedirectUrl,
+    scopes: Vec<String>,
+    username_claim: String,
+    default_role: Option<Role>,
+    role_claim: Option<String>,
+    role_mapping: Vec<(String, Role)>,
+    flow_timeout_secs: u64,
+}
+
+impl TryFrom<OidcServiceConfig> for OidcProvider {
+    type Error = ConfigError;
+
+    fn try_from(config: OidcServiceConfig) -> Result<Self, Self::Error> {
+        let issuer_url = config
+            .issuer_url
+            .ok_or(ConfigError::MissingSetting("RASOPUS_OIDC_ISSUER_URL"))?;
+        let client_id = config
+            .client_id
+            .ok_or(ConfigError::MissingSetting("RASOPUS_OIDC_CLIENT_ID"))?;
+        let redirect_url = config
+            .redirect_url
+            .ok_or(ConfigError::MissingSetting("RASOPUS_OIDC_REDIRECT_URL"))?;
+
+        let default_role = config
+            .default_role
+            .map(|role| role.parse::<Role>())
+            .transpose()
+            .map_err(ConfigError::InvalidDefaultRole)?;
+
+        let mut role_mapping = Vec::new();
+        for entry in config
+            .role_mapping
+            .iter()
+            .flat_map(|role_mapping| role_mapping.split(','))
+            .filter(|entry| !entry.trim().is_empty())
+        {
+            let Some((claim_value, role)) = entry.split_once('=') else {
+                return Err(ConfigError::InvalidRoleMapping(entry.to_string()));
+            };
+
+            let Ok(role) = role.parse::<Role>() else {
+                return Err(ConfigError::InvalidRoleMapping(entry.to_string()));
+            };
+
+            role_mapping.push((claim_value.trim().to_string(), role));
+        }
+
+        let assignable_roles = default_role
+            .iter()
+            .chain(role_mapping.iter().map(|(_, role)| role));
+        for role in assignable_roles {
+            if *role == Role::System {
+                return Err(ConfigError::SystemRole);
+            }
+        }
+
+        Ok(Self {
+            issuer_url: IssuerUrl::new(issuer_url).map_err(ConfigError::InvalidIssuerUrl)?,
+            client_id: ClientId::new(client_id),
+            client_secret: config.client_secret.map(ClientSecret::new),
+            redirect_url: RedirectUrl::new(redirect_url)
+                .map_err(ConfigError::InvalidRedirectUrl)?,
+            scopes: config.scopes,
+            username_claim: config.username_claim,
+            default_role,
+            role_claim: config.role_claim,
+            role_mapping,
+            flow_timeout_secs: config.flow_timeout_secs,
+        })
+    }
+}
+
+impl OidcProvider {
+    /// Returns the highest role that the claim values of the configured role claim map to.
+    fn mapped_role(&self, claims: &HashMap<String, Value>) -> Option<Role> {
+        let role_claim = self.role_claim.as_ref()?;
+        let claim_values = match claims.get(role_claim)? {
+            Value::String(value) => vec![value.as_str()],
+            Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+
+        self.role_mapping
+            .iter()
+            .filter(|(claim_value, _)| claim_values.contains(&claim_value.as_str()))
+            .map(|(_, role)| *role)
+            .min()
+    }
+}
+
+/// Logs users in through an external OpenID Connect identity provider, using the authorization code flow with PKCE.
+///
+/// Identities are linked to users by their issuer and subject. Users logging in for the first time are either
+/// created with the default role or have to link their identity to an existing user while logged in.
+pub struct OidcService {
+    provider: Option<OidcProvider>,
+    http_client: reqwest::Client,
+    client_cache: RwLock<Option<(OidcClient, Instant)>>,
+}
+
+impl std::fmt::Debug for OidcService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcService")
+            .field("provider", &self.provider)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OidcService {
+    pub fn new(config: OidcServiceConfig) -> Result<Self, ConfigError> {
+        let provider = match config.issuer_url {
+            Some(_) => Some(OidcProvider::try_from(config)?),
+            None => None,
+        };
+
+        // Following redirects would allow the identity provider to make us send requests anywhere
+        let http_client = reqwest::ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+            .build()?;
+
+        Ok(Self {
+            provider,
+            http_client,
+            client_cache: RwLock::new(None),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.provider.is_some()
+    }
+
+    /// For how many seconds a started login can be completed.
+    pub fn flow_timeout_secs(&self) -> u64 {
+        self.provider
+            .as_ref()
+            .map(|provider| provider.flow_timeout_secs)
+            .unwrap_or_default()
+    }
+
+    /// Starts a login and returns the URL of the identity provider to send the user to.
+    ///
+    /// When a user is given, the identity is linked to that user instead of logging in.
+    pub async fn begin(
+        &self,
+        link_user_uuid: Option<Uuid>,
+    ) -> Result<(String, OidcFlow), BeginError> {
+        let provider = self.provider.as_ref().ok_or(BeginError::NotConfigured)?;
+        let client = self.client(provider).await.map_err(BeginError::Discovery)?;
+
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let mut request = client
+            .authorize_url(
+                AuthenticationFlow::<CoreResponseType>::AuthorizationCode,
+                CsrfToken::new_random,
+                Nonce::new_random,
+            )
+            .set_pkce_challenge(pkce_challenge);
+
+        // The openid scope is always requested
+        for scope in provider.scopes.iter().filter(|scope| *scope != "openid") {
+            request = request.add_scope(Scope::new(scope.clone()));
+        }
+
+        let (authorize_url, state, nonce) = request.url();
+        let flow = OidcFlow {
+            state: state.secret().clone(),
+            nonce: nonce.secret().clone(),
+            pkce_verifier: pkce_verifier.secret().clone(),
+            link_user_uuid,
+            started_at: Utc::now().timestamp(),
+        };
+
+        Ok((authorize_url.to_string(), flow))
+    }
+
+    /// Verifies the identity with the authorization code the identity provider redirected back with.
+    ///
+    /// This only asks the identity provider. The login is finished by resolving the identity to a user with `complete`.
+    pub async fn authenticate(
+        &self,
+        flow: OidcFlow,
+        code: &str,
+        state: &str,
+    ) -> Result<OidcIdentity, CompleteError> {
+        let provider = self.provider.as_ref().ok_or(CompleteError::NotConfigured)?;
+
+        let expires_at = flow
+            .started_at
+            .saturating_add(provider.flow_timeout_secs as i64);
+        if Utc::now().timestamp() >= expires_at {
+            return Err(CompleteError::Expired);
+        }
+
+        if util::secure_cmp(flow.state.as_bytes(), state.as_bytes()).is_err() {
+            return Err(CompleteError::InvalidState);
+        }
+
+        let client = self
+            .client(provider)
+            .await
+            .map_err(CompleteError::Discovery)?;
+
+        let token_response = client
+            .exchange_code(AuthorizationCode::new(code.to_string()))
+            .map_err(|error| CompleteError::TokenExchange(error.to_string()))?
+            .set_pkce_verifier(PkceCodeVerifier::new(flow.pkce_verifier))
+            .request_async(&self.http_client)
+            .await
+            .map_err(|error| CompleteError::TokenExchange(error.to_string()))?;
+
+        let id_token = token_response
+            .extra_fields()
+            .id_token()
+            .ok_or(CompleteError::MissingIdToken)?;
+        let id_token_claims = id_token
+            .claims(&client.id_token_verifier(), &Nonce::new(flow.nonce))
+            .map_err(|error| CompleteError::InvalidIdToken(error.to_string()))?;
+
+        let issuer = id_token_claims.issuer().to_string();
+        let subject = id_token_claims.subject().to_string();
+        let mut claims = claim_map(id_token_claims);
+
+        // Some identity providers only return profile and group claims through the user info endpoint
+        let needed_claims = [Some(&provider.username_claim), provider.role_claim.as_ref()];
+        let missing_claims = needed_claims
+            .into_iter()
+            .flatten()
+            .any(|claim| !claims.contains_key(claim));
+        if missing_claims {
+            if let Ok(request) = client.user_info(
+                token_response.access_token().clone(),
+                Some(id_token_claims.subject().clone()),
+            ) {
+                let user_info: UserInfoClaims<ExtraClaims, CoreGenderClaim> = request
+                    .request_async(&self.http_client)
+                    .await
+                    .map_err(|error| CompleteError::UserInfo(error.to_string()))?;
+
+                for (claim, value) in claim_map(&user_info) {
+                    claims.entry(claim).or_insert(value);
+                }
+            }
+        }
+
+        let mapped_role = provider.mapped_role(&claims);
+
+        Ok(OidcIdentity {
+            issuer,
+            subject,
+            claims,
+            mapped_role,
+            link_user_uuid: flow.link_user_uuid,
+        })
+    }
+
+    /// Completes a login by resolving the verified identity to a user, creating, linking or updating it as needed.
+    ///
+    /// The changes are made on the given connection and have to be committed by the caller. They should be part of a
+    /// unit of work that is only committed once the login is allowed, so a rejected login leaves no changes behind.
+    pub async fn complete(
+        &self,
+        identity: OidcIdentity,
+        user_service: &UserService,
+        audit_service: &AuditService,
+        connection: &mut PgConnection,
+    ) -> Result<OidcLogin, CompleteError> {
+        let provider = self.provider.as_ref().ok_or(CompleteError::NotConfigured)?;
+        let OidcIdentity {
+            issuer,
+            subject,
+            claims,
+            mapped_role,
+            link_user_uuid,
+        } = identity;
+
+        if let Some(user) = user_service
+            .load_by_identity(&issuer, &subject, &mut *connection)
+            .await?
+        {
+            if link_user_uuid.is_some_and(|link_user_uuid| link_user_uuid != user.uuid) {
+                return Err(CompleteError::IdentityLinkedToOtherUser);
+            }
+
+            let user = self
+                .apply_role(
+                    provider,
+                    user,
+                    mapped_role,
+                    user_service,
+                    audit_service,
+                    &mut *connection,
+                )
+                .await?;
+
+            return Ok(OidcLogin {
+                user,
+                created: false,
+                linked: false,
+            });
+        }
+
+        if let Some(link_user_uuid) = link_user_uuid {
+            let user = user_service
+                .load(&link_user_uuid, &mut *connection)
+                .await?
+                .ok_or(CompleteError::UserNotFound)?;
+
+            if !user_service
+                .link_identity(&user, &issuer, &subject, &mut *connection)
+                .await?
+            {
+                return Err(CompleteError::IdentityLinkedToOtherUser);
+            }
+
+            println!(
+                "Linked identity {} of issuer {} to user {}",
+                subject, issuer, user.uuid
+            );
+
+            audit_service
+                .record(
+                    AuditAction::UserIdentityLinked,
+                    AuditRecord {
+                        actor_uuid: Some(user.uuid),
+                        target: Some((AuditTargetType::User, user.uuid)),
+                        after: Some(serde_json::json!({ "issuer": issuer, "subject": subject })),
+                        ..Default::default()
+                    },
+                    &mut *connection,
+                )
+                .await?;
+
+            let user = self
+                .apply_role(
+                    provider,
+                    user,
+                    mapped_role,
+                    user_service,
+                    audit_service,
+                    &mut *connection,
+                )
+                .await?;
+
+            return Ok(OidcLogin {
+                user,
+                created: false,
+                linked: true,
+            });
+        }
+
+        let Some(default_role) = provider.default_role else {
+            return Err(CompleteError::NotLinked);
+        };
+
+        let username = match claims.get(&provider.username_claim) {
+            Some(Value::String(username)) => username.clone(),
+            _ => {
+                return Err(CompleteError::MissingUsernameClaim(
+                    provider.username_claim.clone(),
+                ));
+            }
+        };
+
+        // A user in the trash would keep the identity and the username from being taken by the new user
+        if let Some(deleted_user) = user_service
+            .purge_deleted_by_identity(&issuer, &subject, &mut *connection)
+            .await?
+        {
+            audit_service
+                .record(
+                    AuditAction::UserPurged,
+                    AuditRecord {
+                        target: Some((AuditTargetType::User, deleted_user.uuid)),
+                        before: Some(AuditService::user_snapshot(&deleted_user)),
+                        ..Default::default()
+                    },
+                    &mut *connection,
+                )
+                .await?;
+
+            println!(
+                "Purged user {} from the trash, because their identity {} of issuer {} logged in again",
+                deleted_user.uuid, subject, issuer
+            );
+        }
+
+        let user = user_service
+            .generate_external(username, mapped_role.unwrap_or(default_role))
+            .await?;
+        user_service.create(&user, &mut *connection).await?;
+
+        // Another login of the same identity might have created a user at the same time. Rolling back the unit of work
+        // undoes the creation of this one.
+        if !user_service
+            .link_identity(&user, &issuer, &subject, &mut *connection)
+            .await?
+        {
+            return Err(CompleteError::IdentityLinkedToOtherUser);
+        }
+
+        audit_service
+            .record(
+                AuditAction::UserCreated,
+                AuditRecord {
+                    actor_uuid: Some(user.uuid),
+                    target: Some((AuditTargetType::User, user.uuid)),
+                    after: Some(AuditService::user_snapshot(&user)),
+                    ..Default::default()
+                },
+                &mut *connection,
+            )
+            .await?;
+
+        println!(
+            "Created user {} for identity {} of issuer {}",
+            user.uuid, subject, issuer
+        );
+
+        Ok(OidcLogin {
+            user,
+            created: true,
+            linked: false,
+        })
+    }
+
+    /// Returns the client for the identity provider, discovering its metadata if it wasn't discovered recently.
+    ///
+    /// The metadata is refreshed regularly, so rotated signing keys are picked up.
+    async fn client(&self, provider: &OidcProvider) -> Result<OidcClient, String> {
+        if let Some((client, discovered_at)) = &*self.client_cache.read().await {
+            if discovered_at.elapsed() < Duration::from_secs(METADATA_REFRESH_SECS) {
+                return Ok(client.clone());
+            }
+        }
+
+        let provider_metadata =
+            CoreProviderMetadata::discover_async(provider.issuer_url.clone(), &self.http_client)
+                .await
+                .map_err(|error| error.to_string())?;
+
+        let client = OidcClient::from_provider_metadata(
+            provider_metadata,
+            provider.client_id.clone(),
+            provider.client_secret.clone(),
+        )
+        .set_redirect_uri(provider.redirect_url.clone());
+
+        *self.client_cache.write().await = Some((client.clone(), Instant::now()));
+
+        Ok(client)
+    }
+
+    /// Updates the role of the user to the role their claims map to, if a role claim is configured.
+    ///
+    /// The system user's role is never changed.
+    async fn apply_role(
+        &self,
+        provider: &OidcProvider,
+        mut user: User,
+        mapped_role: Option<Role>,
+        user_service: &UserService,
+        audit_service: &AuditService,
+        connection: &mut PgConnection,
+    ) -> Result<User, CompleteError> {
+        if provider.role_claim.is_none() || user.role == Role::System {
+            return Ok(user);
+        }
+
+        let Some(role) = mapped_role.or(provider.default_role) else {
+            return Err(CompleteError::NoMatchingRole);
+        };
+
+        if user.role != role {
+            println!(
+                "Changing the role of user {} from {:?} to {:?} based on their identity provider claims",
+                user.uuid, user.role, role
+            );
+
+            let before = AuditService::user_snapshot(&user);
+            user.role = role;
+            user_service.update(&user, &mut *connection).await?;
+
+            audit_service
+                .record(
+                    AuditAction::UserRoleChanged,
+                    AuditRecord {
+                        actor_uuid: Some(user.uuid),
+                        target: Some((AuditTargetType::User, user.uuid)),
+                        before: Some(before),
+                        after: Some(AuditService::user_snapshot(&user)),
+                        ..Default::default()
+                    },
+                    &mut *connection,
+                )
+                .await?;
+        }
+
+        Ok(user)
+    }
+}
+
+/// Returns all claims by name, including the standard ones.
+fn claim_map<T: Serialize>(claims: &T) -> HashMap<String, Value> {
+    match serde_json::to_value(claims) {
+        Ok(Value::Object(claims)) => claims.into_iter().collect(),
+        _ => HashMap::new(),
+    }
+}
@@ -0,0 +1,146 @@
+use orion::{
+    errors::UnknownCryptoError,
+    pwhash::{self, Password, PasswordHash},
+};
+
+const BYTES_PER_MB: u32 = 1024 * 1024;
+
+/// The maximum length of a password that is verified, in bytes.
+///
+/// This is a fixed limit, unlike the password policy, so lowering the policy's maximum length doesn't lock out users
+/// whose passwords were allowed before. It still limits how much work an attacker can cause by sending huge passwords.
+pub const MAX_PASSWORD_BYTES: usize = 1024;
+
+/// Hashes passwords with Argon2 and verifies them against their hashes.
+///
+/// Argon2 takes long enough to stall the async runtime, so it always runs on a blocking thread.
+#[derive(Debug, Clone)]
+pub struct PasswordHasher {
+    iterations: u32,
+    memory_kib: u32,
+}
+
+impl PasswordHasher {
+    pub fn new(iterations: u32, memory_mib: u32) -> Self {
+        Self {
+            iterations,
+            memory_kib: memory_mib * BYTES_PER_MB / 1024, // We have to convert back from MiB to KiB because orion expects KiB
+        }
+    }
+
+    /// Hashes the given password. Whether it satisfies the password policy has to be checked beforehand.
+    pub async fn hash(&self, password: &[u8]) -> Result<PasswordHash, UnknownCryptoError> {
+        let password = Password::from_slice(password)?;
+        let iterations = self.iterations;
+        let memory_kib = self.memory_kib;
+
+        run_blocking(move || pwhash::hash_password(&password, iterations, memory_kib)).await
+    }
+
+    /// Returns whether the given password matches the password hash.
+    ///
+    /// No password can be empty or longer than `MAX_PASSWORD_BYTES`, so such passwords are rejected without spending
+    /// time and memory on hashing them.
+    pub async fn verify(
+        &self,
+        password_hash: &PasswordHash,
+        password: &str,
+    ) -> Result<bool, UnknownCryptoError> {
+        if !self.is_possible(password) {
+            return Ok(false);
+        }
+
+        let password = Password::from_slice(password.as_bytes())?;
+        let password_hash = PasswordHash::from_encoded(password_hash.unprotected_as_encoded())?;
+
+        let verified =
+            run_blocking(move || pwhash::hash_password_verify(&password_hash, &password).is_ok())
+                .await;
+
+        Ok(verified)
+    }
+
+    /// Hashes the given password only to take as long as verifying it would.
+    ///
+    /// This way, the response time of a login does not reveal whether there was a password hash to verify against.
+    pub async fn hash_in_vain(&self, password: &str) -> Result<(), UnknownCryptoError> {
+        if !self.is_possible(password) {
+            return Ok(());
+        }
+
+        self.hash(password.as_bytes()).await?;
+
+        Ok(())
+    }
+
+    fn is_possible(&self, password: &str) -> bool {
+        !password.is_empty() && password.len() <= MAX_PASSWORD_BYTES
+    }
+}
+
+async fn run_blocking<T, F>(function: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    tokio::task::spawn_blocking(function)
+        .await
+        .unwrap_or_else(|error| std::panic::resume_unwind(error.into_panic()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn password_hasher() -> PasswordHasher {
+        PasswordHasher::new(3, 8)
+    }
+
+    #[tokio::test]
+    async fn verifies_hashed_password() {
+        let password_hasher = password_hasher();
+        let password_hash = password_hasher.hash(b"tulip anchor").await.unwrap();
+
+        assert!(
+            password_hasher
+                .verify(&password_hash, "tulip anchor")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !password_hasher
+                .verify(&password_hash, "tulip anchors")
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_impossible_passwords() {
+        let password_hasher = password_hasher();
+        let too_long = "a".repeat(MAX_PASSWORD_BYTES + 1);
+        let password_hash = password_hasher.hash(too_long.as_bytes()).await.unwrap();
+
+        assert!(!password_hasher.verify(&password_hash, "").await.unwrap());
+        assert!(
+            !password_hasher
+                .verify(&password_hash, &too_long)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn verifies_passwords_up_to_fixed_limit() {
+        let password_hasher = password_hasher();
+        let longest = "ä".repeat(MAX_PASSWORD_BYTES / 2);
+        let password_hash = password_hasher.hash(longest.as_bytes()).await.unwrap();
+
+        assert!(
+            password_hasher
+                .verify(&password_hash, &longest)
+                .await
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::{PgExecutor, Pool, Postgres};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    config::session_service::SessionServiceConfig,
+    model::{
+        DbEntity, PersistError,
+        entity::{
+            session::{DbSession, Session},
+            user::User,
+        },
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum IssueError {
+    #[error("Failed to persist session: {0}")]
+    Persist(#[from] PersistError),
+}
+
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum RevokeError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum PurgeError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+const PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Issues the sessions of logged in users and ends them when they expire or are revoked.
+#[derive(Debug, Clone)]
+pub struct SessionService {
+    config: SessionServiceConfig,
+}
+
+impl SessionService {
+    pub fn new(config: SessionServiceConfig) -> Self {
+        Self { config }
+    }
+
+    /// For how many seconds a session lasts after it was issued.
+    pub fn lifetime_secs(&self) -> u64 {
+        self.config.lifetime_secs
+    }
+
+    /// Issues a new session to the given user.
+    pub async fn issue<'e, E>(&self, user: &User, executor: E) -> Result<Session, IssueError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let now = Utc::now();
+        let session = Session {
+            uuid: Uuid::new_v4(),
+            user_uuid: user.uuid,
+            created_at: now,
+            expires_at: now + chrono::Duration::seconds(self.config.lifetime_secs as i64),
+            revoked_at: None,
+        };
+
+        DbSession::from(&session).create(executor).await?;
+
+        Ok(session)
+    }
+
+    /// Loads the session with the given UUID, unless it expired or was revoked.
+    pub async fn load_active<'e, E>(
+        &self,
+        identifier: &Uuid,
+        executor: E,
+    ) -> Result<Option<Session>, LoadError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "SELECT * FROM {} WHERE uuid = $1 AND revoked_at IS NULL AND expires_at > $2 LIMIT 1",
+            DbSession::main_table_name()
+        );
+
+        let db_session: Option<DbSession> = sqlx::query_as(&query)
+            .bind(identifier)
+            .bind(Utc::now())
+            .fetch_optional(executor)
+            .await?;
+
+        Ok(db_session.map(Session::from))
+    }
+
+    /// Revokes the session with the given UUID. Returns whether it was active.
+    pub async fn revoke<'e, E>(&self, identifier: &Uuid, executor: E) -> Result<bool, RevokeError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "UPDATE {} SET revoked_at = $1 WHERE uuid = $2 AND revoked_at IS NULL",
+            DbSession::main_table_name()
+        );
+
+        let result = sqlx::query(&query)
+            .bind(Utc::now())
+            .bind(identifier)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revokes all sessions of the given user, except for the given one. Returns how many were revoked.
+    pub async fn revoke_all<'e, E>(
+        &self,
+        user_uuid: &Uuid,
+        except: Option<&Uuid>,
+        executor: E,
+    ) -> Result<u64, RevokeError>
+    where
+        E: PgExecutor<'e>,
+    {
+        // uuid IS DISTINCT FROM NULL is true for every session, so nothing is kept without an exception
+        let query = format!(
+            "UPDATE {} SET revoked_at = $1 WHERE user_uuid = $2 AND uuid IS DISTINCT FROM $3 AND revoked_at IS NULL",
+            DbSession::main_table_name()
+        );
+
+        let result = sqlx::query(&query)
+            .bind(Utc::now())
+            .bind(user_uuid)
+            .bind(except)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes the sessions that expired or were revoked. Returns how many were deleted.
+    pub async fn purge_ended<'e, E>(&self, executor: E) -> Result<u64, PurgeError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "DELETE FROM {} WHERE expires_at < $1 OR revoked_at < $1",
+            DbSession::main_table_name()
+        );
+
+        let result = sqlx::query(&query)
+            .bind(Utc::now())
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes the ended sessions once an hour, for as long as Rasopus runs, so they don't pile up.
+    pub async fn run_purge_job(self, postgres_pool: Pool<Postgres>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(PURGE_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            match self.purge_ended(&postgres_pool).await {
+                Ok(0) => {}
+                Ok(sessions) => println!("Purged {} ended sessions", sessions),
+                Err(error) => eprintln!("Failed to purge the ended sessions: {}", error),
+            }
+        }
+    }
+}
@@ -1,22 +1,57 @@
+use orion::{errors::UnknownCryptoError, util};
 use sqlx::{Pool, Postgres};
 use thiserror::Error;
 
-use crate::model::entity::user::Role;
+use crate::{config::setup_service::SetupServiceConfig, model::entity::user::Role};
 
 use super::user::{self, UserService};
 
+const GENERATED_SETUP_TOKEN_BYTES: usize = 24;
+
 #[derive(Debug, Error)]
 pub enum SetupCheckError {
     #[error("The user service returned an error while checking if a system user exists: {0}")]
     UserService(#[from] user::ExistsError),
 }
 
-#[derive(Debug, Default)]
-pub struct SetupService;
+#[derive(Debug, Error)]
+pub enum SetupTokenError {
+    #[error("The given setup token is invalid")]
+    Invalid,
+}
+
+#[derive(Debug)]
+pub struct SetupService {
+    setup_token: String,
+    setup_token_generated: bool,
+}
 
 impl SetupService {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(config: SetupServiceConfig) -> Result<Self, UnknownCryptoError> {
+        let (setup_token, setup_token_generated) = match config.setup_token {
+            Some(setup_token) => (setup_token, false),
+            None => (generate_setup_token()?, true),
+        };
+
+        Ok(Self {
+            setup_token,
+            setup_token_generated,
+        })
+    }
+
+    /// The setup token that has to accompany the setup request.
+    pub fn setup_token(&self) -> &str {
+        &self.setup_token
+    }
+
+    /// Whether the setup token was generated on boot, rather than configured through the environment.
+    pub fn is_setup_token_generated(&self) -> bool {
+        self.setup_token_generated
+    }
+
+    pub fn verify_setup_token(&self, setup_token: &str) -> Result<(), SetupTokenError> {
+        util::secure_cmp(setup_token.as_bytes(), self.setup_token.as_bytes())
+            .map_err(|_| SetupTokenError::Invalid)
     }
 
     pub async fn needs_setup(
@@ -32,3 +67,11 @@ impl SetupService {
         Ok(needs_setup)
     }
 }
+
+fn generate_setup_token() -> Result<String, UnknownCryptoError> {
+    let mut bytes = [0u8; GENERATED_SETUP_TOKEN_BYTES];
+    util::secure_rand_bytes(&mut bytes)?;
+
+    let setup_token = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(setup_token)
+}
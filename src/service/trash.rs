@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::{Pool, Postgres};
+use thiserror::Error;
+
+use crate::{
+    adapter::user::UnadaptUserError,
+    config::trash_service::TrashServiceConfig,
+    model::{
+        entity::{
+            audit_event::{AuditAction, AuditTargetType},
+            user::{DbUser, User},
+        },
+        trash::DbEntityTrash,
+    },
+};
+
+use super::{
+    AuditService,
+    audit::{AuditRecord, RecordError},
+    unit_of_work::{UnitOfWork, UnitOfWorkError},
+};
+
+#[derive(Debug, Error)]
+pub enum PurgeError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Failed to unadapt purged user: {0}")]
+    UnadaptUser(#[from] UnadaptUserError),
+
+    #[error("Failed to record the purge: {0}")]
+    AuditServiceRecord(#[from] RecordError),
+
+    #[error("Unit of work error: {0}")]
+    UnitOfWork(#[from] UnitOfWorkError),
+}
+
+/// Permanently deletes the entities that have been in the trash for longer than the configured retention.
+///
+/// Every purged user is recorded in the audit log, in the same transaction as the purge.
+#[derive(Debug, Clone)]
+pub struct TrashService {
+    config: TrashServiceConfig,
+}
+
+impl TrashService {
+    pub fn new(config: TrashServiceConfig) -> Self {
+        Self { config }
+    }
+
+    /// Purges the entities whose retention is over. Returns how many were purged.
+    pub async fn purge_expired(
+        &self,
+        audit_service: &AuditService,
+        postgres_pool: &Pool<Postgres>,
+    ) -> Result<u64, PurgeError> {
+        let deleted_before =
+            Utc::now() - chrono::Duration::seconds(self.config.retention_secs as i64);
+
+        let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+
+        let db_users =
+            DbUser::purge_deleted_before(deleted_before, unit_of_work.connection()).await?;
+        for db_user in &db_users {
+            let user = User::try_from(db_user)?;
+            audit_service
+                .record(
+                    AuditAction::UserPurged,
+                    AuditRecord {
+                        target: Some((AuditTargetType::User, user.uuid)),
+                        before: Some(AuditService::user_snapshot(&user)),
+                        ..Default::default()
+                    },
+                    unit_of_work.connection(),
+                )
+                .await?;
+        }
+
+        unit_of_work.commit().await?;
+
+        let users = db_users.len() as u64;
+        if users > 0 {
+            println!("Purged {} users from the trash", users);
+        }
+
+        Ok(users)
+    }
+
+    /// Purges the trash once per purge interval, for as long as Rasopus runs.
+    pub async fn run_purge_job(self, audit_service: AuditService, postgres_pool: Pool<Postgres>) {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(self.config.purge_interval_secs.max(1)));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(error) = self.purge_expired(&audit_service, &postgres_pool).await {
+                eprintln!("Failed to purge the trash: {}", error);
+            }
+        }
+    }
+}
@@ -0,0 +1,512 @@
+use chrono::Utc;
+use orion::{
+    aead::{self, SecretKey},
+    errors::UnknownCryptoError,
+    hash,
+    hazardous::kdf::hkdf,
+    util,
+};
+use sqlx::{PgConnection, PgExecutor, Pool, Postgres};
+use thiserror::Error;
+use totp_rs::{Algorithm, TOTP, TotpUrlError};
+use uuid::Uuid;
+
+use crate::{
+    config::two_factor_service::TwoFactorServiceConfig,
+    model::{
+        DbEntity, PersistError,
+        entity::{
+            recovery_code::{DbRecoveryCode, RecoveryCode},
+            user::User,
+            user_totp::{DbUserTotp, UserTotp},
+        },
+    },
+};
+
+const TOTP_SECRET_BYTES: usize = 20;
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECS: u64 = 30;
+const TOTP_SKEW_STEPS: u64 = 1;
+
+const RECOVERY_CODE_LENGTH: usize = 10;
+// 32 characters, so every random byte maps to a character without bias. Characters that are easily confused are left out.
+const RECOVERY_CODE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+const ENCRYPTION_KEY_SALT: &[u8] = b"rasopus";
+const ENCRYPTION_KEY_INFO: &[u8] = b"rasopus totp secret encryption";
+const ENCRYPTION_KEY_BYTES: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum EnabledError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum EnrollError {
+    #[error("The user already has a confirmed TOTP")]
+    AlreadyEnrolled,
+
+    #[error("Cryptography error: {0}")]
+    Cryptography(#[from] UnknownCryptoError),
+
+    #[error("Failed to create TOTP: {0}")]
+    Totp(#[from] TotpUrlError),
+
+    #[error("Failed to create QR code: {0}")]
+    QrCode(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ConfirmError {
+    #[error("The user has no TOTP enrollment to confirm")]
+    NotEnrolling,
+
+    #[error("The given code is invalid")]
+    InvalidCode,
+
+    #[error("The TOTP secret can't be decrypted, so the secret key was probably changed")]
+    SecretUnreadable,
+
+    #[error("Cryptography error: {0}")]
+    Cryptography(#[from] UnknownCryptoError),
+
+    #[error("Failed to create TOTP: {0}")]
+    Totp(#[from] TotpUrlError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Failed to persist TOTP confirmation: {0}")]
+    Persist(#[from] PersistError),
+}
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("The user has no confirmed TOTP")]
+    NotEnrolled,
+
+    #[error("The TOTP secret can't be decrypted, so the secret key was probably changed")]
+    SecretUnreadable,
+
+    #[error("Cryptography error: {0}")]
+    Cryptography(#[from] UnknownCryptoError),
+
+    #[error("Failed to create TOTP: {0}")]
+    Totp(#[from] TotpUrlError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum DisableError {
+    #[error("The user has no TOTP")]
+    NotEnrolled,
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// The information needed to add a TOTP to an authenticator app.
+#[derive(Debug)]
+pub struct TotpEnrollment {
+    /// The TOTP secret, encoded as base32, for entering it manually.
+    pub secret: String,
+
+    /// The otpauth:// URI containing all TOTP parameters.
+    pub provisioning_uri: String,
+
+    /// A QR code of the provisioning URI, as a base64 encoded PNG.
+    pub qr_code_png_base64: String,
+}
+
+/// Manages TOTP second factors and recovery codes.
+///
+/// TOTP secrets are encrypted at rest with a key derived from the configured secret key.
+/// Recovery codes are random and long enough that a fast hash is sufficient to store them.
+pub struct TwoFactorService {
+    config: TwoFactorServiceConfig,
+    encryption_key: SecretKey,
+}
+
+// The encryption key must never be printed
+impl std::fmt::Debug for TwoFactorService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TwoFactorService")
+            .field("issuer", &self.config.issuer)
+            .field("recovery_code_count", &self.config.recovery_code_count)
+            .field("pending_login_secs", &self.config.pending_login_secs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TwoFactorService {
+    pub fn new(config: TwoFactorServiceConfig) -> Result<Self, UnknownCryptoError> {
+        let mut key_bytes = [0u8; ENCRYPTION_KEY_BYTES];
+        hkdf::sha256::derive_key(
+            ENCRYPTION_KEY_SALT,
+            config.secret_key.as_bytes(),
+            Some(ENCRYPTION_KEY_INFO),
+            &mut key_bytes,
+        )?;
+        let encryption_key = SecretKey::from_slice(&key_bytes)?;
+
+        Ok(Self {
+            config,
+            encryption_key,
+        })
+    }
+
+    /// For how many seconds a user may enter their second factor after entering their password.
+    pub fn pending_login_secs(&self) -> u64 {
+        self.config.pending_login_secs
+    }
+
+    /// Whether the user has a confirmed TOTP and therefore has to provide a code when logging in.
+    pub async fn is_enabled(
+        &self,
+        user: &User,
+        postgres_pool: &Pool<Postgres>,
+    ) -> Result<bool, EnabledError> {
+        let query = format!(
+            "SELECT * FROM {} WHERE user_uuid = $1 AND confirmed LIMIT 1",
+            DbUserTotp::main_table_name()
+        );
+
+        let result = sqlx::query(&query)
+            .bind(user.uuid)
+            .fetch_optional(postgres_pool)
+            .await?;
+
+        Ok(result.is_some())
+    }
+
+    /// Starts the TOTP enrollment for the given user, replacing any previous unconfirmed enrollment.
+    ///
+    /// The TOTP is only required for logging in after it was confirmed with `confirm_enrollment`.
+    pub async fn begin_enrollment(
+        &self,
+        user: &User,
+        postgres_pool: &Pool<Postgres>,
+    ) -> Result<TotpEnrollment, EnrollError> {
+        let mut secret = vec![0u8; TOTP_SECRET_BYTES];
+        util::secure_rand_bytes(&mut secret)?;
+        let encrypted_secret = aead::seal(&self.encryption_key, &secret)?;
+
+        let totp = self.build_totp(secret, user)?;
+        let enrollment = TotpEnrollment {
+            secret: totp.get_secret_base32(),
+            provisioning_uri: totp.get_url(),
+            qr_code_png_base64: totp.get_qr_base64().map_err(EnrollError::QrCode)?,
+        };
+
+        let user_totp = UserTotp {
+            user_uuid: user.uuid,
+            encrypted_secret,
+            confirmed: false,
+            last_used_step: None,
+            created_at: Utc::now(),
+        };
+        let db_user_totp = DbUserTotp::from(&user_totp);
+
+        // Checking for a confirmed TOTP and writing the new one in one statement keeps a concurrent confirmation from being overwritten
+        let query = format!(
+            "INSERT INTO {0} (user_uuid, encrypted_secret, confirmed, last_used_step, created_at) VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT (user_uuid) DO UPDATE SET encrypted_secret = EXCLUDED.encrypted_secret, last_used_step = EXCLUDED.last_used_step, created_at = EXCLUDED.created_at \
+             WHERE {0}.confirmed = FALSE",
+            DbUserTotp::main_table_name()
+        );
+
+        let result = sqlx::query(&query)
+            .bind(db_user_totp.user_uuid)
+            .bind(&db_user_totp.encrypted_secret)
+            .bind(db_user_totp.confirmed)
+            .bind(db_user_totp.last_used_step)
+            .bind(db_user_totp.created_at)
+            .execute(postgres_pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(EnrollError::AlreadyEnrolled);
+        }
+
+        Ok(enrollment)
+    }
+
+    /// Confirms the TOTP enrollment of the given user with a code from their authenticator.
+    ///
+    /// Returns freshly generated recovery codes, replacing any previous ones. They can't be retrieved again later.
+    pub async fn confirm_enrollment(
+        &self,
+        user: &User,
+        code: &str,
+        connection: &mut PgConnection,
+    ) -> Result<Vec<String>, ConfirmError> {
+        let user_totp = match self.load_user_totp(&user.uuid, &mut *connection).await? {
+            Some(user_totp) if !user_totp.confirmed => user_totp,
+            _ => return Err(ConfirmError::NotEnrolling),
+        };
+
+        let secret = self
+            .open_secret(user, &user_totp)
+            .ok_or(ConfirmError::SecretUnreadable)?;
+        let totp = self.build_totp(secret, user)?;
+        let Some(step) = matching_step(&totp, code, user_totp.last_used_step) else {
+            return Err(ConfirmError::InvalidCode);
+        };
+
+        let user_totp = UserTotp {
+            confirmed: true,
+            last_used_step: Some(step),
+            ..user_totp
+        };
+        DbUserTotp::from(&user_totp)
+            .update(&mut *connection)
+            .await?;
+
+        let recovery_codes = self
+            .regenerate_recovery_codes(&user.uuid, connection)
+            .await?;
+
+        Ok(recovery_codes)
+    }
+
+    /// Verifies a TOTP code or a recovery code of the given user.
+    ///
+    /// Each TOTP code and each recovery code is only accepted once.
+    pub async fn verify(
+        &self,
+        user: &User,
+        code: &str,
+        postgres_pool: &Pool<Postgres>,
+    ) -> Result<bool, VerifyError> {
+        let user_totp = match self.load_user_totp(&user.uuid, postgres_pool).await? {
+            Some(user_totp) if user_totp.confirmed => user_totp,
+            _ => return Err(VerifyError::NotEnrolled),
+        };
+
+        let code = code.trim();
+        let is_totp_code =
+            code.len() == TOTP_DIGITS && code.chars().all(|character| character.is_ascii_digit());
+        if !is_totp_code {
+            return self.use_recovery_code(user, code, postgres_pool).await;
+        }
+
+        // Recovery codes are hashed instead of encrypted, so they keep working when the secret can't be decrypted
+        let secret = self
+            .open_secret(user, &user_totp)
+            .ok_or(VerifyError::SecretUnreadable)?;
+        let totp = self.build_totp(secret, user)?;
+        let Some(step) = matching_step(&totp, code, user_totp.last_used_step) else {
+            return Ok(false);
+        };
+
+        // Only one request can use a step, even if two requests with the same code arrive at the same time
+        let query = format!(
+            "UPDATE {} SET last_used_step = $1 WHERE user_uuid = $2 AND (last_used_step IS NULL OR last_used_step < $1)",
+            DbUserTotp::main_table_name()
+        );
+
+        let result = sqlx::query(&query)
+            .bind(step as i64)
+            .bind(user.uuid)
+            .execute(postgres_pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Removes the TOTP and recovery codes of the given user.
+    pub async fn disable(
+        &self,
+        user_uuid: &Uuid,
+        connection: &mut PgConnection,
+    ) -> Result<(), DisableError> {
+        if !DbUserTotp::delete(user_uuid, &mut *connection).await? {
+            return Err(DisableError::NotEnrolled);
+        }
+
+        self.delete_recovery_codes(user_uuid, connection).await?;
+
+        Ok(())
+    }
+
+    /// Decrypts the TOTP secret of the given user.
+    ///
+    /// This only fails if the secret was encrypted with another key, which happens when the secret key is changed.
+    fn open_secret(&self, user: &User, user_totp: &UserTotp) -> Option<Vec<u8>> {
+        match aead::open(&self.encryption_key, &user_totp.encrypted_secret) {
+            Ok(secret) => Some(secret),
+            Err(_) => {
+                eprintln!(
+                    "The TOTP secret of user {} can't be decrypted. If the secret key was changed, the two-factor authentication of the user has to be reset.",
+                    user.uuid
+                );
+                None
+            }
+        }
+    }
+
+    fn build_totp(&self, secret: Vec<u8>, user: &User) -> Result<TOTP, TotpUrlError> {
+        TOTP::new(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            TOTP_SKEW_STEPS as u8,
+            TOTP_STEP_SECS,
+            secret,
+            Some(self.config.issuer.clone()),
+            user.username.clone(),
+        )
+    }
+
+    async fn load_user_totp<'e, E>(
+        &self,
+        user_uuid: &Uuid,
+        executor: E,
+    ) -> Result<Option<UserTotp>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        let db_user_totp = match DbUserTotp::load(user_uuid, executor).await {
+            Ok(db_user_totp) => db_user_totp,
+            Err(sqlx::Error::RowNotFound) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let user_totp = UserTotp::from(db_user_totp);
+
+        Ok(Some(user_totp))
+    }
+
+    async fn use_recovery_code(
+        &self,
+        user: &User,
+        code: &str,
+        postgres_pool: &Pool<Postgres>,
+    ) -> Result<bool, VerifyError> {
+        let code_hash = hash_recovery_code(code)?;
+
+        // Marking the code as used in the same query makes sure it can only be used once
+        let query = format!(
+            "UPDATE {} SET used_at = $1 WHERE user_uuid = $2 AND code_hash = $3 AND used_at IS NULL",
+            DbRecoveryCode::main_table_name()
+        );
+
+        let result = sqlx::query(&query)
+            .bind(Utc::now())
+            .bind(user.uuid)
+            .bind(code_hash)
+            .execute(postgres_pool)
+            .await?;
+
+        let used = result.rows_affected() > 0;
+        if used {
+            println!("User {} logged in with a recovery code", user.uuid);
+        }
+
+        Ok(used)
+    }
+
+    async fn regenerate_recovery_codes(
+        &self,
+        user_uuid: &Uuid,
+        connection: &mut PgConnection,
+    ) -> Result<Vec<String>, ConfirmError> {
+        self.delete_recovery_codes(user_uuid, &mut *connection)
+            .await?;
+
+        let mut codes = Vec::with_capacity(self.config.recovery_code_count);
+        for _ in 0..self.config.recovery_code_count {
+            let code = generate_recovery_code()?;
+            let recovery_code = RecoveryCode {
+                uuid: Uuid::new_v4(),
+                user_uuid: *user_uuid,
+                code_hash: hash_recovery_code(&code)?,
+                used_at: None,
+                created_at: Utc::now(),
+            };
+            DbRecoveryCode::from(&recovery_code)
+                .create(&mut *connection)
+                .await?;
+
+            codes.push(code);
+        }
+
+        Ok(codes)
+    }
+
+    async fn delete_recovery_codes<'e, E>(
+        &self,
+        user_uuid: &Uuid,
+        executor: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "DELETE FROM {} WHERE user_uuid = $1",
+            DbRecoveryCode::main_table_name()
+        );
+
+        sqlx::query(&query)
+            .bind(user_uuid)
+            .execute(executor)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns the time step the given code is valid for, if it is valid now and newer than the last used step.
+fn matching_step(totp: &TOTP, code: &str, last_used_step: Option<u64>) -> Option<u64> {
+    let current_step = Utc::now().timestamp() as u64 / TOTP_STEP_SECS;
+    let first_step = current_step.saturating_sub(TOTP_SKEW_STEPS);
+    let last_step = current_step + TOTP_SKEW_STEPS;
+
+    (first_step..=last_step)
+        .filter(|step| last_used_step.is_none_or(|last_used_step| *step > last_used_step))
+        .find(|step| {
+            let expected = totp.generate(step * TOTP_STEP_SECS);
+            util::secure_cmp(expected.as_bytes(), code.as_bytes()).is_ok()
+        })
+}
+
+fn generate_recovery_code() -> Result<String, UnknownCryptoError> {
+    let mut bytes = [0u8; RECOVERY_CODE_LENGTH];
+    util::secure_rand_bytes(&mut bytes)?;
+
+    let characters = bytes
+        .iter()
+        .map(|byte| RECOVERY_CODE_ALPHABET[(*byte as usize) % RECOVERY_CODE_ALPHABET.len()] as char)
+        .collect::<Vec<_>>();
+
+    let (first_half, second_half) = characters.split_at(RECOVERY_CODE_LENGTH / 2);
+    let code = format!(
+        "{}-{}",
+        first_half.iter().collect::<String>(),
+        second_half.iter().collect::<String>()
+    );
+
+    Ok(code)
+}
+
+/// Hashes a recovery code, ignoring case and dashes, so it doesn't matter how the user types it.
+fn hash_recovery_code(code: &str) -> Result<String, UnknownCryptoError> {
+    let normalized = code
+        .chars()
+        .filter(|character| !character.is_whitespace() && *character != '-')
+        .map(|character| character.to_ascii_uppercase())
+        .collect::<String>();
+
+    let digest = hash::digest(normalized.as_bytes())?;
+    let code_hash = digest
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    Ok(code_hash)
+}
@@ -0,0 +1,43 @@
+use sqlx::{PgConnection, Pool, Postgres, Transaction};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum UnitOfWorkError {
+    #[error("Failed to begin the transaction: {0}")]
+    Begin(sqlx::Error),
+
+    #[error("Failed to commit the transaction: {0}")]
+    Commit(sqlx::Error),
+}
+
+/// Changes made through several services that are committed to the database together, or not at all.
+///
+/// The connection of the unit of work has to be passed to every service call that belongs to it.
+/// If the unit of work is dropped without being committed, for example because a service call returned an error,
+/// all of its changes are rolled back.
+#[derive(Debug)]
+pub struct UnitOfWork {
+    transaction: Transaction<'static, Postgres>,
+}
+
+impl UnitOfWork {
+    pub async fn begin(postgres_pool: &Pool<Postgres>) -> Result<Self, UnitOfWorkError> {
+        let transaction = postgres_pool
+            .begin()
+            .await
+            .map_err(UnitOfWorkError::Begin)?;
+
+        Ok(Self { transaction })
+    }
+
+    pub fn connection(&mut self) -> &mut PgConnection {
+        &mut self.transaction
+    }
+
+    pub async fn commit(self) -> Result<(), UnitOfWorkError> {
+        self.transaction
+            .commit()
+            .await
+            .map_err(UnitOfWorkError::Commit)
+    }
+}
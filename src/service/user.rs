@@ -1,26 +1,69 @@
-use orion::{
-    errors::UnknownCryptoError,
-    pwhash::{self, Password},
-};
-use sqlx::{Pool, Postgres};
+use chrono::Utc;
+use orion::{errors::UnknownCryptoError, pwhash::PasswordHash, util};
+use sqlx::{PgConnection, PgExecutor, Pool, Postgres};
 use thiserror::Error;
 use uuid::Uuid;
 
 use crate::{
-    adapter::user::UnadaptUserError,
+    adapter::{
+        user::{PRIMARY_KEY, UNIQUE_SYSTEM_ROLE_INDEX, UNIQUE_USERNAME_INDEX, UnadaptUserError},
+        user_identity::UNIQUE_SUBJECT_INDEX,
+    },
     config::user_service::UserServiceConfig,
     model::{
-        DbEntity,
-        entity::user::{DbUser, Role, User},
+        self, DbEntity, Persisted,
+        entity::{
+            audit_event::{AuditAction, AuditTargetType},
+            user::{DbUser, Role, User, UserFilter, UserSortColumn},
+            user_identity::{DbUserIdentity, UserIdentity},
+        },
+        list::{self, DbEntityList, ListQuery, Listed},
+        trash::DbEntityTrash,
+    },
+    validation::{
+        password::{self, PasswordError},
+        username::{self, UsernameError},
     },
 };
 
-const BYTES_PER_MB: u32 = 1024 * 1024;
+use super::{
+    AuditService,
+    audit::{AuditRecord, RecordError},
+    auth_backend::{
+        AuthBackend, AuthBackendError, AuthResult, ExternalIdentity, LdapAuthBackend,
+        PasswordAuthBackend, ldap::LdapConfigError,
+    },
+    password_hasher::{MAX_PASSWORD_BYTES, PasswordHasher},
+    unit_of_work::{UnitOfWork, UnitOfWorkError},
+};
+
+const EXTERNAL_USER_PASSWORD_BYTES: usize = 32;
 
 #[derive(Debug, Error)]
 pub enum GenerateError {
-    #[error("The given password was empty")]
-    EmptyPassword,
+    #[error("The given username is invalid: {0}")]
+    InvalidUsername(#[from] UsernameError),
+
+    #[error("The given password is invalid: {0}")]
+    InvalidPassword(#[from] PasswordError),
+
+    #[error("Cryptography error: {0}")]
+    Cryptography(#[from] UnknownCryptoError),
+}
+
+impl From<SetPasswordError> for GenerateError {
+    fn from(error: SetPasswordError) -> Self {
+        match error {
+            SetPasswordError::InvalidPassword(error) => Self::InvalidPassword(error),
+            SetPasswordError::Cryptography(error) => Self::Cryptography(error),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SetPasswordError {
+    #[error("The given password is invalid: {0}")]
+    InvalidPassword(#[from] PasswordError),
 
     #[error("Cryptography error: {0}")]
     Cryptography(#[from] UnknownCryptoError),
@@ -37,26 +80,113 @@ pub enum CreateError {
     #[error("User already exists")]
     AlreadyExists,
 
+    #[error("A system user already exists")]
+    SystemUserAlreadyExists,
+
+    #[error("The username is already taken")]
+    UsernameTaken,
+
+    #[error("Failed to persist user: {0}")]
+    Persist(#[from] model::PersistError),
+}
+
+#[derive(Debug, Error)]
+pub enum LoadError {
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
+
+    #[error("Failed to unadapt user from database user: {0}")]
+    Unadapt(#[from] UnadaptUserError),
 }
 
 #[derive(Debug, Error)]
-pub enum LoadError {
+pub enum ListError {
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
+    #[error("Failed to list users: {0}")]
+    List(#[from] list::ListError),
+
     #[error("Failed to unadapt user from database user: {0}")]
     Unadapt(#[from] UnadaptUserError),
 }
 
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Unknown authentication backend: {0}")]
+    UnknownAuthBackend(String),
+
+    #[error("No authentication backend is enabled")]
+    NoAuthBackend,
+
+    #[error("The LDAP authentication backend is enabled, but RASOPUS_LDAP_URL is not set")]
+    MissingLdapConfig,
+
+    #[error("The LDAP authentication backend is misconfigured: {0}")]
+    Ldap(#[from] LdapConfigError),
+
+    #[error("RASOPUS_PASSWORD_MAX_LENGTH must be at most {0}")]
+    PasswordMaxLengthTooLarge(usize),
+}
+
+#[derive(Debug, Error)]
+pub enum AuthenticateError {
+    #[error("The {0} authentication backend returned an error: {1}")]
+    AuthBackend(&'static str, AuthBackendError),
+
+    #[error("The username of the external user is invalid: {0}")]
+    InvalidExternalUsername(UsernameError),
+
+    #[error("Failed to load the user of the external identity: {0}")]
+    Load(#[from] LoadError),
+
+    #[error("Failed to generate the user of the external identity: {0}")]
+    Generate(GenerateError),
+
+    #[error("Failed to create the user of the external identity: {0}")]
+    Create(#[from] CreateError),
+
+    #[error("Failed to update the user of the external identity: {0}")]
+    Update(#[from] UpdateError),
+
+    #[error("Failed to commit the changes to the user of the external identity: {0}")]
+    UnitOfWork(#[from] UnitOfWorkError),
+
+    #[error("Failed to link the external identity: {0}")]
+    LinkIdentity(#[from] LinkIdentityError),
+
+    #[error("The external identity logged in several times at once")]
+    ConcurrentLogin,
+
+    #[error("Failed to record the changes to the user of the external identity: {0}")]
+    AuditRecord(#[from] RecordError),
+}
+
+impl From<GenerateError> for AuthenticateError {
+    fn from(error: GenerateError) -> Self {
+        match error {
+            GenerateError::InvalidUsername(error) => Self::InvalidExternalUsername(error),
+            error => Self::Generate(error),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LinkIdentityError {
+    #[error("Failed to persist user identity: {0}")]
+    Persist(#[from] model::PersistError),
+}
+
 #[derive(Debug, Error)]
 pub enum UpdateError {
     #[error("User not found")]
     NotFound,
 
-    #[error("Database error: {0}")]
-    Database(#[from] sqlx::Error),
+    #[error("The username is already taken")]
+    UsernameTaken,
+
+    #[error("Failed to persist user: {0}")]
+    Persist(#[from] model::PersistError),
 }
 
 #[derive(Debug, Error)]
@@ -69,19 +199,80 @@ pub enum DeleteError {
 }
 
 #[derive(Debug, Error)]
-pub enum PersistError {
+pub enum RestoreError {
+    #[error("User not found in the trash")]
+    NotFound,
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 }
 
+#[derive(Debug, Error)]
+pub enum PersistError {
+    #[error("A system user already exists")]
+    SystemUserAlreadyExists,
+
+    #[error("The username is already taken")]
+    UsernameTaken,
+
+    #[error("The user is in the trash")]
+    Deleted,
+
+    #[error("Failed to persist user: {0}")]
+    Persist(#[from] model::PersistError),
+}
+
+/// The outcome of a successful authentication.
+#[derive(Debug)]
+pub enum Authenticated {
+    /// The local user was authenticated.
+    Local,
+
+    /// An external identity was authenticated. Contains the user it is linked to.
+    External(User),
+}
+
 #[derive(Debug)]
 pub struct UserService {
     config: UserServiceConfig,
+    password_hasher: PasswordHasher,
+    auth_backends: Vec<Box<dyn AuthBackend>>,
 }
 
 impl UserService {
-    pub fn new(config: UserServiceConfig) -> Self {
-        Self { config }
+    pub fn new(config: UserServiceConfig) -> Result<Self, ConfigError> {
+        // A character takes at most 4 bytes, so every password the policy allows can be verified
+        let max_password_length = MAX_PASSWORD_BYTES / 4;
+        if config.password_policy.max_length > max_password_length {
+            return Err(ConfigError::PasswordMaxLengthTooLarge(max_password_length));
+        }
+
+        let password_hasher =
+            PasswordHasher::new(config.argon2_iterations, config.argon2_memory_mib);
+
+        let mut auth_backends: Vec<Box<dyn AuthBackend>> = Vec::new();
+        for auth_backend in &config.auth_backends {
+            match auth_backend.as_str() {
+                "password" => {
+                    auth_backends.push(Box::new(PasswordAuthBackend::new(password_hasher.clone())))
+                }
+                "ldap" => {
+                    let ldap_config = config.ldap.clone().ok_or(ConfigError::MissingLdapConfig)?;
+                    auth_backends.push(Box::new(LdapAuthBackend::new(ldap_config)?));
+                }
+                _ => return Err(ConfigError::UnknownAuthBackend(auth_backend.clone())),
+            }
+        }
+
+        if auth_backends.is_empty() {
+            return Err(ConfigError::NoAuthBackend);
+        }
+
+        Ok(Self {
+            config,
+            password_hasher,
+            auth_backends,
+        })
     }
 
     pub async fn generate(
@@ -90,16 +281,8 @@ impl UserService {
         password: &str,
         role: Role,
     ) -> Result<User, GenerateError> {
-        if password.is_empty() {
-            return Err(GenerateError::EmptyPassword);
-        }
-
-        let mb = self.config.argon2_memory_mib;
-        let bytes = mb * BYTES_PER_MB / 1024; // We have to convert back from MiB to KiB because orion expects KiB
-        let iterations = self.config.argon2_iterations;
-
-        let password = Password::from_slice(password.as_bytes())?;
-        let password_hash = pwhash::hash_password(&password, iterations, bytes)?;
+        let username = username::validate(&username)?;
+        let password_hash = self.hash_password(password, &username).await?;
 
         let uuid = Uuid::new_v4();
         let created_at = chrono::Utc::now();
@@ -109,106 +292,582 @@ impl UserService {
             role,
             password_hash,
             created_at,
+            updated_at: created_at,
+            deleted_at: None,
+            deleted_by: None,
         };
 
         Ok(user)
     }
 
-    pub async fn exists_any_user_by_role(
+    /// Validates the new password of the given user against the password policy and replaces their password hash.
+    ///
+    /// The change is not persisted. Every way of setting a password has to go through this, so no password can bypass
+    /// the policy.
+    pub async fn set_password(
+        &self,
+        user: &mut User,
+        password: &str,
+    ) -> Result<(), SetPasswordError> {
+        user.password_hash = self.hash_password(password, &user.username).await?;
+
+        Ok(())
+    }
+
+    /// Returns whether the given password matches the password hash of the given local user.
+    pub async fn verify_password(
+        &self,
+        user: &User,
+        password: &str,
+    ) -> Result<bool, UnknownCryptoError> {
+        self.password_hasher
+            .verify(&user.password_hash, password)
+            .await
+    }
+
+    async fn hash_password(
         &self,
+        password: &str,
+        username: &str,
+    ) -> Result<PasswordHash, SetPasswordError> {
+        password::validate(password, &[username], &self.config.password_policy).await?;
+
+        let password_hash = self.password_hasher.hash(password.as_bytes()).await?;
+
+        Ok(password_hash)
+    }
+
+    /// Generates a user who logs in through an external identity provider.
+    ///
+    /// The user gets a random password that is never revealed, so they can't log in with a password.
+    pub async fn generate_external(
+        &self,
+        username: String,
         role: Role,
-        postgres_pool: &Pool<Postgres>,
-    ) -> Result<bool, ExistsError> {
+    ) -> Result<User, GenerateError> {
+        let username = username::validate(&username)?;
+
+        let mut password = [0u8; EXTERNAL_USER_PASSWORD_BYTES];
+        util::secure_rand_bytes(&mut password)?;
+
+        let password_hash = self.password_hasher.hash(&password).await?;
+
+        let now = chrono::Utc::now();
+        let user = User {
+            uuid: Uuid::new_v4(),
+            username,
+            role,
+            password_hash,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            deleted_by: None,
+        };
+
+        Ok(user)
+    }
+
+    pub async fn exists_any_user_by_role<'e, E>(
+        &self,
+        role: Role,
+        executor: E,
+    ) -> Result<bool, ExistsError>
+    where
+        E: PgExecutor<'e>,
+    {
         let query = format!(
-            "SELECT * FROM {} WHERE role = $1 LIMIT 1",
-            DbUser::main_table_name()
+            "SELECT * FROM {} WHERE role = $1 AND {} IS NULL LIMIT 1",
+            DbUser::main_table_name(),
+            DbUser::DELETED_AT_COLUMN
         );
 
         let result = sqlx::query(&query)
             .bind::<i16>(role.into())
-            .fetch_optional(postgres_pool)
+            .fetch_optional(executor)
             .await?;
 
         Ok(result.is_some())
     }
 
-    pub async fn exists(
-        &self,
-        user: &User,
-        postgres_pool: &Pool<Postgres>,
-    ) -> Result<bool, ExistsError> {
-        let exists = DbUser::exists(&user.uuid, postgres_pool).await?;
+    pub async fn exists<'e, E>(&self, user: &User, executor: E) -> Result<bool, ExistsError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let exists = DbUser::exists(&user.uuid, executor).await?;
 
         Ok(exists)
     }
 
-    pub async fn create(
+    pub async fn create<'e, E>(&self, user: &User, executor: E) -> Result<(), CreateError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let db_user = DbUser::from(user);
+        match db_user.create(executor).await {
+            Err(model::PersistError::AlreadyExists(constraint)) => match constraint.as_str() {
+                PRIMARY_KEY => Err(CreateError::AlreadyExists),
+                UNIQUE_SYSTEM_ROLE_INDEX => Err(CreateError::SystemUserAlreadyExists),
+                UNIQUE_USERNAME_INDEX => Err(CreateError::UsernameTaken),
+                _ => Err(model::PersistError::AlreadyExists(constraint).into()),
+            },
+            result => Ok(result?),
+        }
+    }
+
+    pub async fn load<'e, E>(
         &self,
-        user: &User,
+        identifier: &Uuid,
+        executor: E,
+    ) -> Result<Option<User>, LoadError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let db_user = match DbUser::load(identifier, executor).await {
+            Ok(db_user) => db_user,
+            Err(sqlx::Error::RowNotFound) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let user = User::try_from(&db_user)?;
+
+        Ok(Some(user))
+    }
+
+    /// Lists a page of the users matching the filter. Also returns the number of all matching users.
+    pub async fn list(
+        &self,
+        query: &ListQuery<UserFilter, UserSortColumn>,
         postgres_pool: &Pool<Postgres>,
-    ) -> Result<(), CreateError> {
-        if DbUser::exists(&user.uuid, postgres_pool).await? {
-            return Err(CreateError::AlreadyExists);
-        }
+    ) -> Result<(Listed<User>, i64), ListError> {
+        let total = DbUser::count(&query.filter, postgres_pool).await?;
+        let db_users = DbUser::list(query, postgres_pool).await?;
+        let users = db_users.try_map(User::try_from)?;
 
-        let db_user = DbUser::from(user);
-        db_user.create(postgres_pool).await?;
+        Ok((users, total))
+    }
 
-        Ok(())
+    pub async fn load_by_username<'e, E>(
+        &self,
+        username: &str,
+        executor: E,
+    ) -> Result<Option<User>, LoadError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "SELECT * FROM {} WHERE lower(username) = lower($1) AND {} IS NULL LIMIT 1",
+            DbUser::main_table_name(),
+            DbUser::DELETED_AT_COLUMN
+        );
+
+        let db_user: Option<DbUser> = sqlx::query_as(&query)
+            .bind(username::normalize(username))
+            .fetch_optional(executor)
+            .await?;
+
+        let user = db_user.map(User::try_from).transpose()?;
+        Ok(user)
     }
 
-    pub async fn load(
+    /// Authenticates a login by asking the authentication backends in the configured order.
+    ///
+    /// The user is the local user with the given username, if there is one. If an external identity logs in for the
+    /// first time, a user is created for it.
+    pub async fn authenticate(
         &self,
-        identifier: &Uuid,
+        username: &str,
+        password: &str,
+        user: Option<&User>,
+        audit_service: &AuditService,
         postgres_pool: &Pool<Postgres>,
-    ) -> Result<Option<User>, LoadError> {
-        if !DbUser::exists(identifier, postgres_pool).await? {
-            return Ok(None);
+    ) -> Result<Option<Authenticated>, AuthenticateError> {
+        // An unreachable backend must not prevent logins through the backends after it
+        let mut backend_error = None;
+
+        for auth_backend in &self.auth_backends {
+            let result = match auth_backend.authenticate(username, password, user).await {
+                Ok(result) => result,
+                Err(error) => {
+                    eprintln!(
+                        "The {} authentication backend returned an error: {}",
+                        auth_backend.name(),
+                        error
+                    );
+                    backend_error =
+                        Some(AuthenticateError::AuthBackend(auth_backend.name(), error));
+                    continue;
+                }
+            };
+
+            match result {
+                AuthResult::Rejected => continue,
+                AuthResult::Authenticated => return Ok(Some(Authenticated::Local)),
+                AuthResult::External(identity) => {
+                    let username = identity.username.clone();
+                    let Some(user) = self
+                        .resolve_external(identity, audit_service, postgres_pool)
+                        .await?
+                    else {
+                        // Telling the client would reveal that the local user exists
+                        eprintln!(
+                            "The {} authentication backend accepted the credentials of {}, but a local user already has that username",
+                            auth_backend.name(),
+                            username
+                        );
+                        continue;
+                    };
+                    return Ok(Some(Authenticated::External(user)));
+                }
+            }
         }
 
-        let db_user = DbUser::load(identifier, postgres_pool).await?;
-        let user = User::try_from(&db_user)?;
+        match backend_error {
+            Some(error) => Err(error),
+            None => Ok(None),
+        }
+    }
 
-        Ok(Some(user))
+    /// Loads the user the given identity of an external system is linked to.
+    pub async fn load_by_identity<'e, E>(
+        &self,
+        issuer: &str,
+        subject: &str,
+        executor: E,
+    ) -> Result<Option<User>, LoadError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "SELECT {users}.* FROM {users} JOIN {identities} ON {identities}.user_uuid = {users}.uuid WHERE {identities}.issuer = $1 AND {identities}.subject = $2 AND {users}.{deleted_at} IS NULL LIMIT 1",
+            users = DbUser::main_table_name(),
+            identities = DbUserIdentity::main_table_name(),
+            deleted_at = DbUser::DELETED_AT_COLUMN,
+        );
+
+        let db_user: Option<DbUser> = sqlx::query_as(&query)
+            .bind(issuer)
+            .bind(subject)
+            .fetch_optional(executor)
+            .await?;
+
+        let user = db_user.map(User::try_from).transpose()?;
+        Ok(user)
     }
 
-    pub async fn update(
+    /// Links the given identity of an external system to the user, so they can log in with it.
+    ///
+    /// Returns `false` if the identity is already linked to a user.
+    pub async fn link_identity<'e, E>(
         &self,
         user: &User,
+        issuer: &str,
+        subject: &str,
+        executor: E,
+    ) -> Result<bool, LinkIdentityError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let identity = UserIdentity {
+            uuid: Uuid::new_v4(),
+            user_uuid: user.uuid,
+            issuer: issuer.to_string(),
+            subject: subject.to_string(),
+            created_at: Utc::now(),
+        };
+
+        match DbUserIdentity::from(&identity).create(executor).await {
+            Ok(()) => Ok(true),
+            Err(model::PersistError::AlreadyExists(constraint))
+                if constraint == UNIQUE_SUBJECT_INDEX =>
+            {
+                Ok(false)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Returns the user of an external identity, creating a shadow user on its first login.
+    ///
+    /// If the external system manages roles, the role of the user is updated. The system user's role is never changed.
+    /// Returns `None` if the shadow user can't be created because a local user already has the username.
+    async fn resolve_external(
+        &self,
+        identity: ExternalIdentity,
+        audit_service: &AuditService,
         postgres_pool: &Pool<Postgres>,
-    ) -> Result<(), UpdateError> {
-        if !DbUser::exists(&user.uuid, postgres_pool).await? {
-            return Err(UpdateError::NotFound);
+    ) -> Result<Option<User>, AuthenticateError> {
+        let mut unit_of_work = UnitOfWork::begin(postgres_pool).await?;
+
+        let Some(mut user) = self
+            .load_by_identity(
+                &identity.issuer,
+                &identity.subject,
+                unit_of_work.connection(),
+            )
+            .await?
+        else {
+            // A user in the trash would keep the identity and the username from being taken by the new user
+            if let Some(deleted_user) = self
+                .purge_deleted_by_identity(
+                    &identity.issuer,
+                    &identity.subject,
+                    unit_of_work.connection(),
+                )
+                .await?
+            {
+                audit_service
+                    .record(
+                        AuditAction::UserPurged,
+                        AuditRecord {
+                            target: Some((AuditTargetType::User, deleted_user.uuid)),
+                            before: Some(AuditService::user_snapshot(&deleted_user)),
+                            ..Default::default()
+                        },
+                        unit_of_work.connection(),
+                    )
+                    .await?;
+
+                println!(
+                    "Purged user {} from the trash, because their identity {} of issuer {} logged in again",
+                    deleted_user.uuid, identity.subject, identity.issuer
+                );
+            }
+
+            let user = self
+                .generate_external(identity.username, identity.role.unwrap_or_default())
+                .await?;
+            match self.create(&user, unit_of_work.connection()).await {
+                Ok(()) => {}
+                Err(CreateError::UsernameTaken) => return Ok(None),
+                Err(error) => return Err(error.into()),
+            }
+
+            if !self
+                .link_identity(
+                    &user,
+                    &identity.issuer,
+                    &identity.subject,
+                    unit_of_work.connection(),
+                )
+                .await?
+            {
+                // Another login of the same identity created a user at the same time
+                return Err(AuthenticateError::ConcurrentLogin);
+            }
+
+            audit_service
+                .record(
+                    AuditAction::UserCreated,
+                    AuditRecord {
+                        actor_uuid: Some(user.uuid),
+                        target: Some((AuditTargetType::User, user.uuid)),
+                        after: Some(AuditService::user_snapshot(&user)),
+                        ..Default::default()
+                    },
+                    unit_of_work.connection(),
+                )
+                .await?;
+
+            unit_of_work.commit().await?;
+
+            println!(
+                "Created user {} for identity {} of issuer {}",
+                user.uuid, identity.subject, identity.issuer
+            );
+
+            return Ok(Some(user));
+        };
+
+        if let Some(role) = identity.role {
+            if user.role != Role::System && user.role != role {
+                println!(
+                    "Changing the role of user {} from {:?} to {:?} based on their external identity",
+                    user.uuid, user.role, role
+                );
+
+                let before = AuditService::user_snapshot(&user);
+                user.role = role;
+                self.update(&user, unit_of_work.connection()).await?;
+
+                audit_service
+                    .record(
+                        AuditAction::UserRoleChanged,
+                        AuditRecord {
+                            actor_uuid: Some(user.uuid),
+                            target: Some((AuditTargetType::User, user.uuid)),
+                            before: Some(before),
+                            after: Some(AuditService::user_snapshot(&user)),
+                            ..Default::default()
+                        },
+                        unit_of_work.connection(),
+                    )
+                    .await?;
+            }
         }
 
+        unit_of_work.commit().await?;
+
+        Ok(Some(user))
+    }
+
+    pub async fn update<'e, E>(&self, user: &User, executor: E) -> Result<(), UpdateError>
+    where
+        E: PgExecutor<'e>,
+    {
         let db_user = DbUser::from(user);
-        db_user.update(postgres_pool).await?;
+        let updated = match db_user.update(executor).await {
+            Ok(updated) => updated,
+            Err(model::PersistError::AlreadyExists(constraint))
+                if constraint == UNIQUE_USERNAME_INDEX =>
+            {
+                return Err(UpdateError::UsernameTaken);
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        if !updated {
+            return Err(UpdateError::NotFound);
+        }
 
         Ok(())
     }
 
+    /// Moves the user to the trash. They can't log in anymore, but can be restored until the trash is purged.
     pub async fn delete(
         &self,
         user: &User,
-        postgres_pool: &Pool<Postgres>,
+        deleted_by: &Uuid,
+        connection: &mut PgConnection,
     ) -> Result<(), DeleteError> {
-        if !DbUser::exists(&user.uuid, postgres_pool).await? {
+        if !DbUser::delete(&user.uuid, &mut *connection).await? {
             return Err(DeleteError::NotFound);
         }
 
-        DbUser::delete(&user.uuid, postgres_pool).await?;
+        self.set_deleted_by(&user.uuid, Some(deleted_by), connection)
+            .await?;
 
         Ok(())
     }
 
-    pub async fn persist(
+    /// Loads a user from the trash.
+    pub async fn load_deleted<'e, E>(
         &self,
-        user: User,
-        postgres_pool: &Pool<Postgres>,
-    ) -> Result<(), PersistError> {
-        let db_user = DbUser::from(&user);
-        db_user.persist(postgres_pool).await?;
+        identifier: &Uuid,
+        executor: E,
+    ) -> Result<Option<User>, LoadError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let db_user = match DbUser::load_deleted(identifier, executor).await {
+            Ok(db_user) => db_user,
+            Err(sqlx::Error::RowNotFound) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let user = User::try_from(&db_user)?;
+
+        Ok(Some(user))
+    }
+
+    /// Brings a user back from the trash.
+    pub async fn restore(
+        &self,
+        user: &User,
+        connection: &mut PgConnection,
+    ) -> Result<(), RestoreError> {
+        if !DbUser::restore(&user.uuid, &mut *connection).await? {
+            return Err(RestoreError::NotFound);
+        }
+
+        self.set_deleted_by(&user.uuid, None, connection).await?;
+
+        Ok(())
+    }
+
+    async fn set_deleted_by<'e, E>(
+        &self,
+        identifier: &Uuid,
+        deleted_by: Option<&Uuid>,
+        executor: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "UPDATE {} SET deleted_by = $2 WHERE uuid = $1",
+            DbUser::main_table_name()
+        );
+
+        sqlx::query(&query)
+            .bind(identifier)
+            .bind(deleted_by)
+            .execute(executor)
+            .await?;
 
         Ok(())
     }
+
+    /// Permanently deletes the user in the trash the given identity of an external system is linked to, if there is
+    /// one, and returns them.
+    ///
+    /// The identity logged in again, so it gets a new user. The user in the trash would keep both the identity and its
+    /// username reserved otherwise.
+    pub async fn purge_deleted_by_identity<'e, E>(
+        &self,
+        issuer: &str,
+        subject: &str,
+        executor: E,
+    ) -> Result<Option<User>, LoadError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let query = format!(
+            "DELETE FROM {users} WHERE {deleted_at} IS NOT NULL AND uuid IN (SELECT user_uuid FROM {identities} WHERE issuer = $1 AND subject = $2) RETURNING *",
+            users = DbUser::main_table_name(),
+            identities = DbUserIdentity::main_table_name(),
+            deleted_at = DbUser::DELETED_AT_COLUMN,
+        );
+
+        let db_user: Option<DbUser> = sqlx::query_as(&query)
+            .bind(issuer)
+            .bind(subject)
+            .fetch_optional(executor)
+            .await?;
+
+        let user = db_user.map(User::try_from).transpose()?;
+        Ok(user)
+    }
+
+    /// Deletes the user permanently, without moving them to the trash first.
+    ///
+    /// This is meant for users that were never handed out, like a user created for a login that failed afterwards.
+    pub async fn purge<'e, E>(&self, user: &User, executor: E) -> Result<(), DeleteError>
+    where
+        E: PgExecutor<'e>,
+    {
+        if !DbUser::purge(&user.uuid, executor).await? {
+            return Err(DeleteError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    pub async fn persist<'e, E>(&self, user: User, executor: E) -> Result<Persisted, PersistError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let db_user = DbUser::from(&user);
+        match db_user.persist(executor).await {
+            Err(model::PersistError::AlreadyExists(constraint))
+                if constraint == UNIQUE_SYSTEM_ROLE_INDEX =>
+            {
+                Err(PersistError::SystemUserAlreadyExists)
+            }
+            Err(model::PersistError::AlreadyExists(constraint))
+                if constraint == UNIQUE_USERNAME_INDEX =>
+            {
+                Err(PersistError::UsernameTaken)
+            }
+            Err(model::PersistError::Deleted) => Err(PersistError::Deleted),
+            result => Ok(result?),
+        }
+    }
 }
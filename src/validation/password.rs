@@ -0,0 +1,457 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    fs::File,
+    io::{self, BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use rocket::serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+
+const SHA1_HEX_LENGTH: usize = 40;
+
+/// The rules a password has to follow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasswordPolicy {
+    /// The minimum length of a password, in characters.
+    pub min_length: usize,
+
+    /// The maximum length of a password, in characters.
+    pub max_length: usize,
+
+    /// Whether a password has to contain at least one lowercase letter.
+    pub require_lowercase: bool,
+
+    /// Whether a password has to contain at least one uppercase letter.
+    pub require_uppercase: bool,
+
+    /// Whether a password has to contain at least one digit.
+    pub require_digit: bool,
+
+    /// Whether a password has to contain at least one character that is neither a letter nor a digit.
+    pub require_symbol: bool,
+
+    /// The minimum zxcvbn strength score, from 0 (too guessable) to 4 (very unguessable).
+    pub min_strength: u8,
+
+    /// The path to a file of SHA-1 hashes of breached passwords, one per line, sorted in ascending order.
+    /// Lines may be suffixed with a colon and an occurrence count, like in the Have I Been Pwned downloads.
+    pub breached_passwords_file: Option<String>,
+}
+
+// Composition rules are disabled by default, as they are discouraged by NIST and OWASP.
+// See: https://pages.nist.gov/800-63-4/sp800-63b.html#password
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 10,
+            max_length: 128,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            min_strength: 3,
+            breached_passwords_file: None,
+        }
+    }
+}
+
+/// A single rule of the password policy that a password violates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordViolation {
+    TooShort {
+        min_length: usize,
+    },
+    TooLong {
+        max_length: usize,
+    },
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    TooWeak {
+        score: u8,
+        min_score: u8,
+        warning: Option<String>,
+    },
+    Breached,
+}
+
+impl Display for PasswordViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort { min_length } => write!(
+                f,
+                "The password must be at least {} characters long",
+                min_length
+            ),
+            Self::TooLong { max_length } => write!(
+                f,
+                "The password must be at most {} characters long",
+                max_length
+            ),
+            Self::MissingLowercase => write!(f, "The password must contain a lowercase letter"),
+            Self::MissingUppercase => write!(f, "The password must contain an uppercase letter"),
+            Self::MissingDigit => write!(f, "The password must contain a digit"),
+            Self::MissingSymbol => write!(f, "The password must contain a symbol"),
+            Self::TooWeak {
+                score, min_score, ..
+            } => write!(
+                f,
+                "The password is too weak: It has a strength of {} but needs at least {}",
+                score, min_score
+            ),
+            Self::Breached => write!(f, "The password is known from a data breach"),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PasswordError {
+    #[error("The password violates the password policy: {}", join_violations(.0))]
+    PolicyViolation(Vec<PasswordViolation>),
+
+    #[error("Failed to check the password against the breached password list: {0}")]
+    BreachedPasswordCheck(#[from] io::Error),
+}
+
+fn join_violations(violations: &[PasswordViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Validates the given password against the given policy.
+///
+/// All violated rules are reported at once, so users can fix their password in one go.
+/// The user inputs, like the username, are considered guessable by the strength estimator.
+pub async fn validate(
+    password: &str,
+    user_inputs: &[&str],
+    policy: &PasswordPolicy,
+) -> Result<(), PasswordError> {
+    let mut violations = Vec::new();
+
+    let length = password.chars().count();
+    if length < policy.min_length.max(1) {
+        violations.push(PasswordViolation::TooShort {
+            min_length: policy.min_length.max(1),
+        });
+    }
+
+    // Very long passwords are rejected before anything expensive is done with them
+    if length > policy.max_length {
+        violations.push(PasswordViolation::TooLong {
+            max_length: policy.max_length,
+        });
+        return Err(PasswordError::PolicyViolation(violations));
+    }
+
+    if policy.require_lowercase && !password.chars().any(char::is_lowercase) {
+        violations.push(PasswordViolation::MissingLowercase);
+    }
+
+    if policy.require_uppercase && !password.chars().any(char::is_uppercase) {
+        violations.push(PasswordViolation::MissingUppercase);
+    }
+
+    if policy.require_digit && !password.chars().any(|character| character.is_numeric()) {
+        violations.push(PasswordViolation::MissingDigit);
+    }
+
+    if policy.require_symbol && password.chars().all(char::is_alphanumeric) {
+        violations.push(PasswordViolation::MissingSymbol);
+    }
+
+    let entropy = zxcvbn::zxcvbn(password, user_inputs);
+    let score = u8::from(entropy.score());
+    if score < policy.min_strength {
+        let warning = entropy
+            .feedback()
+            .and_then(|feedback| feedback.warning())
+            .map(|warning| warning.to_string());
+
+        violations.push(PasswordViolation::TooWeak {
+            score,
+            min_score: policy.min_strength,
+            warning,
+        });
+    }
+
+    if let Some(breached_passwords_file) = &policy.breached_passwords_file {
+        if is_breached(password, Path::new(breached_passwords_file)).await? {
+            violations.push(PasswordViolation::Breached);
+        }
+    }
+
+    if !violations.is_empty() {
+        return Err(PasswordError::PolicyViolation(violations));
+    }
+
+    Ok(())
+}
+
+/// Checks whether the SHA-1 hash of the given password is contained in the given sorted file.
+///
+/// The file is binary searched in place, so even the multi-gigabyte Have I Been Pwned list can be used without loading it into memory.
+/// The search runs on a blocking thread, so it doesn't stall other requests while it waits for the disk.
+pub async fn is_breached(password: &str, breached_passwords_file: &Path) -> io::Result<bool> {
+    let hash = Sha1::digest(password.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<String>();
+
+    let breached_passwords_file = PathBuf::from(breached_passwords_file);
+    tokio::task::spawn_blocking(move || contains_hash(&hash, &breached_passwords_file))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Binary searches the given sorted file for a line starting with the given uppercase hexadecimal SHA-1 hash.
+fn contains_hash(hash: &str, breached_passwords_file: &Path) -> io::Result<bool> {
+    let file = File::open(breached_passwords_file)?;
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+
+    // `low` always points to the start of a line that has not been ruled out yet
+    let mut low = 0;
+    let mut high = reader.get_ref().metadata()?.len();
+    while low < high {
+        let middle = low + (high - low) / 2;
+
+        // Find the start of the first line at or after the middle
+        let line_start = if middle == 0 {
+            0
+        } else {
+            reader.seek(SeekFrom::Start(middle - 1))?;
+            line.clear();
+            let skipped = reader.read_until(b'\n', &mut line)?;
+            middle - 1 + skipped as u64
+        };
+
+        if line_start >= high {
+            high = middle;
+            continue;
+        }
+
+        reader.seek(SeekFrom::Start(line_start))?;
+        line.clear();
+        let line_length = reader.read_until(b'\n', &mut line)?;
+
+        let line_hash = line
+            .get(..SHA1_HEX_LENGTH)
+            .unwrap_or(&line)
+            .to_ascii_uppercase();
+        match line_hash.as_slice().cmp(hash.as_bytes()) {
+            Ordering::Equal => return Ok(true),
+            Ordering::Less => low = line_start + line_length as u64,
+            Ordering::Greater => high = middle,
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BREACHED_PASSWORDS_FILE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/breached_passwords.txt"
+    );
+
+    const STRONG_PASSWORD: &str = "correct horse battery staple";
+
+    fn lenient_policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_strength: 0,
+            ..Default::default()
+        }
+    }
+
+    fn violations(result: Result<(), PasswordError>) -> Vec<PasswordViolation> {
+        match result {
+            Err(PasswordError::PolicyViolation(violations)) => violations,
+            result => panic!("Expected a policy violation, got {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_password_following_the_default_policy() {
+        let result = validate(STRONG_PASSWORD, &["alice"], &PasswordPolicy::default()).await;
+
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn enforces_length_limits() {
+        let policy = PasswordPolicy {
+            min_length: 12,
+            max_length: 16,
+            ..lenient_policy()
+        };
+
+        assert_eq!(
+            violations(validate("short", &[], &policy).await),
+            vec![PasswordViolation::TooShort { min_length: 12 }]
+        );
+        assert!(validate("exactly twelve", &[], &policy).await.is_ok());
+        assert_eq!(
+            violations(validate("much too long for this", &[], &policy).await),
+            vec![PasswordViolation::TooLong { max_length: 16 }]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_password_without_min_length() {
+        let policy = PasswordPolicy {
+            min_length: 0,
+            ..lenient_policy()
+        };
+
+        assert_eq!(
+            violations(validate("", &[], &policy).await),
+            vec![PasswordViolation::TooShort { min_length: 1 }]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_all_missing_character_classes() {
+        let policy = PasswordPolicy {
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: true,
+            ..lenient_policy()
+        };
+
+        assert_eq!(
+            violations(validate("abcdefghijkl", &[], &policy).await),
+            vec![
+                PasswordViolation::MissingUppercase,
+                PasswordViolation::MissingDigit,
+                PasswordViolation::MissingSymbol,
+            ]
+        );
+        assert_eq!(
+            violations(validate("ABCDEFGHIJKL", &[], &policy).await),
+            vec![
+                PasswordViolation::MissingLowercase,
+                PasswordViolation::MissingDigit,
+                PasswordViolation::MissingSymbol,
+            ]
+        );
+        assert!(validate("Abcdefghij1!", &[], &policy).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn enforces_min_strength() {
+        let policy = PasswordPolicy {
+            min_length: 1,
+            min_strength: 3,
+            ..Default::default()
+        };
+
+        let violations = violations(validate("password1", &[], &policy).await);
+        assert!(matches!(
+            violations.as_slice(),
+            [PasswordViolation::TooWeak { score, min_score: 3, .. }] if *score < 3
+        ));
+
+        assert!(validate(STRONG_PASSWORD, &[], &policy).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn considers_user_inputs_guessable() {
+        let policy = PasswordPolicy {
+            min_length: 1,
+            min_strength: 3,
+            ..Default::default()
+        };
+        let password = "wilhelmina.vandenberg";
+
+        assert!(validate(password, &[], &policy).await.is_ok());
+        assert!(matches!(
+            violations(validate(password, &["wilhelmina.vandenberg"], &policy).await).as_slice(),
+            [PasswordViolation::TooWeak { .. }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_breached_password() {
+        let policy = PasswordPolicy {
+            breached_passwords_file: Some(BREACHED_PASSWORDS_FILE.to_string()),
+            ..lenient_policy()
+        };
+
+        assert_eq!(
+            violations(validate("Tr0ub4dor&3", &[], &policy).await),
+            vec![PasswordViolation::Breached]
+        );
+        assert!(validate(STRONG_PASSWORD, &[], &policy).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn reports_missing_breached_passwords_file() {
+        let policy = PasswordPolicy {
+            breached_passwords_file: Some("/nonexistent/breached_passwords.txt".to_string()),
+            ..lenient_policy()
+        };
+
+        let result = validate(STRONG_PASSWORD, &[], &policy).await;
+
+        assert!(matches!(
+            result,
+            Err(PasswordError::BreachedPasswordCheck(_))
+        ));
+    }
+
+    #[test]
+    fn finds_every_hash_of_the_sorted_file() {
+        let file = Path::new(BREACHED_PASSWORDS_FILE);
+        let contents = std::fs::read_to_string(file).unwrap();
+
+        for line in contents.lines() {
+            let hash = &line[..SHA1_HEX_LENGTH];
+            assert!(contains_hash(hash, file).unwrap(), "{} not found", hash);
+        }
+    }
+
+    #[test]
+    fn does_not_find_missing_hashes() {
+        let file = Path::new(BREACHED_PASSWORDS_FILE);
+
+        // Before the first line, in between two lines and after the last line
+        for hash in [
+            "",
+            "0000000000000000000000000000000000000001",
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD9",
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFG",
+        ] {
+            assert!(!contains_hash(hash, file).unwrap(), "{} found", hash);
+        }
+    }
+
+    #[test]
+    fn handles_empty_and_single_line_files() {
+        let directory = std::env::temp_dir().join(format!("rasopus-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+
+        let empty = directory.join("empty.txt");
+        std::fs::write(&empty, "").unwrap();
+        assert!(!contains_hash("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8", &empty).unwrap());
+
+        // The last line has no line break and the hash is lowercase
+        let single_line = directory.join("single_line.txt");
+        std::fs::write(&single_line, "5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8:1").unwrap();
+        assert!(contains_hash("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8", &single_line).unwrap());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
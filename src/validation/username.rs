@@ -0,0 +1,187 @@
+use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{
+    GeneralSecurityProfile, RestrictionLevel, RestrictionLevelDetection,
+    is_potential_mixed_script_confusable_char,
+};
+
+/// The minimum length of a username, in characters.
+pub const MIN_USERNAME_LENGTH: usize = 3;
+
+/// The maximum length of a username, in characters.
+/// Even with 4 bytes per character, this stays well below the 255 bytes of the username column.
+pub const MAX_USERNAME_LENGTH: usize = 32;
+
+/// The characters that are allowed in between letters and digits.
+pub const USERNAME_SEPARATORS: [char; 3] = ['_', '-', '.'];
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UsernameError {
+    #[error("The username must be at least {MIN_USERNAME_LENGTH} characters long")]
+    TooShort,
+
+    #[error("The username must be at most {MAX_USERNAME_LENGTH} characters long")]
+    TooLong,
+
+    #[error(
+        "The username contains the character {0:?}, but only letters, digits, '_', '-' and '.' are allowed"
+    )]
+    InvalidCharacter(char),
+
+    #[error("The username must start and end with a letter or digit")]
+    InvalidBoundary,
+
+    #[error("The username mixes characters of scripts that are not commonly used together")]
+    MixedScript,
+
+    #[error(
+        "The username consists only of characters that can be confused with characters of other scripts"
+    )]
+    Confusable,
+}
+
+/// Normalizes the given username to Unicode NFKC, without validating it.
+pub fn normalize(username: &str) -> String {
+    username.nfkc().collect()
+}
+
+/// Returns the canonical form of the given username, which is equal for all usernames that are considered the same.
+pub fn canonicalize(username: &str) -> String {
+    normalize(username).to_lowercase()
+}
+
+/// Validates the given username and returns its normalized form, which is the form that should be stored.
+///
+/// Usernames are normalized to Unicode NFKC, so that visually identical usernames are also stored identically.
+/// Case is preserved, but uniqueness is checked case-insensitively by the database.
+pub fn validate(username: &str) -> Result<String, UsernameError> {
+    let normalized = normalize(username);
+
+    let length = normalized.chars().count();
+    if length < MIN_USERNAME_LENGTH {
+        return Err(UsernameError::TooShort);
+    }
+
+    if length > MAX_USERNAME_LENGTH {
+        return Err(UsernameError::TooLong);
+    }
+
+    for character in normalized.chars() {
+        if USERNAME_SEPARATORS.contains(&character) {
+            continue;
+        }
+
+        if !character.is_alphanumeric() || !character.identifier_allowed() {
+            return Err(UsernameError::InvalidCharacter(character));
+        }
+    }
+
+    let starts_with_separator = normalized.starts_with(USERNAME_SEPARATORS);
+    let ends_with_separator = normalized.ends_with(USERNAME_SEPARATORS);
+    if starts_with_separator || ends_with_separator {
+        return Err(UsernameError::InvalidBoundary);
+    }
+
+    if normalized.is_ascii() {
+        return Ok(normalized);
+    }
+
+    if !normalized
+        .as_str()
+        .check_restriction_level(RestrictionLevel::HighlyRestrictive)
+    {
+        return Err(UsernameError::MixedScript);
+    }
+
+    // A username like "сосо" written entirely in Cyrillic would otherwise look exactly like the Latin "coco"
+    let is_whole_script_confusable = normalized
+        .chars()
+        .filter(|character| !character.is_ascii())
+        .all(is_potential_mixed_script_confusable_char);
+    if is_whole_script_confusable {
+        return Err(UsernameError::Confusable);
+    }
+
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_usernames() {
+        assert_eq!(validate("alice"), Ok("alice".to_string()));
+        assert_eq!(
+            validate("Alice_Smith-1.0"),
+            Ok("Alice_Smith-1.0".to_string())
+        );
+        assert_eq!(validate("jürgen"), Ok("jürgen".to_string()));
+        assert_eq!(validate("ユーザー"), Ok("ユーザー".to_string()));
+    }
+
+    #[test]
+    fn normalizes_to_nfkc() {
+        // The fullwidth letters and the decomposed "ü" are stored in their NFKC form
+        assert_eq!(validate("ａｌｉｃｅ"), Ok("alice".to_string()));
+        assert_eq!(validate("ju\u{308}rgen"), Ok("jürgen".to_string()));
+    }
+
+    #[test]
+    fn rejects_invalid_lengths() {
+        assert_eq!(validate("ab"), Err(UsernameError::TooShort));
+        assert!(validate(&"a".repeat(MIN_USERNAME_LENGTH)).is_ok());
+        assert!(validate(&"a".repeat(MAX_USERNAME_LENGTH)).is_ok());
+        assert_eq!(
+            validate(&"a".repeat(MAX_USERNAME_LENGTH + 1)),
+            Err(UsernameError::TooLong)
+        );
+    }
+
+    #[test]
+    fn counts_length_in_characters() {
+        assert!(validate(&"ü".repeat(MAX_USERNAME_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_characters() {
+        assert_eq!(
+            validate("alice smith"),
+            Err(UsernameError::InvalidCharacter(' '))
+        );
+        assert_eq!(
+            validate("alice@example"),
+            Err(UsernameError::InvalidCharacter('@'))
+        );
+        assert_eq!(
+            validate("alice\u{200b}"),
+            Err(UsernameError::InvalidCharacter('\u{200b}'))
+        );
+    }
+
+    #[test]
+    fn rejects_separators_at_boundaries() {
+        assert_eq!(validate("_alice"), Err(UsernameError::InvalidBoundary));
+        assert_eq!(validate("alice."), Err(UsernameError::InvalidBoundary));
+        assert_eq!(validate("-alice-"), Err(UsernameError::InvalidBoundary));
+    }
+
+    #[test]
+    fn rejects_mixed_scripts() {
+        // "а" is the Cyrillic small letter a
+        assert_eq!(validate("аlice"), Err(UsernameError::MixedScript));
+    }
+
+    #[test]
+    fn rejects_whole_script_confusables() {
+        // Written entirely in Cyrillic, this looks exactly like the Latin "coco"
+        assert_eq!(validate("сосо"), Err(UsernameError::Confusable));
+    }
+
+    #[test]
+    fn canonicalizes_case_and_normalization() {
+        assert_eq!(canonicalize("Alice"), canonicalize("aLiCe"));
+        assert_eq!(canonicalize("ＡＬＩＣＥ"), "alice");
+        assert_eq!(canonicalize("Ju\u{308}rgen"), canonicalize("jürgen"));
+    }
+}
@@ -0,0 +1,140 @@
+//! Records changes and logins in the audit log.
+
+mod common;
+
+use std::net::SocketAddr;
+
+use common::TestDatabase;
+use rasopus::{
+    config::user_service::UserServiceConfig,
+    model::entity::user::{Role, User},
+    service::UserService,
+};
+use rocket::{
+    http::{ContentType, Header, Status},
+    local::asynchronous::Client,
+    serde::json::{Value, serde_json},
+};
+use uuid::Uuid;
+
+const PASSWORD: &str = "meadow lantern pebble";
+
+async fn setup(ip_header: Option<&str>) -> Option<(TestDatabase, Client, User, User)> {
+    let database = TestDatabase::create().await?;
+    let config = common::config(serde_json::json!({ "ip_header": ip_header }));
+
+    let user_service = UserService::new(UserServiceConfig::from(&config)).unwrap();
+    let create = async |username: &str, role: Role| {
+        let user = user_service
+            .generate(username.to_string(), PASSWORD, role)
+            .await
+            .unwrap();
+        user_service.create(&user, &database.pool).await.unwrap();
+        user
+    };
+    let alice = create("alice", Role::Admin).await;
+    let bob = create("bob", Role::User).await;
+
+    let client = common::client(&config, &database).await;
+
+    Some((database, client, alice, bob))
+}
+
+/// Logs in from 192.0.2.1, claiming to come from 198.51.100.1 in `X-Real-IP`.
+async fn login(client: &Client, username: &str) {
+    let remote: SocketAddr = "192.0.2.1:40000".parse().unwrap();
+    let response = client
+        .post("/login")
+        .remote(remote)
+        .header(ContentType::JSON)
+        .header(Header::new("X-Real-IP", "198.51.100.1"))
+        .body(serde_json::json!({ "username": username, "password": PASSWORD }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+}
+
+async fn login_ip_address(database: &TestDatabase) -> Option<String> {
+    sqlx::query_scalar("SELECT ip_address FROM audit_events WHERE action = 'login.succeeded'")
+        .fetch_one(&database.pool)
+        .await
+        .unwrap()
+}
+
+async fn put_role(client: &Client, user: &User, role: &str) -> Status {
+    client
+        .put(format!("/users/{}/role", user.uuid))
+        .header(ContentType::JSON)
+        .body(serde_json::json!({ "role": role }).to_string())
+        .dispatch()
+        .await
+        .status()
+}
+
+#[rocket::async_test]
+async fn records_address_of_connection() {
+    let Some((database, client, ..)) = setup(None).await else {
+        return;
+    };
+
+    login(&client, "alice").await;
+    assert_eq!(
+        login_ip_address(&database).await,
+        Some("192.0.2.1".to_string())
+    );
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn records_address_from_header_of_trusted_proxy() {
+    let Some((database, client, ..)) = setup(Some("X-Real-IP")).await else {
+        return;
+    };
+
+    login(&client, "alice").await;
+    assert_eq!(
+        login_ip_address(&database).await,
+        Some("198.51.100.1".to_string())
+    );
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn records_role_changed_by_admin() {
+    let Some((database, client, alice, bob)) = setup(None).await else {
+        return;
+    };
+
+    login(&client, "alice").await;
+    assert_eq!(put_role(&client, &bob, "Admin").await, Status::Ok);
+
+    let role: i16 = sqlx::query_scalar("SELECT role FROM users WHERE uuid = $1")
+        .bind(bob.uuid)
+        .fetch_one(&database.pool)
+        .await
+        .unwrap();
+    assert_eq!(role, Role::Admin as i16);
+
+    let changes: Vec<(Uuid, Uuid, Value, Value)> = sqlx::query_as(
+        "SELECT actor_uuid, target_uuid, before->'role', after->'role' FROM audit_events WHERE action = 'user.role_changed'",
+    )
+    .fetch_all(&database.pool)
+    .await
+    .unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!((changes[0].0, changes[0].1), (alice.uuid, bob.uuid));
+    assert_ne!(changes[0].2, changes[0].3);
+
+    assert_eq!(
+        put_role(&client, &bob, "System").await,
+        Status::UnprocessableEntity
+    );
+    assert_eq!(put_role(&client, &alice, "User").await, Status::Conflict);
+
+    drop(client);
+    database.drop().await;
+}
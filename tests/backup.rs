@@ -0,0 +1,252 @@
+//! Backs up a database and restores the archive into another one.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use common::TestDatabase;
+use rasopus::{
+    backup::{self, BackupError, RestoreError},
+    config::user_service::UserServiceConfig,
+    database,
+    model::entity::user::Role,
+    service::UserService,
+};
+use rocket::{
+    http::{ContentType, Status},
+    serde::json::{Value, serde_json},
+};
+use uuid::Uuid;
+
+const PASSWORD: &str = "meadow lantern pebble";
+
+/// The tables a backup holds. Sessions are left out of backups.
+const TABLES: [&str; 7] = [
+    "users",
+    "user_identities",
+    "user_totps",
+    "recovery_codes",
+    "account_lockouts",
+    "login_attempts",
+    "audit_events",
+];
+
+/// An archive path in the temporary directory, which is removed again when dropped.
+struct ArchivePath(PathBuf);
+
+impl ArchivePath {
+    fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("rasopus-backup-{}.ndjson", Uuid::new_v4())))
+    }
+}
+
+impl Drop for ArchivePath {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+/// A directory in the temporary directory, which is removed again with its contents when dropped.
+struct ArchiveDirectory(PathBuf);
+
+impl ArchiveDirectory {
+    fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("rasopus-backups-{}", Uuid::new_v4()));
+        std::fs::create_dir(&path).unwrap();
+        Self(path)
+    }
+
+    fn file_names(&self) -> Vec<String> {
+        std::fs::read_dir(&self.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+}
+
+impl Drop for ArchiveDirectory {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// Creates a database with users, login attempts, audit events and a user in the trash.
+async fn populated_database() -> Option<TestDatabase> {
+    let database = TestDatabase::create().await?;
+    let config = common::config(serde_json::json!({}));
+
+    let user_service = UserService::new(UserServiceConfig::from(&config)).unwrap();
+    let user = user_service
+        .generate("alice".to_string(), PASSWORD, Role::Admin)
+        .await
+        .unwrap();
+    user_service.create(&user, &database.pool).await.unwrap();
+
+    // The client has to be dropped before the database, so it is confined to this block
+    {
+        let client = common::client(&config, &database).await;
+        let response = client
+            .post("/login")
+            .header(ContentType::JSON)
+            .body(serde_json::json!({ "username": "alice", "password": PASSWORD }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .post("/users")
+            .header(ContentType::JSON)
+            .body(
+                serde_json::json!({ "username": "bob", "password": PASSWORD, "role": "User" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let bob: Value = response.into_json().await.unwrap();
+
+        let response = client
+            .delete(format!("/users/{}", bob["uuid"].as_str().unwrap()))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    Some(database)
+}
+
+/// The rows of all backed up tables, in a stable order.
+async fn contents(database: &TestDatabase) -> Vec<Value> {
+    let mut contents = Vec::new();
+    for table in TABLES {
+        let query = format!(
+            "SELECT COALESCE(jsonb_agg(to_jsonb({table}) ORDER BY to_jsonb({table})::TEXT), '[]') FROM {table}"
+        );
+        let rows: Value = sqlx::query_scalar(&query)
+            .fetch_one(&database.pool)
+            .await
+            .unwrap();
+        contents.push(rows);
+    }
+
+    contents
+}
+
+async fn create_backup(database: &TestDatabase) -> (ArchivePath, u64) {
+    let path = ArchivePath::new();
+    let rows = backup::backup(&database.pool, &database::MIGRATOR, &path.0)
+        .await
+        .unwrap();
+
+    (path, rows)
+}
+
+async fn restore(database: &TestDatabase, path: &Path) -> Result<u64, RestoreError> {
+    backup::restore(&database.pool, &database::MIGRATOR, path).await
+}
+
+#[rocket::async_test]
+async fn restores_backup_into_empty_database() {
+    let Some(source) = populated_database().await else {
+        return;
+    };
+    let (path, rows) = create_backup(&source).await;
+    assert!(rows > 0);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(&path.0).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let target = TestDatabase::create_unmigrated().await.unwrap();
+    assert_eq!(restore(&target, &path.0).await.unwrap(), rows);
+    assert_eq!(contents(&target).await, contents(&source).await);
+
+    let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions")
+        .fetch_one(&target.pool)
+        .await
+        .unwrap();
+    assert_eq!(sessions, 0);
+
+    source.drop().await;
+    target.drop().await;
+}
+
+#[rocket::async_test]
+async fn leaves_database_with_data_unchanged() {
+    let Some(source) = populated_database().await else {
+        return;
+    };
+    let (path, _) = create_backup(&source).await;
+    let contents_before = contents(&source).await;
+
+    assert!(matches!(
+        restore(&source, &path.0).await,
+        Err(RestoreError::NotEmpty("users"))
+    ));
+    assert_eq!(contents(&source).await, contents_before);
+
+    source.drop().await;
+}
+
+#[rocket::async_test]
+async fn rolls_back_migrations_when_archive_is_invalid() {
+    let Some(source) = populated_database().await else {
+        return;
+    };
+    let (path, _) = create_backup(&source).await;
+
+    // Drop the second row, so the archive doesn't match its checksum anymore
+    let archive = std::fs::read_to_string(&path.0).unwrap();
+    let mut lines: Vec<&str> = archive.lines().collect();
+    lines.remove(2);
+    std::fs::write(&path.0, lines.join("\n") + "\n").unwrap();
+
+    let target = TestDatabase::create_unmigrated().await.unwrap();
+    assert!(matches!(
+        restore(&target, &path.0).await,
+        Err(RestoreError::ChecksumMismatch)
+    ));
+
+    let tables: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM pg_tables WHERE schemaname = current_schema()")
+            .fetch_one(&target.pool)
+            .await
+            .unwrap();
+    assert_eq!(tables, 0);
+
+    source.drop().await;
+    target.drop().await;
+}
+
+#[rocket::async_test]
+async fn leaves_no_partial_archive_behind() {
+    let Some(source) = populated_database().await else {
+        return;
+    };
+    let directory = ArchiveDirectory::new();
+
+    let path = directory.0.join("rasopus.ndjson");
+    backup::backup(&source.pool, &database::MIGRATOR, &path)
+        .await
+        .unwrap();
+    assert_eq!(directory.file_names(), vec!["rasopus.ndjson"]);
+
+    // A directory in the way makes the backup fail after the whole archive has been written
+    let path = directory.0.join("blocked.ndjson");
+    std::fs::create_dir(&path).unwrap();
+    std::fs::write(path.join("keep"), "").unwrap();
+    assert!(matches!(
+        backup::backup(&source.pool, &database::MIGRATOR, &path).await,
+        Err(BackupError::Io(_))
+    ));
+
+    let mut file_names = directory.file_names();
+    file_names.sort();
+    assert_eq!(file_names, vec!["blocked.ndjson", "rasopus.ndjson"]);
+
+    source.drop().await;
+}
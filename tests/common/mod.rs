@@ -0,0 +1,137 @@
+//! Helpers shared by the integration tests.
+//!
+//! The integration tests need a Postgres server. They connect to the one in `DATABASE_URL` and create a database of
+//! their own on it, which is dropped again afterwards. Without `DATABASE_URL`, they are skipped.
+
+#![allow(dead_code)]
+
+use std::str::FromStr;
+
+use rasopus::{
+    build_rocket,
+    config::{rasopus::RasopusConfig, rocket::RocketConfig},
+    database,
+    service::ServiceCollection,
+};
+use rocket::{
+    local::asynchronous::Client,
+    serde::json::{Value, serde_json},
+};
+use sqlx::{
+    ConnectOptions, Connection, PgConnection, Pool, Postgres,
+    postgres::{PgConnectOptions, PgPoolOptions},
+};
+use uuid::Uuid;
+
+/// A database that only exists for the duration of one test.
+pub struct TestDatabase {
+    pub pool: Pool<Postgres>,
+    name: String,
+    admin_options: PgConnectOptions,
+}
+
+impl TestDatabase {
+    /// Creates an empty database and applies all migrations to it.
+    ///
+    /// Returns `None` if `DATABASE_URL` is not set, in which case the test should be skipped.
+    pub async fn create() -> Option<Self> {
+        let database = Self::create_unmigrated().await?;
+        database::MIGRATOR
+            .run(&database.pool)
+            .await
+            .expect("Migrating the test database should succeed");
+
+        Some(database)
+    }
+
+    /// Creates an empty database without applying any migrations to it.
+    ///
+    /// Returns `None` if `DATABASE_URL` is not set, in which case the test should be skipped.
+    pub async fn create_unmigrated() -> Option<Self> {
+        let Ok(database_url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL is not set, skipping the test");
+            return None;
+        };
+
+        let admin_options = PgConnectOptions::from_str(&database_url)
+            .expect("DATABASE_URL should be a valid Postgres URL");
+        let name = format!("rasopus_test_{}", Uuid::new_v4().simple());
+
+        let mut admin_connection = admin_options
+            .connect()
+            .await
+            .expect("Connecting to DATABASE_URL should succeed");
+        sqlx::query(&format!("CREATE DATABASE {name}"))
+            .execute(&mut admin_connection)
+            .await
+            .expect("Creating the test database should succeed");
+        admin_connection.close().await.ok();
+
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(admin_options.clone().database(&name))
+            .await
+            .expect("Connecting to the test database should succeed");
+
+        Some(Self {
+            pool,
+            name,
+            admin_options,
+        })
+    }
+
+    /// Drops the database again.
+    pub async fn drop(self) {
+        self.pool.close().await;
+
+        let mut admin_connection: PgConnection = self
+            .admin_options
+            .connect()
+            .await
+            .expect("Connecting to DATABASE_URL should succeed");
+        sqlx::query(&format!(
+            "DROP DATABASE IF EXISTS {} WITH (FORCE)",
+            self.name
+        ))
+        .execute(&mut admin_connection)
+        .await
+        .expect("Dropping the test database should succeed");
+    }
+}
+
+/// A configuration with cheap password hashing, extended by the given settings.
+///
+/// The settings are named like the fields of `RasopusConfig`, for example `oidc_issuer_url`.
+pub fn config(settings: Value) -> RasopusConfig {
+    let mut config = serde_json::json!({
+        "secret_key": "pnUM94/8LhhtSE1REEihKj7npG6+lvigAy4C885fIzY=",
+        "postgres_user": "rasopus",
+        "postgres_password": "rasopus",
+        "postgres_host": "localhost",
+        "postgres_port": 5432,
+        "postgres_database": "rasopus",
+        "argon2_iterations": 3,
+        "argon2_memory_mib": 8,
+    });
+
+    if let (Some(config), Value::Object(settings)) = (config.as_object_mut(), settings) {
+        config.extend(settings);
+    }
+
+    serde_json::from_value(config).expect("The test configuration should be valid")
+}
+
+/// Builds Rasopus with the given configuration on top of the test database.
+pub async fn client(config: &RasopusConfig, database: &TestDatabase) -> Client {
+    let service_collection =
+        ServiceCollection::new(config).expect("The services should initialize");
+    let rocket = build_rocket(
+        RocketConfig::from(config),
+        database.pool.clone(),
+        service_collection,
+    );
+
+    Client::tracked(rocket)
+        .await
+        .expect("Rasopus should be a valid Rocket instance")
+}
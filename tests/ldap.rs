@@ -0,0 +1,385 @@
+//! Logs in through a stub LDAP directory that understands just enough of the protocol for binds and searches.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use bytes::BytesMut;
+use common::TestDatabase;
+use lber::{
+    common::TagClass,
+    parse::parse_tag,
+    structure::{PL, StructureTag},
+    structures::{ASNTag, Enumerated, Integer},
+    write::encode_into,
+};
+use rasopus::{
+    config::user_service::UserServiceConfig, model::entity::user::Role, service::UserService,
+};
+use rocket::{
+    http::{ContentType, Status},
+    local::asynchronous::Client,
+    serde::json::{Value, serde_json},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+const PEOPLE_DN: &str = "ou=people,dc=example,dc=com";
+const GROUPS_DN: &str = "ou=groups,dc=example,dc=com";
+
+const BIND_REQUEST: u64 = 0;
+const BIND_RESPONSE: u64 = 1;
+const UNBIND_REQUEST: u64 = 2;
+const SEARCH_REQUEST: u64 = 3;
+const SEARCH_RESULT_ENTRY: u64 = 4;
+const SEARCH_RESULT_DONE: u64 = 5;
+
+const SUCCESS: i64 = 0;
+const INVALID_CREDENTIALS: i64 = 49;
+const BASE_OBJECT_SCOPE: i64 = 0;
+
+struct DirectoryUser {
+    uid: String,
+    password: String,
+    groups: Vec<String>,
+}
+
+/// The users of the stub directory. Their DN is their `uid` under `ou=people`.
+#[derive(Clone, Default)]
+struct Directory(Arc<Mutex<Vec<DirectoryUser>>>);
+
+impl Directory {
+    fn add(&self, uid: &str, password: &str, groups: &[&str]) {
+        self.0.lock().unwrap().push(DirectoryUser {
+            uid: uid.to_string(),
+            password: password.to_string(),
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+        });
+    }
+
+    fn set_groups(&self, uid: &str, groups: &[&str]) {
+        let mut users = self.0.lock().unwrap();
+        let user = users.iter_mut().find(|user| user.uid == uid).unwrap();
+        user.groups = groups.iter().map(|group| group.to_string()).collect();
+    }
+
+    /// Returns the uid of the user with the given DN if the password is theirs.
+    fn bind(&self, dn: &str, password: &str) -> Option<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|user| user_dn(&user.uid).eq_ignore_ascii_case(dn) && user.password == password)
+            .map(|user| user.uid.clone())
+    }
+
+    fn groups(&self, uid: &str) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|user| user.uid == uid)
+            .map(|user| user.groups.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn user_dn(uid: &str) -> String {
+    format!("uid={uid},{PEOPLE_DN}")
+}
+
+fn octet_string(value: &str) -> StructureTag {
+    StructureTag {
+        class: TagClass::Universal,
+        id: 4,
+        payload: PL::P(value.as_bytes().to_vec()),
+    }
+}
+
+fn constructed(class: TagClass, id: u64, inner: Vec<StructureTag>) -> StructureTag {
+    StructureTag {
+        class,
+        id,
+        payload: PL::C(inner),
+    }
+}
+
+fn message(message_id: i64, operation: StructureTag) -> StructureTag {
+    let message_id = Integer {
+        id: 2,
+        class: TagClass::Universal,
+        inner: message_id,
+    };
+
+    constructed(
+        TagClass::Universal,
+        16,
+        vec![message_id.into_structure(), operation],
+    )
+}
+
+fn result(operation: u64, result_code: i64) -> StructureTag {
+    let result_code = Enumerated {
+        id: 10,
+        class: TagClass::Universal,
+        inner: result_code,
+    };
+
+    constructed(
+        TagClass::Application,
+        operation,
+        vec![
+            result_code.into_structure(),
+            octet_string(""),
+            octet_string(""),
+        ],
+    )
+}
+
+/// An entry with a single attribute, which is all the backend asks for.
+fn entry(dn: &str, attribute: &str, value: &str) -> StructureTag {
+    let values = constructed(TagClass::Universal, 17, vec![octet_string(value)]);
+    let attribute = constructed(
+        TagClass::Universal,
+        16,
+        vec![octet_string(attribute), values],
+    );
+
+    constructed(
+        TagClass::Application,
+        SEARCH_RESULT_ENTRY,
+        vec![
+            octet_string(dn),
+            constructed(TagClass::Universal, 16, vec![attribute]),
+        ],
+    )
+}
+
+fn integer(tag: StructureTag) -> i64 {
+    let bytes = tag.expect_primitive().unwrap();
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | byte as i64)
+}
+
+fn string(tag: StructureTag) -> String {
+    String::from_utf8(tag.expect_primitive().unwrap()).unwrap()
+}
+
+/// Answers the requests of one connection until it is unbound or closed.
+async fn serve(directory: Directory, mut stream: TcpStream) {
+    let mut buffer = Vec::new();
+    let mut bound_uid = None;
+
+    loop {
+        let Ok((rest, request)) = parse_tag(&buffer) else {
+            let mut chunk = [0u8; 4096];
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+            }
+            continue;
+        };
+        buffer.drain(..buffer.len() - rest.len());
+
+        let mut parts = request.expect_constructed().unwrap().into_iter();
+        let message_id = integer(parts.next().unwrap());
+        let operation = parts.next().unwrap();
+
+        let mut responses = Vec::new();
+        match operation.id {
+            BIND_REQUEST => {
+                let mut fields = operation.expect_constructed().unwrap().into_iter().skip(1);
+                let dn = string(fields.next().unwrap());
+                let password = string(fields.next().unwrap());
+
+                bound_uid = directory.bind(&dn, &password);
+                let result_code = match bound_uid {
+                    Some(_) => SUCCESS,
+                    None => INVALID_CREDENTIALS,
+                };
+                responses.push(result(BIND_RESPONSE, result_code));
+            }
+            SEARCH_REQUEST => {
+                let mut fields = operation.expect_constructed().unwrap().into_iter();
+                let base_dn = string(fields.next().unwrap());
+                let scope = integer(fields.next().unwrap());
+
+                // The backend only reads the entry of the bound user and searches their groups
+                if let Some(uid) = &bound_uid {
+                    if scope == BASE_OBJECT_SCOPE {
+                        responses.push(entry(&base_dn, "uid", uid));
+                    } else {
+                        for group in directory.groups(uid) {
+                            responses.push(entry(&format!("cn={group},{GROUPS_DN}"), "cn", &group));
+                        }
+                    }
+                }
+                responses.push(result(SEARCH_RESULT_DONE, SUCCESS));
+            }
+            UNBIND_REQUEST => return,
+            operation => panic!("The stub directory doesn't support operation {operation}"),
+        }
+
+        let mut output = BytesMut::new();
+        for response in responses {
+            encode_into(&mut output, message(message_id, response)).unwrap();
+        }
+        if stream.write_all(&output).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Starts the stub directory on a free port and returns its URL.
+async fn start_stub_directory(directory: Directory) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ldap://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(directory.clone(), stream));
+        }
+    });
+
+    url
+}
+
+async fn setup() -> Option<(TestDatabase, Client, Directory)> {
+    let database = TestDatabase::create().await?;
+    let directory = Directory::default();
+    let url = start_stub_directory(directory.clone()).await;
+    let config = common::config(serde_json::json!({
+        "ldap_url": url,
+        "ldap_user_dn_template": format!("uid={{username}},{PEOPLE_DN}"),
+        "ldap_group_base_dn": GROUPS_DN,
+        "ldap_admin_groups": "admins",
+    }));
+
+    // A local user, who isn't in the directory
+    let user_service = UserService::new(UserServiceConfig::from(&config)).unwrap();
+    let user = user_service
+        .generate("dave".to_string(), "local pumpkin harbor", Role::User)
+        .await
+        .unwrap();
+    user_service.create(&user, &database.pool).await.unwrap();
+
+    let client = common::client(&config, &database).await;
+
+    Some((database, client, directory))
+}
+
+async fn login(client: &Client, username: &str, password: &str) -> (Status, Value) {
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(serde_json::json!({ "username": username, "password": password }).to_string())
+        .dispatch()
+        .await;
+    let status = response.status();
+    let body = response.into_json::<Value>().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+async fn users(database: &TestDatabase) -> Vec<(String, i16)> {
+    sqlx::query_as("SELECT username, role FROM users ORDER BY username")
+        .fetch_all(&database.pool)
+        .await
+        .unwrap()
+}
+
+#[rocket::async_test]
+async fn creates_user_with_role_of_groups_and_updates_it() {
+    let Some((database, client, directory)) = setup().await else {
+        return;
+    };
+    directory.add("erin", "directory kettle meadow", &["admins"]);
+
+    let (status, _) = login(&client, "erin", "directory kettle meadow").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(
+        users(&database).await,
+        vec![
+            ("dave".to_string(), Role::User as i16),
+            ("erin".to_string(), Role::Admin as i16),
+        ]
+    );
+
+    client.post("/logout").dispatch().await;
+    directory.set_groups("erin", &[]);
+    let (status, _) = login(&client, "erin", "directory kettle meadow").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(
+        users(&database).await[1],
+        ("erin".to_string(), Role::User as i16)
+    );
+
+    let (status, _) = login(&client, "erin", "wrong password").await;
+    assert_eq!(status, Status::Unauthorized);
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn falls_back_to_local_passwords() {
+    let Some((database, client, _)) = setup().await else {
+        return;
+    };
+
+    let (status, _) = login(&client, "dave", "local pumpkin harbor").await;
+    assert_eq!(status, Status::Ok);
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn does_not_reveal_local_users_with_the_username() {
+    let Some((database, client, directory)) = setup().await else {
+        return;
+    };
+    directory.add("dave", "directory kettle meadow", &[]);
+
+    // The directory accepts the password, but the username belongs to a local user with another password
+    let (status, body) = login(&client, "dave", "directory kettle meadow").await;
+    assert_eq!(status, Status::Unauthorized);
+    assert_eq!(body, serde_json::json!("InvalidCredentials"));
+    assert_eq!(users(&database).await.len(), 1);
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn replaces_user_in_trash_when_directory_user_logs_in_again() {
+    let Some((database, client, directory)) = setup().await else {
+        return;
+    };
+    directory.add("erin", "directory kettle meadow", &[]);
+
+    let (status, _) = login(&client, "erin", "directory kettle meadow").await;
+    assert_eq!(status, Status::Ok);
+    sqlx::query("UPDATE users SET deleted_at = now() WHERE username = 'erin'")
+        .execute(&database.pool)
+        .await
+        .unwrap();
+
+    client.post("/logout").dispatch().await;
+    let (status, _) = login(&client, "erin", "directory kettle meadow").await;
+    assert_eq!(status, Status::Ok);
+
+    let deleted: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE deleted_at IS NOT NULL")
+            .fetch_one(&database.pool)
+            .await
+            .unwrap();
+    assert_eq!(deleted, 0);
+    assert_eq!(users(&database).await.len(), 2);
+
+    drop(client);
+    database.drop().await;
+}
@@ -0,0 +1,85 @@
+//! Throttles failed logins by the IP address of the client.
+
+mod common;
+
+use std::net::SocketAddr;
+
+use common::TestDatabase;
+use rocket::{
+    http::{ContentType, Header, Status},
+    local::asynchronous::Client,
+    serde::json::serde_json,
+};
+
+const IP_THRESHOLD: u32 = 3;
+
+async fn setup(ip_header: Option<&str>) -> Option<(TestDatabase, Client)> {
+    let database = TestDatabase::create().await?;
+    let config = common::config(serde_json::json!({
+        "login_ip_threshold": IP_THRESHOLD,
+        "ip_header": ip_header,
+    }));
+    let client = common::client(&config, &database).await;
+
+    Some((database, client))
+}
+
+/// Fails to log in as a user who doesn't exist, claiming to come from the given IP address in `X-Real-IP`.
+async fn fail_login(client: &Client, attempt: u32, claimed_ip_address: &str) -> Status {
+    let remote: SocketAddr = "192.0.2.1:40000".parse().unwrap();
+
+    client
+        .post("/login")
+        .remote(remote)
+        .header(ContentType::JSON)
+        .header(Header::new("X-Real-IP", claimed_ip_address.to_string()))
+        .body(
+            serde_json::json!({ "username": format!("nobody{attempt}"), "password": "wrong" })
+                .to_string(),
+        )
+        .dispatch()
+        .await
+        .status()
+}
+
+#[rocket::async_test]
+async fn ignores_ip_address_header_by_default() {
+    let Some((database, client)) = setup(None).await else {
+        return;
+    };
+
+    for attempt in 0..IP_THRESHOLD {
+        let claimed_ip_address = format!("198.51.100.{attempt}");
+        let status = fail_login(&client, attempt, &claimed_ip_address).await;
+        assert_eq!(status, Status::Unauthorized);
+    }
+
+    let status = fail_login(&client, IP_THRESHOLD, "198.51.100.99").await;
+    assert_eq!(status, Status::TooManyRequests);
+
+    let ip_addresses: Vec<Option<String>> =
+        sqlx::query_scalar("SELECT DISTINCT ip_address FROM login_attempts")
+            .fetch_all(&database.pool)
+            .await
+            .unwrap();
+    assert_eq!(ip_addresses, vec![Some("192.0.2.1".to_string())]);
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn uses_ip_address_header_of_trusted_proxy() {
+    let Some((database, client)) = setup(Some("X-Real-IP")).await else {
+        return;
+    };
+
+    for attempt in 0..=IP_THRESHOLD {
+        let claimed_ip_address = format!("198.51.100.{attempt}");
+        let status = fail_login(&client, attempt, &claimed_ip_address).await;
+        assert_eq!(status, Status::Unauthorized);
+    }
+
+    drop(client);
+    database.drop().await;
+}
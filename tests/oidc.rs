@@ -0,0 +1,474 @@
+//! Logs in through a stub OpenID Connect identity provider that serves discovery, JWKS and token endpoints.
+
+mod common;
+
+use std::net::TcpListener;
+
+use chrono::{Duration, Utc};
+use common::TestDatabase;
+use openidconnect::{
+    AccessToken, Audience, AuthUrl, EmptyAdditionalClaims, EmptyAdditionalProviderMetadata,
+    EmptyExtraTokenFields, EndUserUsername, IssuerUrl, JsonWebKeySetUrl, Nonce, PrivateSigningKey,
+    ResponseTypes, StandardClaims, SubjectIdentifier, TokenUrl,
+    core::{
+        CoreIdToken, CoreIdTokenClaims, CoreIdTokenFields, CoreJsonWebKeySet,
+        CoreJwsSigningAlgorithm, CoreProviderMetadata, CoreResponseType, CoreRsaPrivateSigningKey,
+        CoreSubjectIdentifierType, CoreTokenResponse, CoreTokenType,
+    },
+    url::Url,
+};
+use rasopus::{
+    config::user_service::UserServiceConfig, model::entity::user::Role, service::UserService,
+};
+use rocket::{
+    Config, State,
+    form::Form,
+    get,
+    http::{ContentType, Status},
+    local::asynchronous::Client,
+    post, routes,
+    serde::json::{Json, Value, serde_json},
+};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+const CLIENT_ID: &str = "rasopus";
+const PASSWORD: &str = "meadow lantern pebble";
+const SIGNING_KEY_PEM: &str = include_str!("fixtures/oidc_signing_key.pem");
+
+struct StubProvider {
+    issuer_url: IssuerUrl,
+    signing_key: CoreRsaPrivateSigningKey,
+}
+
+#[get("/.well-known/openid-configuration")]
+fn discovery(provider: &State<StubProvider>) -> Json<CoreProviderMetadata> {
+    let issuer = provider.issuer_url.as_str().trim_end_matches('/');
+    let metadata = CoreProviderMetadata::new(
+        provider.issuer_url.clone(),
+        AuthUrl::new(format!("{issuer}/authorize")).unwrap(),
+        JsonWebKeySetUrl::new(format!("{issuer}/jwks")).unwrap(),
+        vec![ResponseTypes::new(vec![CoreResponseType::Code])],
+        vec![CoreSubjectIdentifierType::Public],
+        vec![CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256],
+        EmptyAdditionalProviderMetadata {},
+    )
+    .set_token_endpoint(Some(TokenUrl::new(format!("{issuer}/token")).unwrap()));
+
+    Json(metadata)
+}
+
+#[get("/jwks")]
+fn jwks(provider: &State<StubProvider>) -> Json<CoreJsonWebKeySet> {
+    Json(CoreJsonWebKeySet::new(vec![
+        provider.signing_key.as_verification_key(),
+    ]))
+}
+
+#[derive(rocket::FromForm)]
+struct TokenRequest {
+    code: String,
+}
+
+/// Issues an ID token for the authorization code. The stub doesn't remember logins, so the code carries the nonce, the
+/// subject and the username, separated by dots.
+#[post("/token", data = "<request>")]
+fn token(provider: &State<StubProvider>, request: Form<TokenRequest>) -> Json<CoreTokenResponse> {
+    let mut parts = request.code.splitn(3, '.');
+    let (Some(nonce), Some(subject), Some(username)) = (parts.next(), parts.next(), parts.next())
+    else {
+        panic!("The authorization code should contain a nonce, a subject and a username");
+    };
+
+    let claims = CoreIdTokenClaims::new(
+        provider.issuer_url.clone(),
+        vec![Audience::new(CLIENT_ID.to_string())],
+        Utc::now() + Duration::minutes(5),
+        Utc::now(),
+        StandardClaims::new(SubjectIdentifier::new(subject.to_string()))
+            .set_preferred_username(Some(EndUserUsername::new(username.to_string()))),
+        EmptyAdditionalClaims {},
+    )
+    .set_nonce(Some(Nonce::new(nonce.to_string())));
+
+    let id_token = CoreIdToken::new(
+        claims,
+        &provider.signing_key,
+        CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256,
+        None,
+        None,
+    )
+    .unwrap();
+
+    Json(CoreTokenResponse::new(
+        AccessToken::new("stub-access-token".to_string()),
+        CoreTokenType::Bearer,
+        CoreIdTokenFields::new(Some(id_token), EmptyExtraTokenFields {}),
+    ))
+}
+
+/// Starts the stub identity provider on a free port and returns its issuer URL.
+async fn start_stub_provider() -> String {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap()
+        .port();
+    let issuer_url = format!("http://127.0.0.1:{port}");
+
+    let config = Config {
+        port,
+        address: [127, 0, 0, 1].into(),
+        log_level: rocket::config::LogLevel::Off,
+        ..Config::debug_default()
+    };
+    let provider = StubProvider {
+        issuer_url: IssuerUrl::new(issuer_url.clone()).unwrap(),
+        signing_key: CoreRsaPrivateSigningKey::from_pem(SIGNING_KEY_PEM, None).unwrap(),
+    };
+    let rocket = rocket::custom(config)
+        .manage(provider)
+        .mount("/", routes![discovery, jwks, token]);
+    tokio::spawn(rocket.launch());
+
+    let discovery_url = format!("{issuer_url}/.well-known/openid-configuration");
+    for _ in 0..50 {
+        if openidconnect::reqwest::get(&discovery_url).await.is_ok() {
+            return issuer_url;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    panic!("The stub identity provider should start");
+}
+
+async fn setup() -> Option<(TestDatabase, Client)> {
+    let database = TestDatabase::create().await?;
+    let issuer_url = start_stub_provider().await;
+    let config = common::config(serde_json::json!({
+        "oidc_issuer_url": issuer_url,
+        "oidc_client_id": CLIENT_ID,
+        "oidc_redirect_url": "http://localhost/login/oidc/callback",
+        "oidc_default_role": "user",
+    }));
+    let client = common::client(&config, &database).await;
+
+    Some((database, client))
+}
+
+/// Logs in through the stub identity provider and returns the status and body of the callback.
+async fn login(client: &Client, subject: &str, username: &str) -> (Status, Value) {
+    authenticate(client, "/login/oidc", subject, username).await
+}
+
+/// Goes through the stub identity provider, starting at the given URI, and returns the status and body of the callback.
+async fn authenticate(
+    client: &Client,
+    uri: &str,
+    subject: &str,
+    username: &str,
+) -> (Status, Value) {
+    let response = client.get(uri.to_string()).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let authorize_url = Url::parse(response.headers().get_one("Location").unwrap()).unwrap();
+    let parameter = |name: &str| {
+        authorize_url
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap()
+    };
+    let code = format!("{}.{}.{}", parameter("nonce"), subject, username);
+    let callback = format!(
+        "/login/oidc/callback?code={}&state={}",
+        code,
+        parameter("state")
+    );
+
+    let response = client.get(callback).dispatch().await;
+    let status = response.status();
+    let body = response.into_json::<Value>().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+async fn post(client: &Client, uri: &str, body: Value) -> (Status, Value) {
+    let response = client
+        .post(uri.to_string())
+        .header(ContentType::JSON)
+        .body(body.to_string())
+        .dispatch()
+        .await;
+    let status = response.status();
+    let body = response.into_json::<Value>().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+fn user_uuid(body: &Value) -> Uuid {
+    body["uuid"].as_str().unwrap().parse().unwrap()
+}
+
+#[rocket::async_test]
+async fn creates_user_on_first_login_and_issues_session() {
+    let Some((database, client)) = setup().await else {
+        return;
+    };
+
+    let (status, body) = login(&client, "subject-1", "alice").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body["created"], true);
+    assert_eq!(body["two_factor_required"], false);
+
+    // The audit log records which session was issued
+    let (session_uuid, audited_session_uuid): (Uuid, String) = sqlx::query_as(
+        "SELECT sessions.uuid, audit_events.after->>'session_uuid' FROM sessions, audit_events WHERE audit_events.action = 'login.succeeded'",
+    )
+    .fetch_one(&database.pool)
+    .await
+    .unwrap();
+    assert_eq!(audited_session_uuid, session_uuid.to_string());
+
+    // Only a logged in user can start enrolling a TOTP
+    let (status, _) = post(&client, "/users/me/totp", Value::Null).await;
+    assert_eq!(status, Status::Ok);
+
+    let (status, second_body) = login(&client, "subject-1", "alice").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(second_body["created"], false);
+    assert_eq!(user_uuid(&second_body), user_uuid(&body));
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn rejects_locked_users() {
+    let Some((database, client)) = setup().await else {
+        return;
+    };
+
+    let (status, body) = login(&client, "subject-2", "bob").await;
+    assert_eq!(status, Status::Ok);
+
+    sqlx::query(
+        "INSERT INTO account_lockouts (uuid, user_uuid, locked_at, locked_until) VALUES ($1, $2, now(), now() + interval '15 minutes')",
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_uuid(&body))
+    .execute(&database.pool)
+    .await
+    .unwrap();
+
+    client.post("/logout").dispatch().await;
+    let (status, body) = login(&client, "subject-2", "bob").await;
+    assert_eq!(status, Status::Locked);
+    assert!(body.get("Locked").is_some());
+
+    let (status, _) = post(&client, "/users/me/totp", Value::Null).await;
+    assert_eq!(status, Status::Unauthorized);
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn leaves_identity_of_locked_users_unlinked() {
+    let Some((database, client)) = setup().await else {
+        return;
+    };
+
+    let config = common::config(serde_json::json!({}));
+    let user_service = UserService::new(UserServiceConfig::from(&config)).unwrap();
+    let user = user_service
+        .generate("frank".to_string(), PASSWORD, Role::User)
+        .await
+        .unwrap();
+    user_service.create(&user, &database.pool).await.unwrap();
+
+    let (status, _) = post(
+        &client,
+        "/login",
+        serde_json::json!({ "username": "frank", "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+
+    sqlx::query(
+        "INSERT INTO account_lockouts (uuid, user_uuid, locked_at, locked_until) VALUES ($1, $2, now(), now() + interval '15 minutes')",
+    )
+    .bind(Uuid::new_v4())
+    .bind(user.uuid)
+    .execute(&database.pool)
+    .await
+    .unwrap();
+
+    let (status, _) = authenticate(&client, "/users/me/oidc", "subject-8", "frank").await;
+    assert_eq!(status, Status::Locked);
+
+    let identities: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_identities")
+        .fetch_one(&database.pool)
+        .await
+        .unwrap();
+    let linked: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM audit_events WHERE action = 'user.identity_linked'",
+    )
+    .fetch_one(&database.pool)
+    .await
+    .unwrap();
+    assert_eq!((identities, linked), (0, 0));
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn requires_second_factor_of_users_with_totp() {
+    let Some((database, client)) = setup().await else {
+        return;
+    };
+
+    let (status, _) = login(&client, "subject-3", "carol").await;
+    assert_eq!(status, Status::Ok);
+
+    let (status, enrollment) = post(&client, "/users/me/totp", Value::Null).await;
+    assert_eq!(status, Status::Ok);
+    let secret = Secret::Encoded(enrollment["secret"].as_str().unwrap().to_string())
+        .to_bytes()
+        .unwrap();
+    let totp = TOTP::new_unchecked(Algorithm::SHA1, 6, 1, 30, secret, None, String::new());
+    let (status, confirmation) = post(
+        &client,
+        "/users/me/totp/confirm",
+        serde_json::json!({ "code": totp.generate_current().unwrap() }),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    let recovery_code = confirmation["recovery_codes"][0].as_str().unwrap();
+
+    client.post("/logout").dispatch().await;
+    let (status, body) = login(&client, "subject-3", "carol").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body["two_factor_required"], true);
+
+    // The session only starts once the second factor was entered
+    let (status, _) = post(&client, "/users/me/totp", Value::Null).await;
+    assert_eq!(status, Status::Unauthorized);
+
+    let (status, _) = post(
+        &client,
+        "/login/two-factor",
+        serde_json::json!({ "code": recovery_code }),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+
+    let (status, _) = post(
+        &client,
+        "/users/me/totp/disable",
+        serde_json::json!({ "code": "000000" }),
+    )
+    .await;
+    assert_eq!(status, Status::UnprocessableEntity);
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn rejects_usernames_of_other_users() {
+    let Some((database, client)) = setup().await else {
+        return;
+    };
+
+    let (status, _) = login(&client, "subject-4", "dave").await;
+    assert_eq!(status, Status::Ok);
+
+    client.post("/logout").dispatch().await;
+    let (status, _) = login(&client, "subject-5", "Dave").await;
+    assert_eq!(status, Status::Conflict);
+
+    let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(&database.pool)
+        .await
+        .unwrap();
+    let identities: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_identities")
+        .fetch_one(&database.pool)
+        .await
+        .unwrap();
+    assert_eq!((users, identities), (1, 1));
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn rolls_back_created_user_when_linking_fails() {
+    let Some((database, client)) = setup().await else {
+        return;
+    };
+
+    sqlx::query(
+        "CREATE FUNCTION fail_linking() RETURNS trigger AS $$ BEGIN RAISE EXCEPTION 'linking failed'; END $$ LANGUAGE plpgsql",
+    )
+    .execute(&database.pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "CREATE TRIGGER fail_linking BEFORE INSERT ON user_identities FOR EACH ROW EXECUTE FUNCTION fail_linking()",
+    )
+    .execute(&database.pool)
+    .await
+    .unwrap();
+
+    let (status, _) = login(&client, "subject-6", "erin").await;
+    assert_eq!(status, Status::InternalServerError);
+
+    let users: Vec<String> = sqlx::query_scalar("SELECT username FROM users")
+        .fetch_all(&database.pool)
+        .await
+        .unwrap();
+    assert!(users.is_empty());
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn replaces_user_in_trash_when_identity_logs_in_again() {
+    let Some((database, client)) = setup().await else {
+        return;
+    };
+
+    let (status, body) = login(&client, "subject-7", "erin").await;
+    assert_eq!(status, Status::Ok);
+    let deleted_uuid = user_uuid(&body);
+
+    sqlx::query("UPDATE users SET deleted_at = now() WHERE uuid = $1")
+        .bind(deleted_uuid)
+        .execute(&database.pool)
+        .await
+        .unwrap();
+
+    client.post("/logout").dispatch().await;
+    let (status, body) = login(&client, "subject-7", "erin").await;
+    assert_eq!(status, Status::Ok);
+    assert_ne!(user_uuid(&body), deleted_uuid);
+
+    let users: Vec<(String, bool)> =
+        sqlx::query_as("SELECT username, deleted_at IS NOT NULL FROM users")
+            .fetch_all(&database.pool)
+            .await
+            .unwrap();
+    assert_eq!(users, vec![("erin".to_string(), false)]);
+
+    let purged: Vec<Uuid> =
+        sqlx::query_scalar("SELECT target_uuid FROM audit_events WHERE action = 'user.purged'")
+            .fetch_all(&database.pool)
+            .await
+            .unwrap();
+    assert_eq!(purged, vec![deleted_uuid]);
+
+    drop(client);
+    database.drop().await;
+}
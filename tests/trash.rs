@@ -0,0 +1,242 @@
+//! Moves users to the trash and restores them through the API.
+
+mod common;
+
+use common::TestDatabase;
+use rasopus::{
+    config::{trash_service::TrashServiceConfig, user_service::UserServiceConfig},
+    model::entity::user::{Role, User},
+    service::{AuditService, TrashService, UserService, user::PersistError},
+};
+use rocket::{
+    http::{ContentType, Status},
+    local::asynchronous::Client,
+    serde::json::{Value, serde_json},
+};
+
+const PASSWORD: &str = "meadow lantern pebble";
+
+struct Users {
+    alice: User,
+    bob: User,
+    carol: User,
+    dave: User,
+}
+
+fn user_service() -> UserService {
+    let config = common::config(serde_json::json!({}));
+    UserService::new(UserServiceConfig::from(&config)).unwrap()
+}
+
+async fn setup() -> Option<(TestDatabase, Client, Users)> {
+    let database = TestDatabase::create().await?;
+    let config = common::config(serde_json::json!({}));
+
+    let user_service = user_service();
+    let create = async |username: &str, role: Role| {
+        let user = user_service
+            .generate(username.to_string(), PASSWORD, role)
+            .await
+            .unwrap();
+        user_service.create(&user, &database.pool).await.unwrap();
+        user
+    };
+    let users = Users {
+        alice: create("alice", Role::Admin).await,
+        bob: create("bob", Role::Admin).await,
+        carol: create("carol", Role::User).await,
+        dave: create("dave", Role::User).await,
+    };
+
+    let client = common::client(&config, &database).await;
+
+    Some((database, client, users))
+}
+
+async fn login(client: &Client, username: &str) {
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(serde_json::json!({ "username": username, "password": PASSWORD }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+}
+
+async fn get(client: &Client, uri: &str) -> Value {
+    let response = client.get(uri).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    response.into_json().await.unwrap()
+}
+
+async fn delete(client: &Client, uri: &str) -> Status {
+    client.delete(uri).dispatch().await.status()
+}
+
+async fn post(client: &Client, uri: &str) -> Status {
+    client.post(uri).dispatch().await.status()
+}
+
+fn usernames(page: &Value) -> Vec<&str> {
+    page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|user| user["username"].as_str().unwrap())
+        .collect()
+}
+
+#[rocket::async_test]
+async fn lists_trash_of_requesting_user() {
+    let Some((database, client, users)) = setup().await else {
+        return;
+    };
+
+    login(&client, "alice").await;
+    assert_eq!(
+        delete(&client, &format!("/users/{}", users.carol.uuid)).await,
+        Status::Ok
+    );
+    client.post("/logout").dispatch().await;
+
+    login(&client, "bob").await;
+    assert_eq!(
+        delete(&client, &format!("/users/{}", users.dave.uuid)).await,
+        Status::Ok
+    );
+    client.post("/logout").dispatch().await;
+
+    login(&client, "alice").await;
+    let page = get(&client, "/users/me/trash").await;
+    assert_eq!(usernames(&page), vec!["carol"]);
+    assert_eq!(
+        page["items"][0]["deleted_by"],
+        serde_json::json!(users.alice.uuid)
+    );
+
+    let page = get(&client, "/admin/users/trash?sort=username").await;
+    assert_eq!(usernames(&page), vec!["carol", "dave"]);
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn restores_user_and_records_it() {
+    let Some((database, client, users)) = setup().await else {
+        return;
+    };
+
+    login(&client, "alice").await;
+    assert_eq!(
+        delete(&client, &format!("/users/{}", users.carol.uuid)).await,
+        Status::Ok
+    );
+
+    assert_eq!(
+        post(&client, &format!("/users/{}/restore", users.carol.uuid)).await,
+        Status::Ok
+    );
+
+    let page = get(&client, "/users/me/trash").await;
+    assert!(usernames(&page).is_empty());
+
+    let page = get(&client, "/admin/users?sort=username").await;
+    assert_eq!(usernames(&page), vec!["alice", "bob", "carol", "dave"]);
+    assert_eq!(page["items"][2]["deleted_by"], Value::Null);
+
+    let restored: Vec<(uuid::Uuid, uuid::Uuid)> = sqlx::query_as(
+        "SELECT actor_uuid, target_uuid FROM audit_events WHERE action = 'user.restored'",
+    )
+    .fetch_all(&database.pool)
+    .await
+    .unwrap();
+    assert_eq!(restored, vec![(users.alice.uuid, users.carol.uuid)]);
+
+    assert_eq!(
+        post(&client, &format!("/users/{}/restore", users.bob.uuid)).await,
+        Status::NotFound
+    );
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn leaves_persisted_user_in_trash_unchanged() {
+    let Some((database, client, users)) = setup().await else {
+        return;
+    };
+
+    login(&client, "alice").await;
+    assert_eq!(
+        delete(&client, &format!("/users/{}", users.carol.uuid)).await,
+        Status::Ok
+    );
+
+    let mut carol = users.carol;
+    carol.role = Role::Admin;
+    assert!(matches!(
+        user_service().persist(carol, &database.pool).await,
+        Err(PersistError::Deleted)
+    ));
+
+    let (role, deleted): (i16, bool) =
+        sqlx::query_as("SELECT role, deleted_at IS NOT NULL FROM users WHERE username = 'carol'")
+            .fetch_one(&database.pool)
+            .await
+            .unwrap();
+    assert_eq!((role, deleted), (Role::User as i16, true));
+
+    drop(client);
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn purges_expired_user_and_records_it() {
+    let Some((database, client, users)) = setup().await else {
+        return;
+    };
+
+    login(&client, "alice").await;
+    assert_eq!(
+        delete(&client, &format!("/users/{}", users.carol.uuid)).await,
+        Status::Ok
+    );
+    assert_eq!(
+        delete(&client, &format!("/users/{}", users.dave.uuid)).await,
+        Status::Ok
+    );
+
+    // Only carol has been in the trash for longer than the retention
+    sqlx::query("UPDATE users SET deleted_at = '2000-01-01T00:00:00Z' WHERE uuid = $1")
+        .bind(users.carol.uuid)
+        .execute(&database.pool)
+        .await
+        .unwrap();
+
+    let config = common::config(serde_json::json!({}));
+    let trash_service = TrashService::new(TrashServiceConfig::from(&config));
+    let purged = trash_service
+        .purge_expired(&AuditService::new(), &database.pool)
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+
+    let page = get(&client, "/admin/users/trash").await;
+    assert_eq!(usernames(&page), vec!["dave"]);
+
+    let purged: Vec<(Option<uuid::Uuid>, uuid::Uuid, Value)> = sqlx::query_as(
+        "SELECT actor_uuid, target_uuid, before->'username' FROM audit_events WHERE action = 'user.purged'",
+    )
+    .fetch_all(&database.pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        purged,
+        vec![(None, users.carol.uuid, serde_json::json!("carol"))]
+    );
+
+    drop(client);
+    database.drop().await;
+}
@@ -0,0 +1,89 @@
+//! Enrolls users in TOTP second factors.
+
+mod common;
+
+use common::TestDatabase;
+use rasopus::{
+    config::{two_factor_service::TwoFactorServiceConfig, user_service::UserServiceConfig},
+    model::entity::user::{Role, User},
+    service::{TwoFactorService, UserService, two_factor::EnrollError},
+};
+use rocket::serde::json::serde_json;
+
+const PASSWORD: &str = "meadow lantern pebble";
+
+async fn setup() -> Option<(TestDatabase, TwoFactorService, User)> {
+    let database = TestDatabase::create().await?;
+    let config = common::config(serde_json::json!({}));
+
+    let user_service = UserService::new(UserServiceConfig::from(&config)).unwrap();
+    let user = user_service
+        .generate("alice".to_string(), PASSWORD, Role::User)
+        .await
+        .unwrap();
+    user_service.create(&user, &database.pool).await.unwrap();
+
+    let two_factor_service = TwoFactorService::new(TwoFactorServiceConfig::from(&config)).unwrap();
+
+    Some((database, two_factor_service, user))
+}
+
+async fn encrypted_secret(database: &TestDatabase, user: &User) -> Vec<u8> {
+    sqlx::query_scalar("SELECT encrypted_secret FROM user_totps WHERE user_uuid = $1")
+        .bind(user.uuid)
+        .fetch_one(&database.pool)
+        .await
+        .unwrap()
+}
+
+#[rocket::async_test]
+async fn replaces_unconfirmed_enrollment() {
+    let Some((database, two_factor_service, user)) = setup().await else {
+        return;
+    };
+
+    let first = two_factor_service
+        .begin_enrollment(&user, &database.pool)
+        .await
+        .unwrap();
+    let first_secret = encrypted_secret(&database, &user).await;
+
+    let second = two_factor_service
+        .begin_enrollment(&user, &database.pool)
+        .await
+        .unwrap();
+    assert_ne!(first.secret, second.secret);
+    assert_ne!(encrypted_secret(&database, &user).await, first_secret);
+
+    database.drop().await;
+}
+
+#[rocket::async_test]
+async fn keeps_confirmed_enrollment() {
+    let Some((database, two_factor_service, user)) = setup().await else {
+        return;
+    };
+
+    two_factor_service
+        .begin_enrollment(&user, &database.pool)
+        .await
+        .unwrap();
+
+    // Stands in for a confirmation that completes while another enrollment is being prepared
+    sqlx::query("UPDATE user_totps SET confirmed = TRUE WHERE user_uuid = $1")
+        .bind(user.uuid)
+        .execute(&database.pool)
+        .await
+        .unwrap();
+    let confirmed_secret = encrypted_secret(&database, &user).await;
+
+    assert!(matches!(
+        two_factor_service
+            .begin_enrollment(&user, &database.pool)
+            .await,
+        Err(EnrollError::AlreadyEnrolled)
+    ));
+    assert_eq!(encrypted_secret(&database, &user).await, confirmed_secret);
+
+    database.drop().await;
+}